@@ -0,0 +1,14 @@
+-- The seed admin shipped with a publicly known password. It is only removed
+-- while it still has that password, installs which changed it keep the account.
+DELETE FROM idempotency
+WHERE user_id IN (
+	SELECT user_id FROM users
+	WHERE
+		user_id = 'ddf8994f-d522-4659-8d02-c1d479057be6' AND
+		password_hash = '$argon2id$v=19$m=1500,t=2,p=1$Nw5PiOSB089EWO7S0oDQBA$jyprKkiSpKzATlyVrz96GmmdtE3hUyHb1a2PxgfyeQw'
+);
+
+DELETE FROM users
+WHERE
+	user_id = 'ddf8994f-d522-4659-8d02-c1d479057be6' AND
+	password_hash = '$argon2id$v=19$m=1500,t=2,p=1$Nw5PiOSB089EWO7S0oDQBA$jyprKkiSpKzATlyVrz96GmmdtE3hUyHb1a2PxgfyeQw';
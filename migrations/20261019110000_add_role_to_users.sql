@@ -0,0 +1,4 @@
+-- Every existing user could do everything, keep it that way.
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'owner';
+ALTER TABLE users ALTER COLUMN role DROP DEFAULT;
+ALTER TABLE users ADD CONSTRAINT users_role_check CHECK (role IN ('owner', 'editor', 'viewer'));
@@ -0,0 +1,12 @@
+ALTER TABLE users ADD COLUMN is_active BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE users ADD COLUMN email TEXT NULL UNIQUE;
+
+CREATE TABLE user_invitations (
+	invitation_token_hash TEXT NOT NULL,
+	email TEXT NOT NULL,
+	role TEXT NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+	invited_by uuid NULL REFERENCES users(user_id) ON DELETE SET NULL,
+	created_at timestamptz NOT NULL,
+	expires_at timestamptz NOT NULL,
+	PRIMARY KEY(invitation_token_hash)
+);
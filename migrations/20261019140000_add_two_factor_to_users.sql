@@ -0,0 +1,8 @@
+ALTER TABLE users ADD COLUMN totp_secret TEXT NULL;
+ALTER TABLE users ADD COLUMN totp_last_used_step BIGINT NULL;
+
+CREATE TABLE recovery_codes (
+	user_id uuid NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+	code_hash TEXT NOT NULL,
+	PRIMARY KEY(user_id, code_hash)
+);
@@ -0,0 +1,7 @@
+CREATE TABLE login_throttles (
+	throttle_key TEXT NOT NULL,
+	failures INT NOT NULL,
+	last_failure_at timestamptz NOT NULL,
+	locked_until timestamptz NULL,
+	PRIMARY KEY(throttle_key)
+);
@@ -0,0 +1,10 @@
+-- Issues start as drafts and are only delivered once published.
+ALTER TABLE newsletter_issues
+	ALTER COLUMN published_at TYPE timestamptz USING published_at::timestamptz,
+	ALTER COLUMN published_at DROP NOT NULL;
+ALTER TABLE newsletter_issues ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
+ALTER TABLE newsletter_issues ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now();
+-- Delivery progress. Tasks are removed from the queue once settled, so we keep counts here.
+ALTER TABLE newsletter_issues ADD COLUMN n_recipients INT NOT NULL DEFAULT 0;
+ALTER TABLE newsletter_issues ADD COLUMN n_delivered INT NOT NULL DEFAULT 0;
+ALTER TABLE newsletter_issues ADD COLUMN n_failed INT NOT NULL DEFAULT 0;
@@ -0,0 +1,20 @@
+CREATE TABLE subscriber_imports (
+	import_id uuid NOT NULL,
+	user_id uuid NULL REFERENCES users(user_id) ON DELETE SET NULL,
+	file_name TEXT NOT NULL,
+	mode TEXT NOT NULL,
+	created_at timestamptz NOT NULL,
+	n_imported INT NOT NULL DEFAULT 0,
+	n_rejected INT NOT NULL DEFAULT 0,
+	n_unsent INT NOT NULL DEFAULT 0,
+	PRIMARY KEY(import_id)
+);
+
+CREATE TABLE subscriber_import_rejections (
+	import_id uuid NOT NULL REFERENCES subscriber_imports(import_id) ON DELETE CASCADE,
+	csv_row BIGINT NOT NULL,
+	email TEXT NOT NULL,
+	name TEXT NOT NULL,
+	reason TEXT NOT NULL,
+	PRIMARY KEY(import_id, csv_row)
+);
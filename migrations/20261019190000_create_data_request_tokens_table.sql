@@ -0,0 +1,9 @@
+CREATE TABLE data_request_tokens (
+	token_hash TEXT NOT NULL,
+	email TEXT NOT NULL,
+	kind TEXT NOT NULL,
+	created_at timestamptz NOT NULL,
+	expires_at timestamptz NOT NULL,
+	PRIMARY KEY(token_hash)
+);
+CREATE INDEX data_request_tokens_email_idx ON data_request_tokens (email);
@@ -0,0 +1,11 @@
+CREATE TABLE consent_events (
+	consent_event_id uuid NOT NULL,
+	subscriber_id uuid NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+	event TEXT NOT NULL,
+	source TEXT NULL,
+	ip_address TEXT NULL,
+	user_agent TEXT NULL,
+	occurred_at timestamptz NOT NULL,
+	PRIMARY KEY(consent_event_id)
+);
+CREATE INDEX consent_events_subscriber_id_idx ON consent_events (subscriber_id);
@@ -0,0 +1,12 @@
+CREATE TABLE audit_log (
+	audit_log_id uuid NOT NULL,
+	-- No foreign key, entries outlive the users who made them.
+	user_id uuid NOT NULL,
+	action TEXT NOT NULL,
+	target TEXT NULL,
+	ip_address TEXT NULL,
+	user_agent TEXT NULL,
+	occurred_at timestamptz NOT NULL,
+	PRIMARY KEY(audit_log_id)
+);
+CREATE INDEX audit_log_occurred_at_idx ON audit_log (occurred_at);
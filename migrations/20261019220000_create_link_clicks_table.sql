@@ -0,0 +1,12 @@
+-- Issues can opt out of click tracking. Existing issues have already been delivered.
+ALTER TABLE newsletter_issues ADD COLUMN track_clicks BOOLEAN NOT NULL DEFAULT TRUE;
+CREATE TABLE link_clicks (
+	link_click_id uuid NOT NULL,
+	newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+	subscriber_id uuid NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+	url TEXT NOT NULL,
+	clicked_at timestamptz NOT NULL,
+	PRIMARY KEY(link_click_id)
+);
+CREATE INDEX link_clicks_newsletter_issue_id_idx ON link_clicks (newsletter_issue_id);
+CREATE INDEX link_clicks_subscriber_id_idx ON link_clicks (subscriber_id);
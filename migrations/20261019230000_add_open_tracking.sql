@@ -0,0 +1,13 @@
+-- Issues can opt out of open tracking, and subscribers out of any tracking.
+ALTER TABLE newsletter_issues ADD COLUMN track_opens BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE subscriptions ADD COLUMN do_not_track BOOLEAN NOT NULL DEFAULT FALSE;
+-- A single row per recipient, counting every time the issue is opened.
+CREATE TABLE email_opens (
+	newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+	subscriber_id uuid NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+	first_opened_at timestamptz NOT NULL,
+	last_opened_at timestamptz NOT NULL,
+	n_opens INT NOT NULL,
+	PRIMARY KEY(newsletter_issue_id, subscriber_id)
+);
+CREATE INDEX email_opens_subscriber_id_idx ON email_opens (subscriber_id);
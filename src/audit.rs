@@ -0,0 +1,250 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::consent::RequestMetadata;
+
+/// A privileged action, recorded with the user who performed it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditAction {
+    LogIn,
+    LogOut,
+    ChangePassword,
+    ResetPassword,
+    EnableTwoFactor,
+    DisableTwoFactor,
+    CreateApiToken,
+    RevokeApiToken,
+    PublishIssue,
+    CreateSubscriber,
+    UpdateSubscriber,
+    ResendConfirmation,
+    UnsubscribeSubscriber,
+    DeleteSubscriber,
+    ImportSubscribers,
+    ExportSubscribers,
+    ExportPersonalData,
+    ErasePersonalData,
+    InviteUser,
+    DeactivateUser,
+    ReactivateUser,
+    DeleteUser,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 22] = [
+        AuditAction::LogIn,
+        AuditAction::LogOut,
+        AuditAction::ChangePassword,
+        AuditAction::ResetPassword,
+        AuditAction::EnableTwoFactor,
+        AuditAction::DisableTwoFactor,
+        AuditAction::CreateApiToken,
+        AuditAction::RevokeApiToken,
+        AuditAction::PublishIssue,
+        AuditAction::CreateSubscriber,
+        AuditAction::UpdateSubscriber,
+        AuditAction::ResendConfirmation,
+        AuditAction::UnsubscribeSubscriber,
+        AuditAction::DeleteSubscriber,
+        AuditAction::ImportSubscribers,
+        AuditAction::ExportSubscribers,
+        AuditAction::ExportPersonalData,
+        AuditAction::ErasePersonalData,
+        AuditAction::InviteUser,
+        AuditAction::DeactivateUser,
+        AuditAction::ReactivateUser,
+        AuditAction::DeleteUser,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::LogIn => "log_in",
+            AuditAction::LogOut => "log_out",
+            AuditAction::ChangePassword => "change_password",
+            AuditAction::ResetPassword => "reset_password",
+            AuditAction::EnableTwoFactor => "enable_two_factor",
+            AuditAction::DisableTwoFactor => "disable_two_factor",
+            AuditAction::CreateApiToken => "create_api_token",
+            AuditAction::RevokeApiToken => "revoke_api_token",
+            AuditAction::PublishIssue => "publish_issue",
+            AuditAction::CreateSubscriber => "create_subscriber",
+            AuditAction::UpdateSubscriber => "update_subscriber",
+            AuditAction::ResendConfirmation => "resend_confirmation",
+            AuditAction::UnsubscribeSubscriber => "unsubscribe_subscriber",
+            AuditAction::DeleteSubscriber => "delete_subscriber",
+            AuditAction::ImportSubscribers => "import_subscribers",
+            AuditAction::ExportSubscribers => "export_subscribers",
+            AuditAction::ExportPersonalData => "export_personal_data",
+            AuditAction::ErasePersonalData => "erase_personal_data",
+            AuditAction::InviteUser => "invite_user",
+            AuditAction::DeactivateUser => "deactivate_user",
+            AuditAction::ReactivateUser => "reactivate_user",
+            AuditAction::DeleteUser => "delete_user",
+        }
+    }
+}
+
+impl TryFrom<String> for AuditAction {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        AuditAction::ALL
+            .into_iter()
+            .find(|action| action.as_str() == value)
+            .ok_or_else(|| format!("{} is not a recorded action.", value))
+    }
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+/// What an action was performed on. Subscribers are referred to by id rather than
+/// email, so the log does not keep personal data around after an erasure.
+#[derive(Clone, Copy, Debug)]
+pub enum AuditTarget {
+    User(Uuid),
+    Issue(Uuid),
+    Subscriber(Uuid),
+    SubscriberImport(Uuid),
+    ApiToken(Uuid),
+}
+
+impl std::fmt::Display for AuditTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditTarget::User(id) => write!(f, "user {}", id),
+            AuditTarget::Issue(id) => write!(f, "issue {}", id),
+            AuditTarget::Subscriber(id) => write!(f, "subscriber {}", id),
+            AuditTarget::SubscriberImport(id) => write!(f, "subscriber import {}", id),
+            AuditTarget::ApiToken(id) => write!(f, "API token {}", id),
+        }
+    }
+}
+
+pub struct AuditEntry {
+    pub user_id: Uuid,
+    /// `None` once the user has been deleted.
+    pub username: Option<String>,
+    pub action: String,
+    pub target: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Takes an executor so the entry can be written in the transaction of the action itself.
+#[tracing::instrument(name = "Record audit event", skip(executor, metadata))]
+pub async fn record_audit_event<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    user_id: Uuid,
+    action: AuditAction,
+    target: Option<AuditTarget>,
+    metadata: &RequestMetadata,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO audit_log (
+            audit_log_id,
+            user_id,
+            action,
+            target,
+            ip_address,
+            user_agent,
+            occurred_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, now())
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        action.as_str(),
+        target.map(|target| target.to_string()),
+        metadata.ip_address,
+        metadata.user_agent
+    );
+    executor
+        .execute(query)
+        .await
+        .context("Failed to record an audit event")?;
+
+    Ok(())
+}
+
+/// Most recent entries first. `None` matches every action.
+#[tracing::instrument(name = "List audit log", skip(pool))]
+pub async fn list_audit_log(
+    action: Option<AuditAction>,
+    limit: i64,
+    offset: i64,
+    pool: &PgPool,
+) -> Result<Vec<AuditEntry>, anyhow::Error> {
+    sqlx::query_as!(
+        AuditEntry,
+        r#"
+        SELECT
+            a.user_id,
+            u.username AS "username?",
+            a.action,
+            a.target,
+            a.ip_address,
+            a.user_agent,
+            a.occurred_at
+        FROM audit_log a
+        LEFT JOIN users u ON u.user_id = a.user_id
+        WHERE $1::text IS NULL OR a.action = $1
+        ORDER BY a.occurred_at DESC, a.audit_log_id
+        LIMIT $2
+        OFFSET $3
+        "#,
+        action.map(|action| action.as_str()),
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to list the audit log")
+}
+
+#[tracing::instrument(name = "Count audit log entries", skip(pool))]
+pub async fn count_audit_log(
+    action: Option<AuditAction>,
+    pool: &PgPool,
+) -> Result<i64, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM audit_log
+        WHERE $1::text IS NULL OR action = $1
+        "#,
+        action.map(|action| action.as_str())
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform a query to count audit log entries")?;
+
+    Ok(row.count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AuditAction;
+
+    #[test]
+    fn actions_round_trip_through_their_string_representation() {
+        for action in AuditAction::ALL {
+            assert_eq!(
+                AuditAction::try_from(action.as_str().to_string()),
+                Ok(action)
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_actions_are_rejected() {
+        assert!(AuditAction::try_from("publish".to_string()).is_err());
+    }
+}
@@ -1,5 +1,37 @@
+mod api_tokens;
+mod invitations;
 mod middleware;
 mod password;
+mod password_reset;
+mod permissions;
+mod throttle;
+mod two_factor;
+mod users;
 
-pub use middleware::{UserId, reject_anonymous_users};
+pub use api_tokens::{
+    ApiTokenScopes, ApiTokenSummary, Scope, create_api_token, list_api_tokens, require_scope,
+    revoke_api_token,
+};
+pub use invitations::{
+    PendingInvitation, accept_invitation, create_invitation, get_pending_invitation,
+    list_pending_invitations,
+};
+pub use middleware::{UserId, reject_anonymous_api_clients, reject_anonymous_users};
 pub use password::{AuthError, Credentials, change_password, validate_credentials};
+pub use password_reset::{
+    PasswordResetRequest, consume_password_reset_token, create_password_reset_token,
+    password_reset_token_is_valid,
+};
+pub use permissions::{Permission, Role, require_permission};
+pub use throttle::{
+    clear_login_failures, login_lockout, password_reset_lockout, record_login_failure,
+    record_password_reset_request,
+};
+pub use two_factor::{
+    TotpEnrollment, disable_two_factor, enable_two_factor, generate_totp_secret,
+    two_factor_enabled, verify_enrollment_code, verify_second_factor,
+};
+pub use users::{
+    CreateUserError, UpdateUserError, UserSummary, create_first_user, create_user, delete_user,
+    get_role, get_user_id, list_users, set_user_active, users_exist,
+};
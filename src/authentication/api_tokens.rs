@@ -0,0 +1,244 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::middleware::Next;
+use actix_web::{HttpMessage, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::{discard_payload, generate_token, hash_token};
+
+/// Prefix making our tokens easy to recognise, e.g. by secret scanners.
+const TOKEN_PREFIX: &str = "nlt_";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    IssuesRead,
+    IssuesWrite,
+    SubscribersRead,
+    SubscribersWrite,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [
+        Self::IssuesRead,
+        Self::IssuesWrite,
+        Self::SubscribersRead,
+        Self::SubscribersWrite,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::IssuesRead => "issues:read",
+            Self::IssuesWrite => "issues:write",
+            Self::SubscribersRead => "subscribers:read",
+            Self::SubscribersWrite => "subscribers:write",
+        }
+    }
+}
+
+impl TryFrom<String> for Scope {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == value)
+            .ok_or_else(|| format!("{} is not a supported scope.", value))
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+/// Scopes granted to the API token which authenticated the current request.
+/// Absent when the request was authenticated with a session cookie, which is
+/// not restricted to any scope.
+#[derive(Clone, Debug)]
+pub struct ApiTokenScopes(Vec<Scope>);
+
+impl ApiTokenScopes {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.0.contains(&scope)
+    }
+}
+
+pub struct ApiTokenSummary {
+    pub api_token_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Creates a token acting on behalf of `user_id` and returns it. Only a hash of
+/// the token is kept in the database, so it can't be shown again.
+#[tracing::instrument(name = "Create API token", skip(pool))]
+pub async fn create_api_token(
+    user_id: Uuid,
+    name: &str,
+    scopes: &[Scope],
+    expires_at: Option<DateTime<Utc>>,
+    pool: &PgPool,
+) -> Result<String, anyhow::Error> {
+    let token = format!("{}{}", TOKEN_PREFIX, generate_token());
+    let scopes: Vec<_> = scopes.iter().map(|s| s.as_str().to_string()).collect();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO api_tokens
+            (api_token_id, user_id, name, token_hash, scopes, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        name,
+        hash_token(&token),
+        &scopes,
+        Utc::now(),
+        expires_at
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store a new API token")?;
+
+    Ok(token)
+}
+
+#[tracing::instrument(name = "List API tokens", skip(pool))]
+pub async fn list_api_tokens(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Vec<ApiTokenSummary>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT api_token_id, name, scopes, created_at, expires_at, last_used_at
+        FROM api_tokens
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to list API tokens")?;
+
+    rows.into_iter()
+        .map(|r| {
+            Ok(ApiTokenSummary {
+                api_token_id: r.api_token_id,
+                name: r.name,
+                scopes: parse_scopes(r.scopes)?,
+                created_at: r.created_at,
+                expires_at: r.expires_at,
+                last_used_at: r.last_used_at,
+            })
+        })
+        .collect()
+}
+
+/// Deletes a token of `user_id`. Returns `false` if they have no such token.
+#[tracing::instrument(name = "Revoke API token", skip(pool))]
+pub async fn revoke_api_token(
+    user_id: Uuid,
+    api_token_id: Uuid,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"DELETE FROM api_tokens WHERE api_token_id = $1 AND user_id = $2"#,
+        api_token_id,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to delete an API token")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Resolves a bearer token to the user it acts for, if it is valid, has not
+/// expired and belongs to an active user. Records when it was last used.
+#[tracing::instrument(name = "Authenticate API token", skip(token, pool))]
+pub(super) async fn authenticate_api_token(
+    token: &str,
+    pool: &PgPool,
+) -> Result<Option<(Uuid, ApiTokenScopes)>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE api_tokens
+        SET last_used_at = now()
+        FROM users
+        WHERE api_tokens.token_hash = $1
+            AND users.user_id = api_tokens.user_id
+            AND users.is_active
+            AND (api_tokens.expires_at IS NULL OR api_tokens.expires_at > now())
+        RETURNING api_tokens.user_id, api_tokens.scopes
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to authenticate an API token")?;
+
+    row.map(|r| Ok((r.user_id, ApiTokenScopes(parse_scopes(r.scopes)?))))
+        .transpose()
+}
+
+fn parse_scopes(scopes: Vec<String>) -> Result<Vec<Scope>, anyhow::Error> {
+    scopes
+        .into_iter()
+        .map(|s| Scope::try_from(s).map_err(anyhow::Error::msg))
+        .collect()
+}
+
+/// Rejects requests authenticated with an API token lacking `scope`.
+/// Must run after [`super::reject_anonymous_api_clients`].
+pub async fn require_scope(
+    scope: Scope,
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let allowed = req
+        .extensions()
+        .get::<ApiTokenScopes>()
+        .is_none_or(|scopes| scopes.allows(scope));
+
+    if allowed {
+        next.call(req).await
+    } else {
+        discard_payload(&mut req).await;
+        let e = anyhow::anyhow!("The API token does not grant the {} scope", scope);
+        Err(InternalError::from_response(e, HttpResponse::Forbidden().finish()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiTokenScopes, Scope};
+
+    #[test]
+    fn scopes_round_trip_through_their_string_representation() {
+        for scope in Scope::ALL {
+            assert_eq!(Scope::try_from(scope.as_str().to_string()), Ok(scope));
+        }
+    }
+
+    #[test]
+    fn unknown_scopes_are_rejected() {
+        assert!(Scope::try_from("issues:delete".to_string()).is_err());
+    }
+
+    #[test]
+    fn tokens_only_allow_their_own_scopes() {
+        let scopes = ApiTokenScopes(vec![Scope::IssuesRead]);
+
+        assert!(scopes.allows(Scope::IssuesRead));
+        assert!(!scopes.allows(Scope::IssuesWrite));
+    }
+}
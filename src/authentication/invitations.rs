@@ -0,0 +1,168 @@
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::{AdminPassword, SubscriberEmail};
+use crate::utils::{generate_token, hash_token};
+
+use super::permissions::Role;
+use super::users::{CreateUserError, hash_password, insert_user};
+
+/// How long an invitation link can be used for.
+const INVITATION_VALIDITY: Duration = Duration::days(3);
+
+pub struct PendingInvitation {
+    pub email: String,
+    pub role: Role,
+}
+
+/// Stores a new invitation and returns the token to send to the invitee.
+/// Only a hash of the token is kept in the database.
+#[tracing::instrument(name = "Create user invitation", skip(transaction))]
+pub async fn create_invitation(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &SubscriberEmail,
+    role: Role,
+    invited_by: Uuid,
+) -> Result<String, CreateUserError> {
+    let email_taken = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM users WHERE email = $1) AS "exists!""#,
+        email.as_ref()
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .context("Failed to perform a query to check if an email address is in use")?
+    .exists;
+    if email_taken {
+        return Err(CreateUserError::EmailTaken(email.as_ref().into()));
+    }
+
+    let token = generate_token();
+    let now = Utc::now();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_invitations
+            (invitation_token_hash, email, role, invited_by, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        hash_token(&token),
+        email.as_ref(),
+        role.as_str(),
+        invited_by,
+        now,
+        now + INVITATION_VALIDITY
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to store a new user invitation")?;
+
+    Ok(token)
+}
+
+/// Returns the invitation matching `token`, unless it does not exist, has
+/// already been used or has expired.
+#[tracing::instrument(name = "Get pending invitation", skip(token, pool))]
+pub async fn get_pending_invitation(
+    token: &str,
+    pool: &PgPool,
+) -> Result<Option<PendingInvitation>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT email, role
+        FROM user_invitations
+        WHERE invitation_token_hash = $1 AND expires_at > now()
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve an invitation")?;
+
+    row.map(|r| {
+        Ok(PendingInvitation {
+            email: r.email,
+            role: Role::try_from(r.role).map_err(anyhow::Error::msg)?,
+        })
+    })
+    .transpose()
+}
+
+/// Lists the invitations which have been neither accepted nor expired yet.
+#[tracing::instrument(name = "List pending invitations", skip(pool))]
+pub async fn list_pending_invitations(
+    pool: &PgPool,
+) -> Result<Vec<PendingInvitation>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT email, role
+        FROM user_invitations
+        WHERE expires_at > now()
+        ORDER BY created_at
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to list pending invitations")?;
+
+    rows.into_iter()
+        .map(|r| {
+            Ok(PendingInvitation {
+                email: r.email,
+                role: Role::try_from(r.role).map_err(anyhow::Error::msg)?,
+            })
+        })
+        .collect()
+}
+
+/// Creates the invited user and consumes the invitation, so that its link
+/// cannot be used again. Returns `None` if the invitation is no longer valid.
+#[tracing::instrument(name = "Accept user invitation", skip(token, password, pool))]
+pub async fn accept_invitation(
+    token: &str,
+    username: &str,
+    password: AdminPassword,
+    pool: &PgPool,
+) -> Result<Option<Uuid>, CreateUserError> {
+    let password_hash = hash_password(password).await?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    // Deleting the row up front makes concurrent uses of the same link wait on each other.
+    let invitation = sqlx::query!(
+        r#"
+        DELETE FROM user_invitations
+        WHERE invitation_token_hash = $1 AND expires_at > now()
+        RETURNING email, role
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to consume a user invitation")?;
+
+    let Some(invitation) = invitation else {
+        return Ok(None);
+    };
+    let role = Role::try_from(invitation.role).map_err(anyhow::Error::msg)?;
+
+    let user_id = insert_user(
+        &mut transaction,
+        username,
+        Some(&invitation.email),
+        password_hash,
+        role,
+    )
+    .await?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to accept a user invitation")?;
+
+    Ok(Some(user_id))
+}
@@ -3,8 +3,11 @@ use std::ops::Deref;
 use actix_web::body::MessageBody;
 use actix_web::dev::{ServiceRequest, ServiceResponse};
 use actix_web::error::InternalError;
+use actix_web::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
 use actix_web::middleware::Next;
-use actix_web::{FromRequest, HttpMessage};
+use actix_web::{FromRequest, HttpMessage, HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::{
@@ -12,6 +15,8 @@ use crate::{
     utils::{e500, see_other},
 };
 
+use super::api_tokens::authenticate_api_token;
+
 #[derive(Clone, Copy, Debug)]
 pub struct UserId(Uuid);
 
@@ -38,12 +43,25 @@ pub async fn reject_anonymous_users(
         TypedSession::from_request(http_request, payload).await
     }?;
 
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .expect("The connection pool is registered as application data")
+        .clone();
+
     match session.get_user_id().map_err(e500)? {
-        Some(user_id) => {
+        Some(user_id) if is_active(user_id, &pool).await.map_err(e500)? => {
             req.extensions_mut().insert(UserId(user_id));
             next.call(req).await
         }
 
+        // Users who were deactivated or deleted since they logged in are signed out.
+        Some(_) => {
+            session.log_out();
+            let response = see_other("/login");
+            let e = anyhow::anyhow!("The user is no longer active");
+            Err(InternalError::from_response(e, response).into())
+        }
+
         None => {
             let response = see_other("/login");
             let e = anyhow::anyhow!("The user has not logged in");
@@ -51,3 +69,76 @@ pub async fn reject_anonymous_users(
         }
     }
 }
+
+/// Identifies API clients either by an `Authorization: Bearer` token or, for
+/// requests coming from the admin pages, by their session. Unlike
+/// [`reject_anonymous_users`], anonymous requests get a 401 instead of a redirect.
+///
+/// Requests authenticated with a token also carry its
+/// [`ApiTokenScopes`](super::ApiTokenScopes), see [`super::require_scope`].
+pub async fn reject_anonymous_api_clients(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .expect("The connection pool is registered as application data")
+        .clone();
+
+    if let Some(token) = bearer_token(&req) {
+        return match authenticate_api_token(&token, &pool).await.map_err(e500)? {
+            Some((user_id, scopes)) => {
+                req.extensions_mut().insert(UserId(user_id));
+                req.extensions_mut().insert(scopes);
+                next.call(req).await
+            }
+            None => Err(unauthorized("The API token is invalid or has expired")),
+        };
+    }
+
+    let session = {
+        let (http_request, payload) = req.parts_mut();
+        TypedSession::from_request(http_request, payload).await
+    }?;
+
+    match session.get_user_id().map_err(e500)? {
+        Some(user_id) if is_active(user_id, &pool).await.map_err(e500)? => {
+            req.extensions_mut().insert(UserId(user_id));
+            next.call(req).await
+        }
+        _ => Err(unauthorized("The client has not authenticated")),
+    }
+}
+
+fn bearer_token(req: &ServiceRequest) -> Option<String> {
+    let value = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim().to_string())
+}
+
+fn unauthorized(message: &'static str) -> actix_web::Error {
+    let response = HttpResponse::Unauthorized()
+        .insert_header((WWW_AUTHENTICATE, "Bearer"))
+        .finish();
+    InternalError::from_response(anyhow::anyhow!(message), response).into()
+}
+
+#[tracing::instrument(name = "Check if user is active", skip(pool))]
+async fn is_active(user_id: Uuid, pool: &PgPool) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT is_active
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to check if a user is active")?;
+
+    Ok(row.is_some_and(|r| r.is_active))
+}
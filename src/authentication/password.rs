@@ -28,7 +28,7 @@ async fn get_stored_credentials(
         r#"
     SELECT user_id, password_hash
     FROM users
-    WHERE username = $1
+    WHERE username = $1 AND is_active
     "#,
         username
     )
@@ -119,7 +119,9 @@ pub async fn change_password(
     Ok(())
 }
 
-fn compute_password_hash(password: AdminPassword) -> Result<Secret<String>, anyhow::Error> {
+pub(super) fn compute_password_hash(
+    password: AdminPassword,
+) -> Result<Secret<String>, anyhow::Error> {
     let salt = SaltString::generate(&mut rand::thread_rng());
 
     let password_hash = Argon2::new(
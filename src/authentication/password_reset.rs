@@ -0,0 +1,125 @@
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use sqlx::{Executor, PgPool};
+use uuid::Uuid;
+
+use crate::domain::SubscriberEmail;
+use crate::utils::{generate_token, hash_token};
+
+/// How long a password reset link can be used for.
+const PASSWORD_RESET_VALIDITY: Duration = Duration::minutes(30);
+
+pub struct PasswordResetRequest {
+    pub email: SubscriberEmail,
+    pub token: String,
+}
+
+/// Issues a reset token for `username`. Returns `None` if there is no active
+/// user with that name, or if the user has no email address to send it to.
+/// Only a hash of the token is kept in the database.
+#[tracing::instrument(name = "Create password reset token", skip(pool))]
+pub async fn create_password_reset_token(
+    username: &str,
+    pool: &PgPool,
+) -> Result<Option<PasswordResetRequest>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT user_id, email AS "email!"
+        FROM users
+        WHERE username = $1 AND is_active AND email IS NOT NULL
+        "#,
+        username
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve the email address of a user")?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let email = SubscriberEmail::parse(row.email).map_err(anyhow::Error::msg)?;
+
+    let token = generate_token();
+    let now = Utc::now();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (token_hash, user_id, created_at, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        hash_token(&token),
+        row.user_id,
+        now,
+        now + PASSWORD_RESET_VALIDITY
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store a new password reset token")?;
+
+    Ok(Some(PasswordResetRequest { email, token }))
+}
+
+#[tracing::instrument(name = "Check password reset token", skip(token, pool))]
+pub async fn password_reset_token_is_valid(
+    token: &str,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM password_reset_tokens
+            WHERE token_hash = $1 AND expires_at > now()
+        ) AS "exists!"
+        "#,
+        hash_token(token)
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform a query to check a password reset token")?;
+
+    Ok(row.exists)
+}
+
+/// Returns the user `token` was issued for, and invalidates every reset token
+/// of that user. Returns `None` if the token is unknown or has expired.
+#[tracing::instrument(name = "Consume password reset token", skip(token, pool))]
+pub async fn consume_password_reset_token(
+    token: &str,
+    pool: &PgPool,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let row = sqlx::query!(
+        r#"
+        DELETE FROM password_reset_tokens
+        WHERE token_hash = $1
+        RETURNING user_id, expires_at > now() AS "is_valid!"
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to consume a password reset token")?;
+
+    let Some(row) = row.filter(|r| r.is_valid) else {
+        return Ok(None);
+    };
+
+    transaction
+        .execute(sqlx::query!(
+            r#"DELETE FROM password_reset_tokens WHERE user_id = $1"#,
+            row.user_id
+        ))
+        .await
+        .context("Failed to delete the remaining password reset tokens of a user")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to consume a password reset token")?;
+
+    Ok(Some(row.user_id))
+}
@@ -0,0 +1,149 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::middleware::Next;
+use actix_web::{HttpMessage, HttpResponse, web};
+use sqlx::PgPool;
+
+use crate::utils::{discard_payload, e500};
+
+use super::UserId;
+use super::users::get_role;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// Can do everything, including publishing issues and managing subscribers and users.
+    Owner,
+    /// Can prepare issues but not publish them.
+    Editor,
+    /// Can only read issue statistics.
+    Viewer,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Permission {
+    ViewStats,
+    DraftIssues,
+    PublishIssues,
+    ViewSubscribers,
+    ManageSubscribers,
+    ManageUsers,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Owner => "owner",
+            Self::Editor => "editor",
+            Self::Viewer => "viewer",
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        match self {
+            Self::Owner => true,
+            Self::Editor => matches!(
+                permission,
+                Permission::ViewStats | Permission::DraftIssues | Permission::ViewSubscribers
+            ),
+            Self::Viewer => matches!(permission, Permission::ViewStats),
+        }
+    }
+}
+
+impl TryFrom<String> for Role {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "owner" => Ok(Self::Owner),
+            "editor" => Ok(Self::Editor),
+            "viewer" => Ok(Self::Viewer),
+            other => Err(format!(
+                "{} is not a supported role. Use `owner`, `editor` or `viewer`.",
+                other
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+/// Rejects requests from users whose role does not grant `permission`.
+/// Must run after [`super::reject_anonymous_users`], which identifies the user.
+///
+/// The role is read from the database on every request, so changes apply immediately.
+pub async fn require_permission(
+    permission: Permission,
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let user_id = req
+        .extensions()
+        .get::<UserId>()
+        .copied()
+        .ok_or_else(|| e500("The user has not been identified"))?;
+
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or_else(|| e500("The connection pool is not available"))?
+        .clone();
+
+    match get_role(*user_id, &pool).await.map_err(e500)? {
+        Some(role) if role.can(permission) => {
+            req.extensions_mut().insert(role);
+            next.call(req).await
+        }
+        _ => {
+            discard_payload(&mut req).await;
+            let e = anyhow::anyhow!("User {} is not allowed to {:?}", user_id, permission);
+            Err(InternalError::from_response(e, HttpResponse::Forbidden().finish()).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Permission, Role};
+
+    #[test]
+    fn owners_can_do_everything() {
+        assert!(Role::Owner.can(Permission::ViewStats));
+        assert!(Role::Owner.can(Permission::DraftIssues));
+        assert!(Role::Owner.can(Permission::PublishIssues));
+        assert!(Role::Owner.can(Permission::ViewSubscribers));
+        assert!(Role::Owner.can(Permission::ManageSubscribers));
+        assert!(Role::Owner.can(Permission::ManageUsers));
+    }
+
+    #[test]
+    fn editors_can_draft_but_not_publish() {
+        assert!(Role::Editor.can(Permission::ViewStats));
+        assert!(Role::Editor.can(Permission::DraftIssues));
+        assert!(!Role::Editor.can(Permission::PublishIssues));
+        assert!(Role::Editor.can(Permission::ViewSubscribers));
+        assert!(!Role::Editor.can(Permission::ManageSubscribers));
+        assert!(!Role::Editor.can(Permission::ManageUsers));
+    }
+
+    #[test]
+    fn viewers_can_only_read_stats() {
+        assert!(Role::Viewer.can(Permission::ViewStats));
+        assert!(!Role::Viewer.can(Permission::DraftIssues));
+        assert!(!Role::Viewer.can(Permission::PublishIssues));
+        assert!(!Role::Viewer.can(Permission::ViewSubscribers));
+        assert!(!Role::Viewer.can(Permission::ManageSubscribers));
+        assert!(!Role::Viewer.can(Permission::ManageUsers));
+    }
+
+    #[test]
+    fn roles_round_trip_through_their_string_representation() {
+        for role in [Role::Owner, Role::Editor, Role::Viewer] {
+            assert_eq!(Role::try_from(role.as_str().to_string()), Ok(role));
+        }
+    }
+}
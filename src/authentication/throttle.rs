@@ -0,0 +1,212 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+
+use crate::configuration::LoginThrottleSettings;
+
+/// Failed logins are counted separately for the username being tried and for
+/// the address of the client, so that neither guessing many passwords for one
+/// account nor trying one password on many accounts goes unchecked.
+struct ThrottleKey {
+    key: String,
+    max_failures: i32,
+    scope: &'static str,
+}
+
+/// Password reset requests are counted apart from failed logins, so that neither
+/// can lock the other.
+const PASSWORD_RESET_KEY_PREFIX: &str = "password_reset:";
+
+fn throttle_keys(
+    key_prefix: &str,
+    username: &str,
+    client_ip: Option<IpAddr>,
+    settings: &LoginThrottleSettings,
+) -> Vec<ThrottleKey> {
+    let mut keys = vec![ThrottleKey {
+        key: format!("{}username:{}", key_prefix, username),
+        max_failures: settings.max_failures_per_username,
+        scope: "username",
+    }];
+
+    if let Some(ip) = client_ip {
+        keys.push(ThrottleKey {
+            key: format!("{}ip:{}", key_prefix, ip),
+            max_failures: settings.max_failures_per_ip,
+            scope: "ip",
+        });
+    }
+
+    keys
+}
+
+/// Returns how long logins for `username` from `client_ip` are locked for,
+/// if they are.
+#[tracing::instrument(name = "Check login lockout", skip(settings, pool))]
+pub async fn login_lockout(
+    username: &str,
+    client_ip: Option<IpAddr>,
+    settings: &LoginThrottleSettings,
+    pool: &PgPool,
+) -> Result<Option<Duration>, anyhow::Error> {
+    lockout(throttle_keys("", username, client_ip, settings), pool).await
+}
+
+/// Counts a failed login, and locks further attempts once there were too many.
+/// Returns the lockout started by this failure, if any.
+#[tracing::instrument(name = "Record login failure", skip(settings, pool))]
+pub async fn record_login_failure(
+    username: &str,
+    client_ip: Option<IpAddr>,
+    settings: &LoginThrottleSettings,
+    pool: &PgPool,
+) -> Result<Option<Duration>, anyhow::Error> {
+    metrics::counter!("login_failures_total").increment(1);
+
+    let mut lockout = None;
+    for (scope, duration) in record_attempt(
+        throttle_keys("", username, client_ip, settings),
+        settings,
+        pool,
+    )
+    .await?
+    {
+        metrics::counter!("login_lockouts_total", "scope" => scope).increment(1);
+        tracing::warn!(scope, ?duration, "Too many failed logins, locking");
+        lockout = lockout.max(Some(duration));
+    }
+
+    Ok(lockout)
+}
+
+/// Returns how long password reset requests for `username` from `client_ip` are
+/// locked for, if they are.
+#[tracing::instrument(name = "Check password reset lockout", skip(settings, pool))]
+pub async fn password_reset_lockout(
+    username: &str,
+    client_ip: Option<IpAddr>,
+    settings: &LoginThrottleSettings,
+    pool: &PgPool,
+) -> Result<Option<Duration>, anyhow::Error> {
+    let keys = throttle_keys(PASSWORD_RESET_KEY_PREFIX, username, client_ip, settings);
+    lockout(keys, pool).await
+}
+
+/// Counts a password reset request, with the same limits as failed logins, and
+/// locks further requests once there were too many.
+#[tracing::instrument(name = "Record password reset request", skip(settings, pool))]
+pub async fn record_password_reset_request(
+    username: &str,
+    client_ip: Option<IpAddr>,
+    settings: &LoginThrottleSettings,
+    pool: &PgPool,
+) -> Result<(), anyhow::Error> {
+    let keys = throttle_keys(PASSWORD_RESET_KEY_PREFIX, username, client_ip, settings);
+    for (scope, duration) in record_attempt(keys, settings, pool).await? {
+        metrics::counter!("password_reset_lockouts_total", "scope" => scope).increment(1);
+        tracing::warn!(
+            scope,
+            ?duration,
+            "Too many password reset requests, locking"
+        );
+    }
+
+    Ok(())
+}
+
+async fn lockout(keys: Vec<ThrottleKey>, pool: &PgPool) -> Result<Option<Duration>, anyhow::Error> {
+    let keys: Vec<_> = keys.into_iter().map(|k| k.key).collect();
+
+    let row = sqlx::query!(
+        r#"
+        SELECT max(locked_until) AS locked_until
+        FROM login_throttles
+        WHERE throttle_key = ANY($1) AND locked_until > now()
+        "#,
+        &keys
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform a query to check lockouts")?;
+
+    Ok(row
+        .locked_until
+        .and_then(|locked_until| (locked_until - Utc::now()).to_std().ok()))
+}
+
+/// Counts an attempt against every key. Returns the scope and duration of the
+/// lockouts it started.
+async fn record_attempt(
+    keys: Vec<ThrottleKey>,
+    settings: &LoginThrottleSettings,
+    pool: &PgPool,
+) -> Result<Vec<(&'static str, Duration)>, anyhow::Error> {
+    let now = Utc::now();
+    let window_start = now - chrono::Duration::from_std(settings.failure_window())?;
+    let mut lockouts = Vec::new();
+
+    for key in keys {
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO login_throttles (throttle_key, failures, last_failure_at)
+            VALUES ($1, 1, $2)
+            ON CONFLICT (throttle_key) DO UPDATE SET
+                failures = CASE
+                    WHEN login_throttles.last_failure_at < $3 THEN 1
+                    ELSE login_throttles.failures + 1
+                END,
+                last_failure_at = $2
+            RETURNING failures
+            "#,
+            key.key,
+            now,
+            window_start
+        )
+        .fetch_one(pool)
+        .await
+        .context("Failed to record an attempt")?;
+
+        let excess_failures = row.failures - key.max_failures;
+        if excess_failures < 0 {
+            continue;
+        }
+
+        // Every attempt past the limit doubles the lockout.
+        let duration = settings
+            .lockout()
+            .saturating_mul(2u32.saturating_pow(excess_failures as u32))
+            .min(settings.max_lockout());
+
+        sqlx::query!(
+            r#"UPDATE login_throttles SET locked_until = $2 WHERE throttle_key = $1"#,
+            key.key,
+            now + chrono::Duration::from_std(duration)?
+        )
+        .execute(pool)
+        .await
+        .context("Failed to lock further attempts")?;
+
+        lockouts.push((key.scope, duration));
+    }
+
+    Ok(lockouts)
+}
+
+/// Forgets the failed logins of `username` after a successful one. Failures
+/// from the client address are kept, a valid account must not let an attacker
+/// reset them.
+#[tracing::instrument(name = "Clear login failures", skip(pool))]
+pub async fn clear_login_failures(username: &str, pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"DELETE FROM login_throttles WHERE throttle_key = $1"#,
+        format!("username:{}", username)
+    )
+    .execute(pool)
+    .await
+    .context("Failed to clear failed logins")?;
+
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use qrcode::QrCode;
+use qrcode::render::svg;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{Executor, PgPool};
+use totp_rs::{Algorithm, TOTP};
+use uuid::Uuid;
+
+use crate::utils::{generate_token, hash_token};
+
+/// Name shown next to the account in authenticator apps.
+const ISSUER: &str = "Newsletter";
+const STEP_SECONDS: u64 = 30;
+const N_RECOVERY_CODES: usize = 10;
+
+/// Base32 encoded secret shared with the authenticator app of a user.
+pub fn generate_totp_secret() -> Secret<String> {
+    Secret::new(totp_rs::Secret::generate_secret().to_encoded().to_string())
+}
+
+/// Everything an admin needs to add their account to an authenticator app.
+pub struct TotpEnrollment {
+    pub secret: Secret<String>,
+    pub otpauth_url: String,
+    pub qr_code_svg: String,
+}
+
+impl TotpEnrollment {
+    pub fn new(secret: Secret<String>, username: &str) -> Result<Self, anyhow::Error> {
+        let otpauth_url = totp(&secret, username)?.get_url();
+        let qr_code_svg = QrCode::new(&otpauth_url)
+            .context("Failed to encode the provisioning URL as a QR code")?
+            .render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .build();
+
+        Ok(Self {
+            secret,
+            otpauth_url,
+            qr_code_svg,
+        })
+    }
+}
+
+fn totp(secret: &Secret<String>, username: &str) -> Result<TOTP, anyhow::Error> {
+    let secret = totp_rs::Secret::Encoded(secret.expose_secret().clone())
+        .to_bytes()
+        .context("The TOTP secret is not valid base32")?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        // Steps are checked one at a time, see `matching_step`.
+        0,
+        STEP_SECONDS,
+        secret,
+        Some(ISSUER.into()),
+        // Labels use `:` to separate the issuer from the account name.
+        username.replace(':', "_"),
+    )
+    .context("Failed to build a TOTP generator")
+}
+
+/// Returns the time step `code` was generated for, accepting codes from the
+/// previous and the next step to allow for clock drift.
+fn matching_step(secret: &Secret<String>, code: &str) -> Result<Option<i64>, anyhow::Error> {
+    let totp = totp(secret, "")?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("The system clock is set before the UNIX epoch")?
+        .as_secs();
+    let current_step = now / STEP_SECONDS;
+
+    let step = [current_step - 1, current_step, current_step + 1]
+        .into_iter()
+        .find(|step| totp.check(code, step * STEP_SECONDS));
+
+    Ok(step.map(|step| step as i64))
+}
+
+/// Checks a code typed during enrollment, before the secret is stored.
+pub fn verify_enrollment_code(
+    secret: &Secret<String>,
+    code: &str,
+) -> Result<Option<i64>, anyhow::Error> {
+    matching_step(secret, code.trim())
+}
+
+/// Stores `secret` for `user_id`, replacing any previous one, and returns a new
+/// set of recovery codes. Only hashes of the codes are kept in the database.
+#[tracing::instrument(name = "Enable two-factor authentication", skip(secret, pool))]
+pub async fn enable_two_factor(
+    user_id: Uuid,
+    secret: &Secret<String>,
+    enrollment_step: i64,
+    pool: &PgPool,
+) -> Result<Vec<String>, anyhow::Error> {
+    let recovery_codes: Vec<_> = (0..N_RECOVERY_CODES).map(|_| generate_token()).collect();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_secret = $2, totp_last_used_step = $3
+            WHERE user_id = $1
+            "#,
+            user_id,
+            secret.expose_secret(),
+            enrollment_step
+        ))
+        .await
+        .context("Failed to store a TOTP secret")?;
+
+    transaction
+        .execute(sqlx::query!(
+            r#"DELETE FROM recovery_codes WHERE user_id = $1"#,
+            user_id
+        ))
+        .await
+        .context("Failed to delete previous recovery codes")?;
+
+    for code in &recovery_codes {
+        transaction
+            .execute(sqlx::query!(
+                r#"INSERT INTO recovery_codes (user_id, code_hash) VALUES ($1, $2)"#,
+                user_id,
+                hash_token(code)
+            ))
+            .await
+            .context("Failed to store a recovery code")?;
+    }
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to enable two-factor authentication")?;
+
+    Ok(recovery_codes)
+}
+
+#[tracing::instrument(name = "Disable two-factor authentication", skip(pool))]
+pub async fn disable_two_factor(user_id: Uuid, pool: &PgPool) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_secret = NULL, totp_last_used_step = NULL
+            WHERE user_id = $1
+            "#,
+            user_id
+        ))
+        .await
+        .context("Failed to delete a TOTP secret")?;
+
+    transaction
+        .execute(sqlx::query!(
+            r#"DELETE FROM recovery_codes WHERE user_id = $1"#,
+            user_id
+        ))
+        .await
+        .context("Failed to delete recovery codes")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to disable two-factor authentication")?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Check if two-factor authentication is enabled", skip(pool))]
+pub async fn two_factor_enabled(user_id: Uuid, pool: &PgPool) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT totp_secret IS NOT NULL AS "enabled!"
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to check if two-factor authentication is enabled")?;
+
+    Ok(row.is_some_and(|r| r.enabled))
+}
+
+/// Accepts either a code from the authenticator app or one of the recovery
+/// codes. Each of them can only be used once.
+#[tracing::instrument(name = "Verify second factor", skip(code, pool))]
+pub async fn verify_second_factor(
+    user_id: Uuid,
+    code: &str,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let code = code.trim();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let row = sqlx::query!(
+        r#"
+        SELECT totp_secret, totp_last_used_step
+        FROM users
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to perform a query to retrieve a TOTP secret")?;
+
+    let Some((secret, last_used_step)) =
+        row.and_then(|r| Some((Secret::new(r.totp_secret?), r.totp_last_used_step)))
+    else {
+        return Ok(false);
+    };
+
+    let is_valid = match matching_step(&secret, code)? {
+        // A code can't be replayed, nor can an older one once a newer one was used.
+        Some(step) if last_used_step.is_none_or(|last| step > last) => {
+            transaction
+                .execute(sqlx::query!(
+                    r#"UPDATE users SET totp_last_used_step = $2 WHERE user_id = $1"#,
+                    user_id,
+                    step
+                ))
+                .await
+                .context("Failed to record the use of a TOTP code")?;
+            true
+        }
+        Some(_) => false,
+        None => {
+            transaction
+                .execute(sqlx::query!(
+                    r#"DELETE FROM recovery_codes WHERE user_id = $1 AND code_hash = $2"#,
+                    user_id,
+                    hash_token(code)
+                ))
+                .await
+                .context("Failed to use a recovery code")?
+                .rows_affected()
+                > 0
+        }
+    };
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to verify a second factor")?;
+
+    Ok(is_valid)
+}
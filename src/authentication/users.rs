@@ -0,0 +1,331 @@
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{Executor, PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::{AdminPassword, SubscriberEmail};
+use crate::telemetry::spawn_blocking_with_tracing;
+
+use super::password::compute_password_hash;
+use super::permissions::Role;
+
+pub struct UserSummary {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: Role,
+    pub email: Option<String>,
+    pub is_active: bool,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CreateUserError {
+    #[error("A user named {0} already exists.")]
+    UsernameTaken(String),
+    #[error("A user with the email address {0} already exists.")]
+    EmailTaken(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+#[tracing::instrument(name = "Create user", skip(password, pool))]
+pub async fn create_user(
+    username: &str,
+    email: Option<&SubscriberEmail>,
+    password: AdminPassword,
+    role: Role,
+    pool: &PgPool,
+) -> Result<Uuid, CreateUserError> {
+    let password_hash = hash_password(password).await?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let user_id = insert_user(
+        &mut transaction,
+        username,
+        email.map(AsRef::as_ref),
+        password_hash,
+        role,
+    )
+    .await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a new user")?;
+
+    Ok(user_id)
+}
+
+/// Creates the first admin of a fresh install. Returns `None`, without creating
+/// anything, if there is already at least one user.
+#[tracing::instrument(name = "Create first user", skip(password, pool))]
+pub async fn create_first_user(
+    username: &str,
+    email: Option<&SubscriberEmail>,
+    password: AdminPassword,
+    pool: &PgPool,
+) -> Result<Option<Uuid>, CreateUserError> {
+    let password_hash = hash_password(password).await?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    // Serialize concurrent setups, only one of them can see an empty table.
+    transaction
+        .execute("LOCK TABLE users IN SHARE ROW EXCLUSIVE MODE")
+        .await
+        .context("Failed to lock the users table")?;
+
+    if users_exist(&mut *transaction).await? {
+        return Ok(None);
+    }
+
+    let user_id = insert_user(
+        &mut transaction,
+        username,
+        email.map(AsRef::as_ref),
+        password_hash,
+        Role::Owner,
+    )
+    .await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store the first user")?;
+
+    Ok(Some(user_id))
+}
+
+#[tracing::instrument(name = "Check if any user exists", skip(executor))]
+pub async fn users_exist(executor: impl PgExecutor<'_>) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(r#"SELECT EXISTS(SELECT 1 FROM users) AS "exists!""#)
+        .fetch_one(executor)
+        .await
+        .context("Failed to perform a query to check if any user exists")?;
+
+    Ok(row.exists)
+}
+
+pub(super) async fn hash_password(
+    password: AdminPassword,
+) -> Result<Secret<String>, anyhow::Error> {
+    spawn_blocking_with_tracing(move || compute_password_hash(password))
+        .await
+        .context("Failed to spawn blocking task.")?
+        .context("Failed to hash password")
+}
+
+pub(super) async fn insert_user(
+    transaction: &mut Transaction<'_, Postgres>,
+    username: &str,
+    email: Option<&str>,
+    password_hash: Secret<String>,
+    role: Role,
+) -> Result<Uuid, CreateUserError> {
+    let user_id = Uuid::new_v4();
+
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, email, password_hash, role)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        username,
+        email,
+        password_hash.expose_secret(),
+        role.as_str()
+    );
+
+    transaction.execute(query).await.map_err(|e| match e {
+        sqlx::Error::Database(e) if e.constraint() == Some("users_email_key") => {
+            CreateUserError::EmailTaken(email.unwrap_or_default().into())
+        }
+        sqlx::Error::Database(e) if e.is_unique_violation() => {
+            CreateUserError::UsernameTaken(username.into())
+        }
+        e => CreateUserError::UnexpectedError(
+            anyhow::Error::new(e).context("Failed to insert a new user in the database."),
+        ),
+    })?;
+
+    Ok(user_id)
+}
+
+#[tracing::instrument(name = "Get user id by username", skip(pool))]
+pub async fn get_user_id(username: &str, pool: &PgPool) -> Result<Option<Uuid>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT user_id
+        FROM users
+        WHERE username = $1
+        "#,
+        username
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve a user id")?;
+
+    Ok(row.map(|r| r.user_id))
+}
+
+#[tracing::instrument(name = "Get user role", skip(pool))]
+pub async fn get_role(user_id: Uuid, pool: &PgPool) -> Result<Option<Role>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT role
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve a user role")?;
+
+    row.map(|r| Role::try_from(r.role).map_err(anyhow::Error::msg))
+        .transpose()
+}
+
+#[tracing::instrument(name = "List users", skip(pool))]
+pub async fn list_users(pool: &PgPool) -> Result<Vec<UserSummary>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT user_id, username, role, email, is_active
+        FROM users
+        ORDER BY username
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to list users")?;
+
+    rows.into_iter()
+        .map(|r| {
+            Ok(UserSummary {
+                user_id: r.user_id,
+                username: r.username,
+                role: Role::try_from(r.role).map_err(anyhow::Error::msg)?,
+                email: r.email,
+                is_active: r.is_active,
+            })
+        })
+        .collect()
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum UpdateUserError {
+    #[error("The user does not exist.")]
+    UnknownUser,
+    #[error("There must always be at least one active owner.")]
+    LastActiveOwner,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+/// Deactivated users cannot log in, and their open sessions are rejected.
+#[tracing::instrument(name = "Set user active", skip(pool))]
+pub async fn set_user_active(
+    user_id: Uuid,
+    is_active: bool,
+    pool: &PgPool,
+) -> Result<(), UpdateUserError> {
+    let mut transaction = begin_user_update(pool).await?;
+    if !is_active {
+        ensure_another_active_owner(&mut transaction, user_id).await?;
+    }
+
+    let query = sqlx::query!(
+        r#"UPDATE users SET is_active = $2 WHERE user_id = $1"#,
+        user_id,
+        is_active
+    );
+    let result = transaction
+        .execute(query)
+        .await
+        .context("Failed to update the status of a user")?;
+    if result.rows_affected() == 0 {
+        return Err(UpdateUserError::UnknownUser);
+    }
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to update the status of a user")?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Delete user", skip(pool))]
+pub async fn delete_user(user_id: Uuid, pool: &PgPool) -> Result<(), UpdateUserError> {
+    let mut transaction = begin_user_update(pool).await?;
+    ensure_another_active_owner(&mut transaction, user_id).await?;
+
+    let query = sqlx::query!(r#"DELETE FROM idempotency WHERE user_id = $1"#, user_id);
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete the saved responses of a user")?;
+
+    let query = sqlx::query!(r#"DELETE FROM users WHERE user_id = $1"#, user_id);
+    let result = transaction
+        .execute(query)
+        .await
+        .context("Failed to delete a user")?;
+    if result.rows_affected() == 0 {
+        return Err(UpdateUserError::UnknownUser);
+    }
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to delete a user")?;
+
+    Ok(())
+}
+
+async fn begin_user_update(pool: &PgPool) -> Result<Transaction<'static, Postgres>, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    // Two owners removing each other concurrently must not leave the install without one.
+    transaction
+        .execute("LOCK TABLE users IN SHARE ROW EXCLUSIVE MODE")
+        .await
+        .context("Failed to lock the users table")?;
+
+    Ok(transaction)
+}
+
+async fn ensure_another_active_owner(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<(), UpdateUserError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            role = 'owner' AND is_active AS "is_active_owner!",
+            EXISTS(
+                SELECT 1 FROM users
+                WHERE role = 'owner' AND is_active AND user_id <> $1
+            ) AS "has_other_owner!"
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .context("Failed to perform a query to count active owners")?
+    .ok_or(UpdateUserError::UnknownUser)?;
+
+    if row.is_active_owner && !row.has_other_owner {
+        return Err(UpdateUserError::LastActiveOwner);
+    }
+
+    Ok(())
+}
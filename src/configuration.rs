@@ -1,9 +1,11 @@
+use std::num::{NonZeroU32, NonZeroU64};
 use std::time::Duration;
 
 use config::{Config, ConfigError};
 use secrecy::Secret;
 
-use crate::{domain::SubscriberEmail, email_client::EmailClient};
+use crate::domain::SubscriberEmail;
+use crate::email_client::{EmailClient, RateLimiter};
 
 #[derive(serde::Deserialize, Clone)]
 pub struct Settings {
@@ -13,6 +15,7 @@ pub struct Settings {
     pub redis_uri: Secret<String>,
     pub metrics: MetricsSettings,
     pub tracer: TracerSettings,
+    pub worker: WorkerSettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -21,6 +24,17 @@ pub struct ApplicationSettings {
     pub port: u16,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    pub shutdown_timeout_milliseconds: u64,
+    pub bootstrap_token: Option<Secret<String>>,
+    pub login_throttle: LoginThrottleSettings,
+    /// Whether issues embed an image recording when they are opened.
+    pub track_opens: bool,
+}
+
+impl ApplicationSettings {
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_millis(self.shutdown_timeout_milliseconds)
+    }
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -29,6 +43,14 @@ pub struct EmailClientSettings {
     pub sender_email: String,
     pub authorization_token: Secret<String>,
     pub timeout_miliseconds: u64,
+    pub rate_limit: Option<RateLimitSettings>,
+}
+
+/// Zero messages or a zero period are rejected when the configuration is loaded.
+#[derive(serde::Deserialize, Clone)]
+pub struct RateLimitSettings {
+    pub messages: NonZeroU32,
+    pub period_milliseconds: NonZeroU64,
 }
 
 impl EmailClientSettings {
@@ -43,12 +65,20 @@ impl EmailClientSettings {
     pub fn client(self) -> EmailClient {
         let sender_email = self.sender().expect("Invalid sender email address.");
         let timeout = self.timeout();
+        let rate_limiter = match self.rate_limit {
+            Some(limit) => RateLimiter::new(
+                limit.messages,
+                Duration::from_millis(limit.period_milliseconds.get()),
+            ),
+            None => RateLimiter::unlimited(),
+        };
 
         EmailClient::new(
             self.base_url,
             sender_email,
             self.authorization_token,
             timeout,
+            rate_limiter,
         )
     }
 }
@@ -89,6 +119,53 @@ pub struct TracerSettings {
     pub sampling_ratio: f64,
 }
 
+#[derive(serde::Deserialize, Clone)]
+pub struct WorkerSettings {
+    pub concurrency: usize,
+    pub idle_backoff_milliseconds: u64,
+    pub error_backoff_milliseconds: u64,
+    pub batch_size: usize,
+    pub max_retries: i32,
+    pub retry_backoff_milliseconds: u64,
+}
+
+impl WorkerSettings {
+    pub fn idle_backoff(&self) -> Duration {
+        Duration::from_millis(self.idle_backoff_milliseconds)
+    }
+
+    pub fn error_backoff(&self) -> Duration {
+        Duration::from_millis(self.error_backoff_milliseconds)
+    }
+
+    pub fn retry_backoff(&self) -> Duration {
+        Duration::from_millis(self.retry_backoff_milliseconds)
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct LoginThrottleSettings {
+    pub max_failures_per_username: i32,
+    pub max_failures_per_ip: i32,
+    pub lockout_milliseconds: u64,
+    pub max_lockout_milliseconds: u64,
+    pub failure_window_milliseconds: u64,
+}
+
+impl LoginThrottleSettings {
+    pub fn lockout(&self) -> Duration {
+        Duration::from_millis(self.lockout_milliseconds)
+    }
+
+    pub fn max_lockout(&self) -> Duration {
+        Duration::from_millis(self.max_lockout_milliseconds)
+    }
+
+    pub fn failure_window(&self) -> Duration {
+        Duration::from_millis(self.failure_window_milliseconds)
+    }
+}
+
 pub fn get_configuration() -> Result<Settings, ConfigError> {
     let base_path = std::env::current_dir().expect("Failed to determine the current directory");
     let configuration_directory = base_path.join("configuration");
@@ -143,3 +220,20 @@ impl TryFrom<String> for Environment {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimitSettings;
+
+    #[test]
+    fn rate_limits_of_zero_are_rejected() {
+        for (messages, period_milliseconds) in [(0, 1000), (50, 0)] {
+            let limit = serde_json::json!({
+                "messages": messages,
+                "period_milliseconds": period_milliseconds,
+            });
+
+            assert!(serde_json::from_value::<RateLimitSettings>(limit).is_err());
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use actix_web::HttpRequest;
+use actix_web::http::header::USER_AGENT;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+/// Longer user agents are truncated.
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// How a subscriber was added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignupSource {
+    /// The public subscription form.
+    Form,
+    Api,
+    Import,
+}
+
+impl SignupSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignupSource::Form => "form",
+            SignupSource::Api => "api",
+            SignupSource::Import => "import",
+        }
+    }
+}
+
+/// Where a request came from, as recorded in the consent and audit trails.
+#[derive(Debug, Default)]
+pub struct RequestMetadata {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl RequestMetadata {
+    pub fn from_request(request: &HttpRequest) -> Self {
+        // Forwarding headers can be forged, only the address of the peer is trusted.
+        let ip_address = request.peer_addr().map(|addr| addr.ip().to_string());
+        let user_agent = request
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+        Self {
+            ip_address,
+            user_agent,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct ConsentEvent {
+    /// Either `signup` or `confirmation`.
+    pub event: String,
+    /// How the subscriber signed up, for `signup` events.
+    pub source: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Record signup", skip(executor, metadata))]
+pub async fn record_signup<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    subscriber_id: Uuid,
+    source: SignupSource,
+    metadata: &RequestMetadata,
+) -> Result<(), anyhow::Error> {
+    record(executor, subscriber_id, "signup", Some(source), metadata).await
+}
+
+#[tracing::instrument(name = "Record confirmation", skip(executor, metadata))]
+pub async fn record_confirmation<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    subscriber_id: Uuid,
+    metadata: &RequestMetadata,
+) -> Result<(), anyhow::Error> {
+    record(executor, subscriber_id, "confirmation", None, metadata).await
+}
+
+/// Imported subscribers did not sign up through a request of their own,
+/// so no address or user agent is recorded.
+#[tracing::instrument(name = "Record imported signups", skip_all)]
+pub async fn record_imported_signups<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    subscriber_ids: &[Uuid],
+) -> Result<(), anyhow::Error> {
+    let consent_event_ids: Vec<Uuid> = subscriber_ids.iter().map(|_| Uuid::new_v4()).collect();
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO consent_events (consent_event_id, subscriber_id, event, source, occurred_at)
+        SELECT consent_event_id, subscriber_id, 'signup', $3, now()
+        FROM UNNEST($1::uuid[], $2::uuid[]) AS t(consent_event_id, subscriber_id)
+        "#,
+        &consent_event_ids,
+        subscriber_ids,
+        SignupSource::Import.as_str()
+    );
+    executor
+        .execute(query)
+        .await
+        .context("Failed to record the signup of imported subscribers")?;
+
+    Ok(())
+}
+
+async fn record<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    subscriber_id: Uuid,
+    event: &str,
+    source: Option<SignupSource>,
+    metadata: &RequestMetadata,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO consent_events (
+            consent_event_id,
+            subscriber_id,
+            event,
+            source,
+            ip_address,
+            user_agent,
+            occurred_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, now())
+        "#,
+        Uuid::new_v4(),
+        subscriber_id,
+        event,
+        source.map(|source| source.as_str()),
+        metadata.ip_address,
+        metadata.user_agent
+    );
+    executor
+        .execute(query)
+        .await
+        .context("Failed to record a consent event")?;
+
+    Ok(())
+}
+
+/// Oldest first.
+#[tracing::instrument(name = "List consent events", skip(pool))]
+pub async fn list_consent_events(
+    subscriber_id: Uuid,
+    pool: &PgPool,
+) -> Result<Vec<ConsentEvent>, anyhow::Error> {
+    sqlx::query_as!(
+        ConsentEvent,
+        r#"
+        SELECT event, source, ip_address, user_agent, occurred_at
+        FROM consent_events
+        WHERE subscriber_id = $1
+        ORDER BY occurred_at, consent_event_id
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to list consent events")
+}
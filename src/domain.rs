@@ -2,8 +2,10 @@ mod admin_password;
 mod new_subscriber;
 mod subscriber_email;
 mod subscriber_name;
+mod subscription_status;
 
 pub use admin_password::AdminPassword;
 pub use new_subscriber::NewSubscriber;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscription_status::SubscriptionStatus;
@@ -3,17 +3,14 @@ use unicode_segmentation::UnicodeSegmentation;
 pub struct AdminPassword(String); // TODO: Change String to Secret<String> or SecretString
 
 impl AdminPassword {
-    pub fn new(password: String) -> Result<Self, ()> {
+    pub fn new(password: String) -> Result<Self, String> {
         let password_count = password.trim().graphemes(true).count();
 
-        if password_count <= 12 {
-            // TODO: Change to a better representation of error
-            return Err(());
-        }
-
-        if password_count >= 129 {
-            // TODO: Change to a better representation of error
-            return Err(());
+        if password_count <= 12 || password_count >= 129 {
+            return Err(
+                "The new password must be longer than 12 characters and shorter than 129 characters."
+                    .into(),
+            );
         }
 
         Ok(Self(password))
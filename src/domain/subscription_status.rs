@@ -0,0 +1,69 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubscriptionStatus {
+    /// Waiting for the subscriber to follow the link in their confirmation email.
+    PendingConfirmation,
+    /// Receives every published issue.
+    Confirmed,
+    /// Kept on record but no longer receives issues.
+    Unsubscribed,
+}
+
+impl SubscriptionStatus {
+    pub const ALL: [Self; 3] = [
+        Self::PendingConfirmation,
+        Self::Confirmed,
+        Self::Unsubscribed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PendingConfirmation => "pending_confirmation",
+            Self::Confirmed => "confirmed",
+            Self::Unsubscribed => "unsubscribed",
+        }
+    }
+}
+
+impl TryFrom<String> for SubscriptionStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|status| status.as_str() == value)
+            .ok_or_else(|| {
+                format!(
+                    "{} is not a valid subscription status. \
+                    Use `pending_confirmation`, `confirmed` or `unsubscribed`.",
+                    value
+                )
+            })
+    }
+}
+
+impl std::fmt::Display for SubscriptionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriptionStatus;
+    use claims::assert_err;
+
+    #[test]
+    fn statuses_round_trip_through_their_string_representation() {
+        for status in SubscriptionStatus::ALL {
+            assert_eq!(
+                SubscriptionStatus::try_from(status.as_str().to_string()),
+                Ok(status)
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_statuses_are_rejected() {
+        assert_err!(SubscriptionStatus::try_from("Confirmed".to_string()));
+    }
+}
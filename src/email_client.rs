@@ -1,62 +1,203 @@
-use reqwest::Client;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Response, StatusCode};
 use secrecy::{ExposeSecret, Secret};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 use crate::domain::SubscriberEmail;
 
+mod rate_limiter;
+
+pub use rate_limiter::RateLimiter;
+
 #[derive(Debug)]
 pub struct EmailClient {
     sender: SubscriberEmail,
     base_url: String,
     http_client: Client,
     authorization_token: Secret<String>,
+    rate_limiter: RateLimiter,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SendEmailError {
+    /// Sending now would go over the rate limit, or the provider asked us to back off.
+    /// Holds how long to wait before trying again.
+    #[error("The email provider is throttling our requests.")]
+    Throttled(Duration),
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
 }
 
 impl EmailClient {
+    /// Pause used when the provider throttles us without a valid `Retry-After` header.
+    const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+    /// Longest [`EmailClient::send_email`] waits for the rate limiter. Deliveries only
+    /// take what is available, so this is rarely reached unless sending is paused.
+    const MAX_SEND_EMAIL_WAIT: Duration = Duration::from_secs(5);
+
     pub fn new(
         base_url: String,
         sender: SubscriberEmail,
         authorization_token: Secret<String>,
         timeout: Duration,
+        rate_limiter: RateLimiter,
     ) -> Self {
         Self {
             http_client: Client::builder().timeout(timeout).build().unwrap(),
             base_url,
             sender,
             authorization_token,
+            rate_limiter,
         }
     }
 
+    /// Used while handling requests, so it waits at most [`Self::MAX_SEND_EMAIL_WAIT`]
+    /// for the rate limiter: it fails with [`SendEmailError::Throttled`] instead.
     pub async fn send_email(
         &self,
         recipient: &SubscriberEmail,
         subject: &str,
         html_content: &str,
         text_content: &str,
-    ) -> Result<(), reqwest::Error> {
-        let url = format!("{}/email", self.base_url);
+    ) -> Result<(), SendEmailError> {
         let request_body = SendEmailRequest {
             from: self.sender.as_ref(),
             to: recipient.as_ref(),
-            subject: subject,
+            subject,
             html_body: html_content,
             text_body: text_content,
         };
 
-        let _response = self
+        self.rate_limiter
+            .acquire_within(1, Self::MAX_SEND_EMAIL_WAIT)
+            .await
+            .map_err(SendEmailError::Throttled)?;
+        let _response = self.post("email", &request_body).await?;
+
+        Ok(())
+    }
+
+    /// Sets `n_messages` aside for [`EmailClient::send_batch`] if they can be sent
+    /// right away. Otherwise the error is how long to wait before trying again.
+    pub fn try_reserve(&self, n_messages: usize) -> Result<(), Duration> {
+        self.rate_limiter.try_acquire(n_messages)
+    }
+
+    /// Most messages that can be reserved at once.
+    pub fn max_reservation(&self) -> usize {
+        self.rate_limiter
+            .burst_size()
+            .map_or(MAX_BATCH_SIZE, |burst_size| burst_size.min(MAX_BATCH_SIZE))
+    }
+
+    /// Gives back messages reserved but not sent.
+    pub fn release(&self, n_messages: usize) {
+        self.rate_limiter.release(n_messages);
+    }
+
+    /// Sends up to [`MAX_BATCH_SIZE`] emails in a single request to Postmark's batch
+    /// endpoint. Results are returned in the same order as `emails`.
+    ///
+    /// The messages must have been set aside with [`EmailClient::try_reserve`].
+    pub async fn send_batch(
+        &self,
+        emails: &[BatchEmail<'_>],
+    ) -> Result<Vec<BatchEmailResult>, SendEmailError> {
+        let request_body: Vec<_> = emails
+            .iter()
+            .map(|email| SendEmailRequest {
+                from: self.sender.as_ref(),
+                to: email.recipient.as_ref(),
+                subject: email.subject,
+                html_body: email.html_content,
+                text_body: email.text_content,
+            })
+            .collect();
+
+        // Another sender was throttled since the messages were reserved.
+        if let Some(remaining) = self.rate_limiter.paused_for() {
+            return Err(SendEmailError::Throttled(remaining));
+        }
+        let results = self
+            .post("email/batch", &request_body)
+            .await?
+            .json()
+            .await?;
+
+        Ok(results)
+    }
+
+    async fn post(&self, path: &str, body: &impl Serialize) -> Result<Response, SendEmailError> {
+        let url = format!("{}/{}", self.base_url, path);
+
+        let response = self
             .http_client
             .post(&url)
             .header(
                 "X-Postmark-Server-Token",
                 self.authorization_token.expose_secret(),
             )
-            .json(&request_body)
+            .json(body)
             .send()
-            .await?
-            .error_for_status()?;
+            .await?;
+
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(Self::DEFAULT_RETRY_AFTER);
+
+            // Every sender sharing this client backs off, not only the current caller.
+            self.rate_limiter.pause_for(retry_after);
+            return Err(SendEmailError::Throttled(retry_after));
+        }
 
-        Ok(())
+        Ok(response.error_for_status()?)
+    }
+}
+
+/// Maximum number of messages accepted by Postmark in a single batch request.
+pub const MAX_BATCH_SIZE: usize = 500;
+
+pub struct BatchEmail<'a> {
+    pub recipient: &'a SubscriberEmail,
+    pub subject: &'a str,
+    pub html_content: &'a str,
+    pub text_content: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BatchEmailResult {
+    pub error_code: i64,
+    pub message: String,
+}
+
+pub enum DeliveryOutcome {
+    Delivered,
+    /// The provider will never accept this message, e.g. the recipient is inactive.
+    Rejected(String),
+    Retryable(String),
+}
+
+impl BatchEmailResult {
+    // See https://postmarkapp.com/developer/api/overview#error-codes
+    const SUCCESS: i64 = 0;
+    const INVALID_EMAIL_REQUEST: i64 = 300;
+    const INACTIVE_RECIPIENT: i64 = 406;
+
+    pub fn outcome(self) -> DeliveryOutcome {
+        match self.error_code {
+            Self::SUCCESS => DeliveryOutcome::Delivered,
+            Self::INVALID_EMAIL_REQUEST | Self::INACTIVE_RECIPIENT => {
+                DeliveryOutcome::Rejected(self.message)
+            }
+            _ => DeliveryOutcome::Retryable(self.message),
+        }
     }
 }
 
@@ -72,6 +213,7 @@ struct SendEmailRequest<'a> {
 
 #[cfg(test)]
 mod test {
+    use std::num::NonZeroU32;
     use std::time::Duration;
 
     use claims::{assert_err, assert_ok};
@@ -83,7 +225,9 @@ mod test {
     use wiremock::{Mock, MockServer, ResponseTemplate, matchers::any};
 
     use crate::domain::SubscriberEmail;
-    use crate::email_client::EmailClient;
+    use crate::email_client::{
+        BatchEmail, DeliveryOutcome, EmailClient, RateLimiter, SendEmailError,
+    };
 
     struct SendEmailBodyMatcher;
 
@@ -111,6 +255,19 @@ mod test {
         Paragraph(1..10).fake()
     }
 
+    fn batch_email<'a>(
+        recipient: &'a SubscriberEmail,
+        subject: &'a str,
+        content: &'a str,
+    ) -> BatchEmail<'a> {
+        BatchEmail {
+            recipient,
+            subject,
+            html_content: content,
+            text_content: content,
+        }
+    }
+
     fn email() -> SubscriberEmail {
         SubscriberEmail::parse(SafeEmail().fake()).unwrap()
     }
@@ -121,6 +278,7 @@ mod test {
             email(),
             Secret::new(Faker.fake()),
             Duration::from_millis(200),
+            RateLimiter::unlimited(),
         )
     }
 
@@ -199,4 +357,151 @@ mod test {
 
         assert_err!(result);
     }
+
+    #[tokio::test]
+    async fn send_batch_fires_a_request_to_the_batch_endpoint() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(path("/email/batch"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"ErrorCode": 0, "Message": "OK"},
+                {"ErrorCode": 0, "Message": "OK"}
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let (recipient, subject, content) = (email(), subject(), content());
+        let emails = vec![
+            batch_email(&recipient, &subject, &content),
+            batch_email(&recipient, &subject, &content),
+        ];
+
+        let results = email_client.send_batch(&emails).await;
+
+        assert_eq!(assert_ok!(results).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn send_batch_reports_the_outcome_of_every_message() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"ErrorCode": 0, "Message": "OK"},
+                {"ErrorCode": 406, "Message": "Inactive recipient"},
+                {"ErrorCode": 100, "Message": "Maintenance"}
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let (recipient, subject, content) = (email(), subject(), content());
+        let emails: Vec<_> = (0..3)
+            .map(|_| batch_email(&recipient, &subject, &content))
+            .collect();
+
+        let outcomes: Vec<_> = assert_ok!(email_client.send_batch(&emails).await)
+            .into_iter()
+            .map(|r| r.outcome())
+            .collect();
+
+        assert!(matches!(outcomes[0], DeliveryOutcome::Delivered));
+        assert!(matches!(outcomes[1], DeliveryOutcome::Rejected(_)));
+        assert!(matches!(outcomes[2], DeliveryOutcome::Retryable(_)));
+    }
+
+    #[tokio::test]
+    async fn send_batch_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let (recipient, subject, content) = (email(), subject(), content());
+        let emails = vec![batch_email(&recipient, &subject, &content)];
+
+        assert_err!(email_client.send_batch(&emails).await);
+    }
+
+    #[tokio::test]
+    async fn send_email_is_throttled_if_the_server_returns_429() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "7"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(SendEmailError::Throttled(retry_after)) if retry_after == Duration::from_secs(7)
+        ));
+    }
+
+    #[tokio::test]
+    async fn send_email_waits_briefly_for_the_rate_limiter() {
+        let mock_server = MockServer::start().await;
+        let email_client = EmailClient::new(
+            mock_server.uri(),
+            email(),
+            Secret::new(Faker.fake()),
+            Duration::from_millis(200),
+            RateLimiter::new(NonZeroU32::new(1).unwrap(), Duration::from_millis(500)),
+        );
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        for _ in 0..2 {
+            let outcome = email_client
+                .send_email(&email(), &subject(), &content(), &content())
+                .await;
+
+            assert_ok!(outcome);
+        }
+    }
+
+    #[tokio::test]
+    async fn throttled_clients_fail_fast_without_sending() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "60"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let _ = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        let (recipient, subject, content) = (email(), subject(), content());
+        let email_result = email_client
+            .send_email(&recipient, &subject, &content, &content)
+            .await;
+        let batch_result = email_client
+            .send_batch(&[batch_email(&recipient, &subject, &content)])
+            .await;
+
+        assert!(matches!(email_result, Err(SendEmailError::Throttled(_))));
+        assert!(matches!(batch_result, Err(SendEmailError::Throttled(_))));
+    }
 }
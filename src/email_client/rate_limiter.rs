@@ -0,0 +1,274 @@
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token bucket shared by every clone, used to keep outbound email under the
+/// provider's limits. It can also be paused when the provider asks us to back off.
+#[derive(Clone, Debug)]
+pub struct RateLimiter(Arc<Mutex<State>>);
+
+#[derive(Debug)]
+struct State {
+    bucket: Option<Bucket>,
+    paused_until: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    tokens: f64,
+    tokens_per_second: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Allows up to `messages` every `period`, with bursts of at most `messages`.
+    /// Panics if `period` is zero.
+    pub fn new(messages: NonZeroU32, period: Duration) -> Self {
+        assert!(!period.is_zero(), "The rate limit period must not be zero");
+        let capacity = f64::from(messages.get());
+        let bucket = Bucket {
+            capacity,
+            tokens: capacity,
+            tokens_per_second: capacity / period.as_secs_f64(),
+            last_refill: Instant::now(),
+        };
+
+        Self::from_state(State {
+            bucket: Some(bucket),
+            paused_until: None,
+        })
+    }
+
+    pub fn unlimited() -> Self {
+        Self::from_state(State {
+            bucket: None,
+            paused_until: None,
+        })
+    }
+
+    fn from_state(state: State) -> Self {
+        Self(Arc::new(Mutex::new(state)))
+    }
+
+    /// Waits until `n_messages` can be sent and takes them, unless that would take
+    /// longer than `max_wait`: then nothing is taken, and the error is how long to wait
+    /// before trying again.
+    pub async fn acquire_within(
+        &self,
+        n_messages: usize,
+        max_wait: Duration,
+    ) -> Result<(), Duration> {
+        let wait = self.reserve(n_messages, max_wait, Instant::now())?;
+
+        if !wait.is_zero() {
+            tracing::debug!("Waiting {:?} before sending more emails", wait);
+            tokio::time::sleep(wait).await;
+        }
+
+        Ok(())
+    }
+
+    /// Takes `n_messages` only if they can be sent right away. Otherwise nothing is
+    /// taken, and the error is how long to wait before trying again.
+    pub fn try_acquire(&self, n_messages: usize) -> Result<(), Duration> {
+        self.reserve(n_messages, Duration::ZERO, Instant::now())
+            .map(|_| ())
+    }
+
+    /// How many messages can be taken at once, if there is a limit.
+    pub fn burst_size(&self) -> Option<usize> {
+        let state = self.0.lock().unwrap();
+        state.bucket.as_ref().map(|bucket| bucket.capacity as usize)
+    }
+
+    /// Gives back `n_messages` acquired but not sent.
+    pub fn release(&self, n_messages: usize) {
+        let mut state = self.0.lock().unwrap();
+
+        if let Some(bucket) = state.bucket.as_mut() {
+            bucket.refill(Instant::now());
+            bucket.tokens = (bucket.tokens + n_messages as f64).min(bucket.capacity);
+        }
+    }
+
+    /// Stops every holder of this limiter from sending for `duration`.
+    pub fn pause_for(&self, duration: Duration) {
+        let resume_at = Instant::now() + duration;
+        let mut state = self.0.lock().unwrap();
+
+        state.paused_until = Some(match state.paused_until {
+            Some(paused_until) => paused_until.max(resume_at),
+            None => resume_at,
+        });
+    }
+
+    /// How long sending is still paused for, if it is.
+    pub fn paused_for(&self) -> Option<Duration> {
+        let remaining = self.0.lock().unwrap().remaining_pause(Instant::now());
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    /// Takes `n_messages` if they can be sent within `max_wait`, possibly going into
+    /// debt, and returns how long the caller has to wait before the reservation is
+    /// honoured. Otherwise nothing is taken, and the error is how long to wait before
+    /// trying again.
+    fn reserve(
+        &self,
+        n_messages: usize,
+        max_wait: Duration,
+        now: Instant,
+    ) -> Result<Duration, Duration> {
+        let mut state = self.0.lock().unwrap();
+        let pause = state.remaining_pause(now);
+        if pause > max_wait {
+            return Err(pause);
+        }
+
+        let mut wait = pause;
+        if let Some(bucket) = state.bucket.as_mut() {
+            bucket.refill(now);
+            bucket.tokens -= n_messages as f64;
+            wait = wait.max(bucket.time_until_paid_off());
+            if wait > max_wait {
+                bucket.tokens += n_messages as f64;
+                return Err(wait);
+            }
+        }
+
+        Ok(wait)
+    }
+}
+
+impl State {
+    fn remaining_pause(&mut self, now: Instant) -> Duration {
+        match self.paused_until {
+            Some(paused_until) if paused_until > now => paused_until - now,
+            Some(_) => {
+                self.paused_until = None;
+                Duration::ZERO
+            }
+            None => Duration::ZERO,
+        }
+    }
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.tokens_per_second).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    fn time_until_paid_off(&self) -> Duration {
+        if self.tokens < 0.0 {
+            Duration::from_secs_f64(-self.tokens / self.tokens_per_second)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+    use std::time::{Duration, Instant};
+
+    use super::RateLimiter;
+
+    #[test]
+    fn an_unlimited_limiter_never_waits() {
+        let limiter = RateLimiter::unlimited();
+
+        assert_eq!(
+            limiter.reserve(10_000, Duration::MAX, Instant::now()),
+            Ok(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn messages_within_capacity_are_sent_right_away() {
+        let limiter = RateLimiter::new(NonZeroU32::new(10).unwrap(), Duration::from_secs(1));
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve(4, Duration::MAX, now), Ok(Duration::ZERO));
+        assert_eq!(limiter.reserve(6, Duration::MAX, now), Ok(Duration::ZERO));
+    }
+
+    #[test]
+    fn messages_over_capacity_wait_for_the_bucket_to_refill() {
+        let limiter = RateLimiter::new(NonZeroU32::new(10).unwrap(), Duration::from_secs(1));
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve(10, Duration::MAX, now), Ok(Duration::ZERO));
+        assert_eq!(
+            limiter.reserve(5, Duration::MAX, now),
+            Ok(Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn tokens_are_refilled_over_time() {
+        let limiter = RateLimiter::new(NonZeroU32::new(10).unwrap(), Duration::from_secs(1));
+        let now = Instant::now();
+
+        limiter.reserve(10, Duration::MAX, now).unwrap();
+
+        assert_eq!(
+            limiter.reserve(5, Duration::MAX, now + Duration::from_millis(500)),
+            Ok(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn a_paused_limiter_waits_until_resumed() {
+        let limiter = RateLimiter::unlimited();
+        limiter.pause_for(Duration::from_secs(30));
+
+        let wait = limiter.reserve(1, Duration::MAX, Instant::now()).unwrap();
+
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn reserve_takes_nothing_when_it_would_wait_too_long() {
+        let limiter = RateLimiter::new(NonZeroU32::new(10).unwrap(), Duration::from_secs(1));
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve(5, Duration::ZERO, now), Ok(Duration::ZERO));
+        assert_eq!(
+            limiter.reserve(10, Duration::ZERO, now),
+            Err(Duration::from_millis(500))
+        );
+        assert_eq!(limiter.reserve(5, Duration::ZERO, now), Ok(Duration::ZERO));
+    }
+
+    #[test]
+    fn reserve_fails_while_paused_for_too_long() {
+        let limiter = RateLimiter::unlimited();
+        limiter.pause_for(Duration::from_secs(30));
+
+        assert!(limiter.reserve(1, Duration::ZERO, Instant::now()).is_err());
+        assert!(limiter.paused_for().is_some());
+    }
+
+    #[test]
+    fn the_burst_size_is_the_bucket_capacity() {
+        let limiter = RateLimiter::new(NonZeroU32::new(10).unwrap(), Duration::from_secs(1));
+
+        assert_eq!(limiter.burst_size(), Some(10));
+        assert_eq!(RateLimiter::unlimited().burst_size(), None);
+    }
+
+    #[test]
+    fn released_messages_can_be_sent_again() {
+        let limiter = RateLimiter::new(NonZeroU32::new(10).unwrap(), Duration::from_secs(1));
+        let now = Instant::now();
+
+        limiter.reserve(10, Duration::MAX, now).unwrap();
+        limiter.release(6);
+
+        assert_eq!(limiter.reserve(5, Duration::MAX, now), Ok(Duration::ZERO));
+    }
+}
@@ -1,130 +1,417 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
 use std::time::Duration;
 
+use sqlx::postgres::PgListener;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
-use tracing::{Span, field::display};
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::Span;
 use uuid::Uuid;
 
 use crate::{
-    configuration::Settings, domain::SubscriberEmail, email_client::EmailClient,
+    configuration::{Settings, WorkerSettings},
+    domain::SubscriberEmail,
+    email_client::{BatchEmail, DeliveryOutcome, EmailClient, SendEmailError},
     startup::get_connection_pool,
+    tracking::{TrackedLink, TrackedOpen, Tracker, add_open_pixel, rewrite_links},
 };
 
-pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+/// Postgres channel notified whenever new rows are added to `issue_delivery_queue`.
+pub const DELIVERY_QUEUE_CHANNEL: &str = "issue_delivery_queue";
+
+/// Delivers queued issues until `shutdown` is cancelled. Batches already being sent are
+/// completed and committed before returning, so no email is delivered twice.
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
     let connection_pool = get_connection_pool(configuration.database.connection_string());
 
-    let email_client = configuration.email_client.client();
+    let email_client = Arc::new(configuration.email_client.client());
+    let tracker = Arc::new(Tracker::new(
+        configuration.application.base_url,
+        configuration.application.hmac_secret,
+        configuration.application.track_opens,
+    ));
+    let settings = configuration.worker;
+    let wakeup = Arc::new(Notify::new());
+
+    let mut workers = JoinSet::new();
+    workers.spawn(listen_for_new_tasks(
+        connection_pool.clone(),
+        wakeup.clone(),
+        settings.clone(),
+        shutdown.clone(),
+    ));
+    for _ in 0..settings.concurrency.max(1) {
+        workers.spawn(worker_loop(
+            connection_pool.clone(),
+            email_client.clone(),
+            tracker.clone(),
+            wakeup.clone(),
+            settings.clone(),
+            shutdown.clone(),
+        ));
+    }
 
-    worker_loop(&connection_pool, email_client).await
+    while let Some(outcome) = workers.join_next().await {
+        // Bring the remaining workers down with the first one that fails.
+        outcome??;
+    }
+
+    Ok(())
 }
 
-async fn worker_loop(pool: &PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
-    loop {
-        match try_execute_task(pool, &email_client).await {
-            Ok(ExecutionOutcome::EmptyQueue) => tokio::time::sleep(Duration::from_secs(10)).await,
+async fn worker_loop(
+    pool: PgPool,
+    email_client: Arc<EmailClient>,
+    tracker: Arc<Tracker>,
+    wakeup: Arc<Notify>,
+    settings: WorkerSettings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    // The current task is never interrupted, cancellation is only checked between tasks.
+    while !shutdown.is_cancelled() {
+        // Register interest before checking the queue, so a notification sent while
+        // we are dequeuing is not lost.
+        let notified = wakeup.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        match try_execute_task(&pool, &email_client, &settings, &tracker).await {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                // Polling is kept as a fallback in case the listener is disconnected.
+                tokio::select! {
+                    _ = notified => {}
+                    _ = tokio::time::sleep(settings.idle_backoff()) => {}
+                    _ = shutdown.cancelled() => {}
+                }
+            }
             Ok(ExecutionOutcome::TaskCompleted) => {}
-            Err(_) => tokio::time::sleep(Duration::from_secs(1)).await,
+            // The tasks are back in the queue, wait without holding them.
+            Ok(ExecutionOutcome::Throttled(retry_after)) => {
+                tokio::select! {
+                    _ = tokio::time::sleep(retry_after) => {}
+                    _ = shutdown.cancelled() => {}
+                }
+            }
+            Err(_) => {
+                tokio::select! {
+                    _ = tokio::time::sleep(settings.error_backoff()) => {}
+                    _ = shutdown.cancelled() => {}
+                }
+            }
         }
     }
+
+    Ok(())
+}
+
+async fn listen_for_new_tasks(
+    pool: PgPool,
+    wakeup: Arc<Notify>,
+    settings: WorkerSettings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    while !shutdown.is_cancelled() {
+        let outcome = tokio::select! {
+            outcome = forward_notifications(&pool, &wakeup) => outcome,
+            _ = shutdown.cancelled() => break,
+        };
+
+        if let Err(e) = outcome {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Lost connection to the delivery queue channel. \
+                Falling back to polling until it is restored"
+            );
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(settings.error_backoff()) => {}
+            _ = shutdown.cancelled() => {}
+        }
+    }
+
+    Ok(())
+}
+
+async fn forward_notifications(pool: &PgPool, wakeup: &Notify) -> Result<(), anyhow::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(DELIVERY_QUEUE_CHANNEL).await?;
+
+    loop {
+        // `try_recv` yields `None` after losing the connection and reconnects on the next
+        // call. Notifications may have been missed meanwhile, so wake the workers either way.
+        listener.try_recv().await?;
+        wakeup.notify_waiters();
+    }
 }
 
 pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
+    /// The rate limit was reached or the provider asked us to slow down, the tasks were put
+    /// back in the queue untouched.
+    /// Holds how long to wait before trying again.
+    Throttled(Duration),
 }
 
-#[tracing::instrument(
-    skip_all,
-    fields(
-        newsletter_issue_id=tracing::field::Empty,
-        subscriber_email=tracing::field::Empty
-    )
-)]
+#[tracing::instrument(skip_all, fields(n_tasks = tracing::field::Empty))]
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    settings: &WorkerSettings,
+    tracker: &Tracker,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
-    let task = dequeue_task(pool).await?;
+    let batch_size = settings.batch_size.min(email_client.max_reservation());
+    let (mut transaction, tasks) = dequeue_tasks(pool, batch_size).await?;
 
-    if task.is_none() {
+    if tasks.is_empty() {
         return Ok(ExecutionOutcome::EmptyQueue);
     }
 
-    let (transaction, issue_id, email) = task.unwrap();
-    Span::current()
-        .record("newsletter_issue_id", &display(issue_id))
-        .record("subscriber_email", &display(&email));
-
-    match SubscriberEmail::parse(email.clone()) {
-        Ok(email) => {
-            let issue = get_issue(pool, issue_id).await?;
-            if let Err(e) = email_client
-                .send_email(
-                    &email,
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
-                )
-                .await
-            {
+    // Never wait for the rate limiter while holding the tasks' locks: put them back,
+    // the caller waits before trying again.
+    if let Err(retry_after) = email_client.try_reserve(tasks.len()) {
+        transaction.rollback().await?;
+        return Ok(ExecutionOutcome::Throttled(retry_after));
+    }
+
+    Span::current().record("n_tasks", tasks.len());
+
+    let mut issues = HashMap::new();
+    let mut recipients = Vec::with_capacity(tasks.len());
+    let mut progress: HashMap<Uuid, DeliveryProgress> = HashMap::new();
+
+    for task in &tasks {
+        match SubscriberEmail::parse(task.subscriber_email.clone()) {
+            Ok(email) => {
+                if let Entry::Vacant(entry) = issues.entry(task.newsletter_issue_id) {
+                    entry.insert(get_issue(pool, task.newsletter_issue_id).await?);
+                }
+
+                recipients.push((task, email));
+            }
+            Err(e) => {
                 tracing::error!(
                     error.cause_chain = ?e,
                     error.message = %e,
-                    "Failed to deliver issue to a confirmed subscriber. \
-                    Skipping"
+                    newsletter_issue_id = %task.newsletter_issue_id,
+                    subscriber_email = %task.subscriber_email,
+                    "Skipping a confirmed subscriber. \
+                    Their stored contact details are invalid"
                 );
+                delete_task(&mut transaction, task).await?;
+                progress.entry(task.newsletter_issue_id).or_default().failed += 1;
+            }
+        }
+    }
+    email_client.release(tasks.len() - recipients.len());
+
+    let html_contents: Vec<_> = recipients
+        .iter()
+        .map(|(task, _)| html_content_for(&issues[&task.newsletter_issue_id], task, tracker))
+        .collect();
+    let emails: Vec<_> = recipients
+        .iter()
+        .zip(&html_contents)
+        .map(|((task, email), html_content)| {
+            let issue = &issues[&task.newsletter_issue_id];
+            BatchEmail {
+                recipient: email,
+                subject: &issue.title,
+                html_content,
+                text_content: &issue.text_content,
             }
+        })
+        .collect();
+
+    let mut results = match email_client.send_batch(&emails).await {
+        Ok(results) => results.into_iter(),
+        Err(SendEmailError::Throttled(retry_after)) => {
+            tracing::warn!(
+                "The email provider is throttling deliveries. Pausing for {:?}",
+                retry_after
+            );
+            // Release the tasks without counting a retry.
+            transaction.rollback().await?;
+            return Ok(ExecutionOutcome::Throttled(retry_after));
         }
         Err(e) => {
             tracing::error!(
                 error.cause_chain = ?e,
                 error.message = %e,
-                "Skipping a confirmed subscriber. \
-                Their stored contact details are invalid"
+                "Failed to deliver a batch of issues. Retrying later"
             );
+            Vec::new().into_iter()
+        }
+    };
+
+    for (task, _) in &recipients {
+        // A missing result means we do not know whether the provider accepted the message.
+        let outcome = results.next().map(|r| r.outcome()).unwrap_or_else(|| {
+            DeliveryOutcome::Retryable("No delivery result was returned".into())
+        });
+
+        let entry = progress.entry(task.newsletter_issue_id).or_default();
+        match settle_task(&mut transaction, task, outcome, settings).await? {
+            Settlement::Delivered => entry.delivered += 1,
+            Settlement::Failed => entry.failed += 1,
+            Settlement::Retrying => {}
         }
     }
 
-    delete_task(transaction, issue_id, &email).await?;
+    for (issue_id, progress) in progress {
+        record_progress(&mut transaction, issue_id, progress).await?;
+    }
+
+    transaction.commit().await?;
 
     Ok(ExecutionOutcome::TaskCompleted)
 }
 
+/// The HTML content of `issue` as sent to the recipient of `task`, with its links
+/// replaced by tracking redirects and an image recording opens, unless the issue
+/// or the recipient opted out.
+fn html_content_for<'a>(
+    issue: &'a NewsletterIssue,
+    task: &DeliveryTask,
+    tracker: &Tracker,
+) -> Cow<'a, str> {
+    let Some(subscriber_id) = task.tracked_subscriber_id else {
+        return Cow::Borrowed(&issue.html_content);
+    };
+
+    let mut html_content = Cow::Borrowed(issue.html_content.as_str());
+    if issue.track_clicks {
+        html_content = Cow::Owned(rewrite_links(&html_content, |_, url| {
+            tracker.click_url(&TrackedLink {
+                newsletter_issue_id: task.newsletter_issue_id,
+                subscriber_id,
+                url: url.to_string(),
+            })
+        }));
+    }
+    if issue.track_opens && tracker.tracks_opens() {
+        let url = tracker.open_url(&TrackedOpen {
+            newsletter_issue_id: task.newsletter_issue_id,
+            subscriber_id,
+        });
+        html_content = Cow::Owned(add_open_pixel(&html_content, &url));
+    }
+    html_content
+}
+
 type PgTransaction = Transaction<'static, Postgres>;
 
+struct DeliveryTask {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    /// `None` if the subscriber was deleted after the issue was published, or asked
+    /// not to be tracked.
+    tracked_subscriber_id: Option<Uuid>,
+    n_retries: i32,
+}
+
 #[tracing::instrument(skip_all)]
-async fn dequeue_task(
+async fn dequeue_tasks(
     pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+    batch_size: usize,
+) -> Result<(PgTransaction, Vec<DeliveryTask>), anyhow::Error> {
     let mut transaction = pool.begin().await?;
 
-    let r = sqlx::query!(
+    let tasks = sqlx::query_as!(
+        DeliveryTask,
         r#"
-    SELECT newsletter_issue_id, subscriber_email
-    FROM issue_delivery_queue
+    SELECT
+        newsletter_issue_id,
+        subscriber_email,
+        (
+            SELECT id FROM subscriptions s
+            WHERE s.email = q.subscriber_email AND NOT s.do_not_track
+        ) AS tracked_subscriber_id,
+        n_retries
+    FROM issue_delivery_queue q
+    WHERE execute_after <= now()
     FOR UPDATE
     SKIP LOCKED
-    LIMIT 1
+    LIMIT $1
     "#,
+        batch_size as i64
     )
-    .fetch_optional(&mut *transaction)
+    .fetch_all(&mut *transaction)
     .await?;
 
-    if let Some(r) = r {
-        Ok(Some((
-            transaction,
-            r.newsletter_issue_id,
-            r.subscriber_email,
-        )))
-    } else {
-        Ok(None)
+    Ok((transaction, tasks))
+}
+
+enum Settlement {
+    Delivered,
+    Failed,
+    Retrying,
+}
+
+#[derive(Default)]
+struct DeliveryProgress {
+    delivered: i32,
+    failed: i32,
+}
+
+async fn settle_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    outcome: DeliveryOutcome,
+    settings: &WorkerSettings,
+) -> Result<Settlement, anyhow::Error> {
+    match outcome {
+        DeliveryOutcome::Delivered => {
+            delete_task(transaction, task).await?;
+            Ok(Settlement::Delivered)
+        }
+        DeliveryOutcome::Rejected(reason) => {
+            tracing::error!(
+                newsletter_issue_id = %task.newsletter_issue_id,
+                subscriber_email = %task.subscriber_email,
+                reason = %reason,
+                "The email provider rejected an issue for a confirmed subscriber. Skipping"
+            );
+            delete_task(transaction, task).await?;
+            Ok(Settlement::Failed)
+        }
+        DeliveryOutcome::Retryable(reason) if task.n_retries >= settings.max_retries => {
+            tracing::error!(
+                newsletter_issue_id = %task.newsletter_issue_id,
+                subscriber_email = %task.subscriber_email,
+                reason = %reason,
+                "Failed to deliver issue to a confirmed subscriber after {} retries. Skipping",
+                task.n_retries
+            );
+            delete_task(transaction, task).await?;
+            Ok(Settlement::Failed)
+        }
+        DeliveryOutcome::Retryable(reason) => {
+            tracing::warn!(
+                newsletter_issue_id = %task.newsletter_issue_id,
+                subscriber_email = %task.subscriber_email,
+                reason = %reason,
+                "Failed to deliver issue to a confirmed subscriber. Retrying later"
+            );
+            retry_task(transaction, task, settings).await?;
+            Ok(Settlement::Retrying)
+        }
     }
 }
 
 #[tracing::instrument(skip_all)]
 async fn delete_task(
-    mut transaction: PgTransaction,
-    issue_id: Uuid,
-    email: &str,
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
 ) -> Result<(), anyhow::Error> {
     let query = sqlx::query!(
         r#"
@@ -132,13 +419,62 @@ async fn delete_task(
     WHERE
         newsletter_issue_id = $1 AND
         subscriber_email = $2
+    "#,
+        task.newsletter_issue_id,
+        task.subscriber_email
+    );
+
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn retry_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    settings: &WorkerSettings,
+) -> Result<(), anyhow::Error> {
+    // Exponential backoff: the delay doubles with every failed attempt.
+    let query = sqlx::query!(
+        r#"
+    UPDATE issue_delivery_queue
+    SET
+        n_retries = n_retries + 1,
+        execute_after = now() + make_interval(secs => $3 * power(2, n_retries))
+    WHERE
+        newsletter_issue_id = $1 AND
+        subscriber_email = $2
+    "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        settings.retry_backoff().as_secs_f64()
+    );
+
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+/// Counts are updated once per batch to limit contention on the issue row.
+#[tracing::instrument(skip_all)]
+async fn record_progress(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    progress: DeliveryProgress,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+    UPDATE newsletter_issues
+    SET
+        n_delivered = n_delivered + $2,
+        n_failed = n_failed + $3
+    WHERE newsletter_issue_id = $1
     "#,
         issue_id,
-        email
+        progress.delivered,
+        progress.failed
     );
 
     transaction.execute(query).await?;
-    transaction.commit().await?;
     Ok(())
 }
 
@@ -146,6 +482,8 @@ struct NewsletterIssue {
     title: String,
     text_content: String,
     html_content: String,
+    track_clicks: bool,
+    track_opens: bool,
 }
 
 #[tracing::instrument(skip_all)]
@@ -153,7 +491,7 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
     let issue = sqlx::query_as!(
         NewsletterIssue,
         r#"
-        SELECT title, text_content, html_content
+        SELECT title, text_content, html_content, track_clicks, track_opens
         FROM newsletter_issues
         WHERE
             newsletter_issue_id = $1
@@ -1,12 +1,19 @@
+pub mod audit;
 pub mod authentication;
 pub mod configuration;
+pub mod consent;
 pub mod domain;
 pub mod email_client;
 pub mod idempotency;
 pub mod issue_delivery_worker;
 pub mod metrics;
+pub mod newsletter_issues;
+pub mod personal_data;
 pub mod routes;
 pub mod session_state;
 pub mod startup;
+pub mod subscriber_imports;
+pub mod subscribers;
 pub mod telemetry;
+pub mod tracking;
 pub mod utils;
@@ -1,16 +1,154 @@
 use std::fmt::{Debug, Display};
+use std::io::BufRead;
 
-use newsletter_backend::configuration::get_configuration;
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand};
+use newsletter_backend::authentication::{
+    Role, change_password, create_user, get_user_id, list_users,
+};
+use newsletter_backend::configuration::{Settings, get_configuration};
+use newsletter_backend::domain::{AdminPassword, SubscriberEmail};
 use newsletter_backend::issue_delivery_worker::run_worker_until_stopped;
 use newsletter_backend::metrics::init_prometheus_exporter;
-use newsletter_backend::startup::Application;
+use newsletter_backend::startup::{Application, get_connection_pool, run_migrations};
 use newsletter_backend::telemetry::{get_opentelemetry_parts, get_subscriber, init_subscriber};
-use tokio::task::JoinError;
+use secrecy::{ExposeSecret, Secret};
+use tokio::task::{JoinError, JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Parser)]
+#[command(about = "Newsletter backend")]
+struct Cli {
+    /// Defaults to running both the API and the delivery worker.
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the HTTP API only
+    Serve,
+    /// Run the issue delivery worker only
+    Worker,
+    /// Run both the HTTP API and the issue delivery worker
+    All,
+    /// Apply pending database migrations
+    Migrate,
+    /// Create a new admin user
+    CreateUser(CreateUserArgs),
+    /// Set a new password for an existing admin user
+    ResetPassword(PasswordArgs),
+    /// List admin users
+    ListUsers,
+}
+
+#[derive(Args)]
+struct CreateUserArgs {
+    #[command(flatten)]
+    credentials: PasswordArgs,
+    /// One of `owner`, `editor` or `viewer`
+    #[arg(long, default_value = "owner", value_parser = parse_role)]
+    role: Role,
+    /// Address used to send password reset links
+    #[arg(long, value_parser = parse_email)]
+    email: Option<SubscriberEmail>,
+}
+
+fn parse_role(role: &str) -> Result<Role, String> {
+    Role::try_from(role.to_string())
+}
+
+fn parse_email(email: &str) -> Result<SubscriberEmail, String> {
+    SubscriberEmail::parse(email.to_string())
+}
+
+#[derive(Args)]
+struct PasswordArgs {
+    username: String,
+    /// Read the password from the first line of stdin instead of prompting for it
+    #[arg(long)]
+    password_stdin: bool,
+}
+
+impl Command {
+    fn runs_api(&self) -> bool {
+        matches!(self, Self::Serve | Self::All)
+    }
+
+    fn runs_worker(&self) -> bool {
+        matches!(self, Self::Worker | Self::All)
+    }
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let command = Cli::parse().command.unwrap_or(Command::All);
+
     let configuration = get_configuration().expect("Error reading initial configuration");
 
+    match command {
+        Command::Migrate => {
+            let pool = get_connection_pool(configuration.database.connection_string());
+            run_migrations(&pool).await?;
+            println!("Migrations applied.");
+        }
+        Command::CreateUser(args) => {
+            let pool = get_connection_pool(configuration.database.connection_string());
+            let username = args.credentials.username;
+            let password = read_new_password(args.credentials.password_stdin)?;
+            let user_id =
+                create_user(&username, args.email.as_ref(), password, args.role, &pool).await?;
+            println!("Created {} {} with id {}.", args.role, username, user_id);
+        }
+        Command::ResetPassword(args) => {
+            let pool = get_connection_pool(configuration.database.connection_string());
+            let user_id = get_user_id(&args.username, &pool)
+                .await?
+                .with_context(|| format!("There is no user named {}.", args.username))?;
+            let password = read_new_password(args.password_stdin)?;
+            change_password(user_id, password, &pool).await?;
+            println!("Password changed for {}.", args.username);
+        }
+        Command::ListUsers => {
+            let pool = get_connection_pool(configuration.database.connection_string());
+            for user in list_users(&pool).await? {
+                let status = if user.is_active {
+                    "active"
+                } else {
+                    "deactivated"
+                };
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    user.user_id, user.username, user.role, status
+                );
+            }
+        }
+        command => run(command, configuration).await?,
+    }
+
+    Ok(())
+}
+
+fn read_new_password(from_stdin: bool) -> Result<AdminPassword, anyhow::Error> {
+    let password = if from_stdin {
+        let mut line = String::new();
+        std::io::stdin().lock().read_line(&mut line)?;
+        Secret::new(line.trim_end_matches(['\r', '\n']).to_string())
+    } else {
+        let password = Secret::new(rpassword::prompt_password("New password: ")?);
+        let password_check = Secret::new(rpassword::prompt_password("Confirm new password: ")?);
+
+        if password.expose_secret() != password_check.expose_secret() {
+            anyhow::bail!("You entered two different passwords - the values must match.");
+        }
+
+        password
+    };
+
+    AdminPassword::new(password.expose_secret().clone()).map_err(anyhow::Error::msg)
+}
+
+async fn run(command: Command, configuration: Settings) -> Result<(), anyhow::Error> {
     let (tracer, provider) = get_opentelemetry_parts(
         &configuration.tracer.export_endpoint,
         configuration.tracer.sampling_ratio,
@@ -21,16 +159,49 @@ async fn main() -> anyhow::Result<()> {
 
     init_prometheus_exporter();
 
-    let configuration = get_configuration().expect("Error reading initial configuration");
+    let shutdown_timeout = configuration.application.shutdown_timeout();
+    let shutdown = CancellationToken::new();
+    let mut tasks = Vec::new();
+
+    if command.runs_api() {
+        let server = Application::build(configuration.clone()).await?;
+        let server_task = tokio::spawn(server.run_until_stopped(shutdown.clone()));
+        tasks.push(tokio::spawn(supervise(
+            "API",
+            server_task,
+            shutdown.clone(),
+        )));
+    }
 
-    let server = Application::build(configuration.clone()).await?;
-    let server_task = tokio::spawn(server.run_until_stopped());
-    let worker_task = tokio::spawn(run_worker_until_stopped(configuration));
+    if command.runs_worker() {
+        let worker_task = tokio::spawn(run_worker_until_stopped(configuration, shutdown.clone()));
+        tasks.push(tokio::spawn(supervise(
+            "Background worker",
+            worker_task,
+            shutdown.clone(),
+        )));
+    }
 
     tokio::select! {
-        o = server_task => report_exit("API", o),
-        o = worker_task => report_exit("Background worker", o),
+        _ = shutdown_signal() => tracing::info!("Shutdown signal received"),
+        _ = shutdown.cancelled() => {}
     };
+    shutdown.cancel();
+
+    // Stop accepting requests, let the worker finish its current batch, then flush traces.
+    if tokio::time::timeout(shutdown_timeout, async {
+        for task in tasks {
+            let _ = task.await;
+        }
+    })
+    .await
+    .is_err()
+    {
+        tracing::warn!(
+            "Tasks did not stop within {:?}, shutting down anyway",
+            shutdown_timeout
+        );
+    }
 
     provider
         .shutdown()
@@ -39,6 +210,39 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Reports how `task` exited and shuts down the rest of the application with it.
+async fn supervise<E>(task_name: &str, task: JoinHandle<Result<(), E>>, shutdown: CancellationToken)
+where
+    E: Debug + Display,
+{
+    report_exit(task_name, task.await);
+    shutdown.cancel();
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
 fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
     match outcome {
         Ok(Ok(_)) => {
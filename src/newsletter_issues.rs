@@ -0,0 +1,252 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::issue_delivery_worker::DELIVERY_QUEUE_CHANNEL;
+
+pub struct NewsletterIssue {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    /// Whether links are replaced by redirects recording clicks.
+    pub track_clicks: bool,
+    /// Whether an image recording opens is added to the HTML content.
+    pub track_opens: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// `None` while the issue is a draft.
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+pub struct NewsletterIssueSummary {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+/// What is recorded about how recipients read an issue.
+#[derive(Clone, Copy, Debug)]
+pub struct IssueTracking {
+    pub clicks: bool,
+    /// Only tracked if open tracking is also enabled in the configuration.
+    pub opens: bool,
+}
+
+/// Every recipient of a published issue is either delivered, failed or still pending.
+pub struct DeliveryStatus {
+    pub published_at: Option<DateTime<Utc>>,
+    pub recipients: i32,
+    pub delivered: i32,
+    pub failed: i32,
+    pub pending: i64,
+}
+
+/// Stores a new draft. Nothing is delivered until [`enqueue_delivery_tasks`] is called.
+#[tracing::instrument(skip_all)]
+pub async fn insert_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    title: &str,
+    text_content: &str,
+    html_content: &str,
+    tracking: IssueTracking,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletters_issue_id = Uuid::new_v4();
+
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            track_clicks,
+            track_opens,
+            created_at,
+            updated_at
+        )
+        VALUES($1, $2, $3, $4, $5, $6, now(), now())
+        "#,
+        newsletters_issue_id,
+        title,
+        text_content,
+        html_content,
+        tracking.clicks,
+        tracking.opens
+    );
+
+    transaction.execute(query).await?;
+
+    Ok(newsletters_issue_id)
+}
+
+/// Replaces the content of a draft. Returns `false` if there is no such draft,
+/// including when the issue has already been published.
+#[tracing::instrument(skip_all, fields(newsletter_issue_id = %newsletter_issue_id))]
+pub async fn update_draft_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    title: &str,
+    text_content: &str,
+    html_content: &str,
+    tracking: IssueTracking,
+) -> Result<bool, sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            title = $2,
+            text_content = $3,
+            html_content = $4,
+            track_clicks = $5,
+            track_opens = $6,
+            updated_at = now()
+        WHERE newsletter_issue_id = $1 AND published_at IS NULL
+        "#,
+        newsletter_issue_id,
+        title,
+        text_content,
+        html_content,
+        tracking.clicks,
+        tracking.opens
+    );
+
+    Ok(transaction.execute(query).await?.rows_affected() > 0)
+}
+
+/// Publishes the issue to every confirmed subscriber.
+#[tracing::instrument(skip_all, fields(newsletter_issue_id = %newsletter_issue_id))]
+pub async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+    INSERT INTO issue_delivery_queue (
+        newsletter_issue_id,
+        subscriber_email
+    )
+    SELECT $1, email FROM subscriptions WHERE status = 'confirmed'
+    "#,
+        newsletter_issue_id
+    );
+
+    let n_recipients = transaction.execute(query).await?.rows_affected();
+
+    let query = sqlx::query!(
+        r#"
+    UPDATE newsletter_issues
+    SET published_at = now(), n_recipients = $2
+    WHERE newsletter_issue_id = $1
+    "#,
+        newsletter_issue_id,
+        n_recipients as i32
+    );
+    transaction.execute(query).await?;
+
+    // Delivered on commit, waking up idle delivery workers.
+    let query = sqlx::query!("SELECT pg_notify($1, '')", DELIVERY_QUEUE_CHANNEL);
+    transaction.execute(query).await?;
+
+    Ok(())
+}
+
+/// Locks the issue until the end of `transaction`, so it can't be published twice.
+/// Returns whether it has already been published, or `None` if it does not exist.
+#[tracing::instrument(skip_all, fields(newsletter_issue_id = %newsletter_issue_id))]
+pub async fn lock_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<bool>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT published_at IS NOT NULL AS "is_published!"
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        FOR UPDATE
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await?;
+
+    Ok(row.map(|r| r.is_published))
+}
+
+#[tracing::instrument(name = "Get newsletter issue", skip(executor))]
+pub async fn get_newsletter_issue<'e>(
+    newsletter_issue_id: Uuid,
+    executor: impl Executor<'e, Database = Postgres>,
+) -> Result<Option<NewsletterIssue>, anyhow::Error> {
+    sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            track_clicks,
+            track_opens,
+            created_at,
+            updated_at,
+            published_at
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to perform a query to retrieve a newsletter issue")
+}
+
+/// Most recently created first.
+#[tracing::instrument(name = "List newsletter issues", skip(pool))]
+pub async fn list_newsletter_issues(
+    pool: &PgPool,
+) -> Result<Vec<NewsletterIssueSummary>, anyhow::Error> {
+    sqlx::query_as!(
+        NewsletterIssueSummary,
+        r#"
+        SELECT newsletter_issue_id, title, created_at, updated_at, published_at
+        FROM newsletter_issues
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to list newsletter issues")
+}
+
+/// Returns `None` if there is no such issue. Drafts have no recipients yet.
+#[tracing::instrument(name = "Get delivery status", skip(pool))]
+pub async fn get_delivery_status(
+    newsletter_issue_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<DeliveryStatus>, anyhow::Error> {
+    sqlx::query_as!(
+        DeliveryStatus,
+        r#"
+        SELECT
+            published_at,
+            n_recipients AS recipients,
+            n_delivered AS delivered,
+            n_failed AS failed,
+            (
+                SELECT count(*)
+                FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.newsletter_issue_id
+            ) AS "pending!"
+        FROM newsletter_issues i
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve the delivery status of an issue")
+}
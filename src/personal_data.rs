@@ -0,0 +1,378 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{Executor, PgPool};
+use uuid::Uuid;
+
+use crate::consent::ConsentEvent;
+use crate::domain::SubscriberEmail;
+use crate::utils::{generate_token, hash_token};
+
+/// How long a link sent for a data request can be used for.
+const DATA_REQUEST_VALIDITY: Duration = Duration::hours(24);
+
+/// Everything stored about an email address, as handed over on a data subject access request.
+#[derive(serde::Serialize)]
+pub struct PersonalData {
+    pub email: String,
+    pub exported_at: DateTime<Utc>,
+    pub subscription: Option<SubscriptionData>,
+    /// Proof of the signup and confirmation of the subscription.
+    pub consent_events: Vec<ConsentEvent>,
+    /// Tokens of the confirmation links sent to the subscriber.
+    pub subscription_tokens: Vec<String>,
+    /// Issues not delivered yet. Delivered issues are not recorded per subscriber.
+    pub pending_deliveries: Vec<PendingDelivery>,
+    /// Rows of subscriber imports which were not imported.
+    pub import_rejections: Vec<ImportRejection>,
+    /// Links of issues followed by the subscriber.
+    pub link_clicks: Vec<LinkClick>,
+    /// Issues opened by the subscriber.
+    pub email_opens: Vec<EmailOpen>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SubscriptionData {
+    pub subscriber_id: Uuid,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+    pub do_not_track: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct PendingDelivery {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub n_retries: i32,
+    pub execute_after: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ImportRejection {
+    pub import_id: Uuid,
+    pub imported_at: DateTime<Utc>,
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct LinkClick {
+    pub newsletter_issue_id: Uuid,
+    pub url: String,
+    pub clicked_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+pub struct EmailOpen {
+    pub newsletter_issue_id: Uuid,
+    pub first_opened_at: DateTime<Utc>,
+    pub last_opened_at: DateTime<Utc>,
+    pub n_opens: i32,
+}
+
+impl PersonalData {
+    pub fn is_empty(&self) -> bool {
+        self.subscription.is_none()
+            && self.subscription_tokens.is_empty()
+            && self.pending_deliveries.is_empty()
+            && self.import_rejections.is_empty()
+    }
+}
+
+#[tracing::instrument(name = "Collect personal data", skip(pool))]
+pub async fn collect_personal_data(
+    email: &str,
+    pool: &PgPool,
+) -> Result<PersonalData, anyhow::Error> {
+    let subscription = sqlx::query_as!(
+        SubscriptionData,
+        r#"
+        SELECT id AS subscriber_id, name, status, subscribed_at, do_not_track
+        FROM subscriptions
+        WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve a subscription")?;
+
+    let consent_events = sqlx::query_as!(
+        ConsentEvent,
+        r#"
+        SELECT c.event, c.source, c.ip_address, c.user_agent, c.occurred_at
+        FROM consent_events c
+        JOIN subscriptions s ON s.id = c.subscriber_id
+        WHERE s.email = $1
+        ORDER BY c.occurred_at, c.consent_event_id
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve consent events")?;
+
+    let subscription_tokens = sqlx::query!(
+        r#"
+        SELECT t.subscription_token
+        FROM subscription_tokens t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE s.email = $1
+        ORDER BY t.subscription_token
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve subscription tokens")?
+    .into_iter()
+    .map(|r| r.subscription_token)
+    .collect();
+
+    let pending_deliveries = sqlx::query_as!(
+        PendingDelivery,
+        r#"
+        SELECT q.newsletter_issue_id, i.title, q.n_retries, q.execute_after
+        FROM issue_delivery_queue q
+        JOIN newsletter_issues i ON i.newsletter_issue_id = q.newsletter_issue_id
+        WHERE q.subscriber_email = $1
+        ORDER BY q.execute_after
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve pending deliveries")?;
+
+    let import_rejections = sqlx::query_as!(
+        ImportRejection,
+        r#"
+        SELECT r.import_id, i.created_at AS imported_at, r.name, r.reason
+        FROM subscriber_import_rejections r
+        JOIN subscriber_imports i ON i.import_id = r.import_id
+        WHERE r.email = $1
+        ORDER BY i.created_at, r.csv_row
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve rejected import rows")?;
+
+    let link_clicks = sqlx::query_as!(
+        LinkClick,
+        r#"
+        SELECT c.newsletter_issue_id, c.url, c.clicked_at
+        FROM link_clicks c
+        JOIN subscriptions s ON s.id = c.subscriber_id
+        WHERE s.email = $1
+        ORDER BY c.clicked_at, c.link_click_id
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve link clicks")?;
+
+    let email_opens = sqlx::query_as!(
+        EmailOpen,
+        r#"
+        SELECT o.newsletter_issue_id, o.first_opened_at, o.last_opened_at, o.n_opens
+        FROM email_opens o
+        JOIN subscriptions s ON s.id = o.subscriber_id
+        WHERE s.email = $1
+        ORDER BY o.first_opened_at, o.newsletter_issue_id
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve opened issues")?;
+
+    Ok(PersonalData {
+        email: email.to_string(),
+        exported_at: Utc::now(),
+        subscription,
+        consent_events,
+        subscription_tokens,
+        pending_deliveries,
+        import_rejections,
+        link_clicks,
+        email_opens,
+    })
+}
+
+/// Deletes everything stored about `email`. Returns `false` if nothing was stored.
+#[tracing::instrument(name = "Erase personal data", skip(pool))]
+pub async fn erase_personal_data(email: &str, pool: &PgPool) -> Result<bool, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let queries = [
+        sqlx::query!(
+            r#"
+            DELETE FROM subscription_tokens
+            WHERE subscriber_id IN (SELECT id FROM subscriptions WHERE email = $1)
+            "#,
+            email
+        ),
+        sqlx::query!(
+            r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+            email
+        ),
+        sqlx::query!(
+            r#"DELETE FROM subscriber_import_rejections WHERE email = $1"#,
+            email
+        ),
+        // Consent events, link clicks and opens are deleted with the subscription.
+        sqlx::query!(r#"DELETE FROM subscriptions WHERE email = $1"#, email),
+        sqlx::query!(r#"DELETE FROM data_request_tokens WHERE email = $1"#, email),
+    ];
+    let mut n_deleted = 0;
+    for query in queries {
+        n_deleted += transaction
+            .execute(query)
+            .await
+            .context("Failed to erase personal data")?
+            .rows_affected();
+    }
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to erase personal data")?;
+
+    Ok(n_deleted > 0)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataRequestKind {
+    Access,
+    Erasure,
+}
+
+impl DataRequestKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataRequestKind::Access => "access",
+            DataRequestKind::Erasure => "erasure",
+        }
+    }
+}
+
+impl TryFrom<String> for DataRequestKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "access" => Ok(DataRequestKind::Access),
+            "erasure" => Ok(DataRequestKind::Erasure),
+            other => Err(format!(
+                "{} is not a valid data request. Use either `access` or `erasure`.",
+                other
+            )),
+        }
+    }
+}
+
+pub struct DataRequest {
+    pub email: SubscriberEmail,
+    pub kind: DataRequestKind,
+}
+
+/// Issues a token confirming that the owner of `email` makes the request. Returns `None`
+/// if nothing is stored about `email`, so there is nobody to send it to.
+/// Only a hash of the token is kept in the database.
+#[tracing::instrument(name = "Create data request token", skip(pool))]
+pub async fn create_data_request_token(
+    email: &SubscriberEmail,
+    kind: DataRequestKind,
+    pool: &PgPool,
+) -> Result<Option<String>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM subscriptions WHERE email = $1) AS "exists!""#,
+        email.as_ref()
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform a query to look up a subscription")?;
+    if !row.exists {
+        return Ok(None);
+    }
+
+    let token = generate_token();
+    let now = Utc::now();
+    sqlx::query!(
+        r#"
+        INSERT INTO data_request_tokens (token_hash, email, kind, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        hash_token(&token),
+        email.as_ref(),
+        kind.as_str(),
+        now,
+        now + DATA_REQUEST_VALIDITY
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store a new data request token")?;
+
+    Ok(Some(token))
+}
+
+/// Returns the request `token` was issued for, or `None` if it is unknown or has expired.
+#[tracing::instrument(name = "Get data request", skip(token, pool))]
+pub async fn get_data_request(
+    token: &str,
+    pool: &PgPool,
+) -> Result<Option<DataRequest>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT email, kind
+        FROM data_request_tokens
+        WHERE token_hash = $1 AND expires_at > now()
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve a data request")?;
+
+    row.map(|r| {
+        Ok(DataRequest {
+            email: SubscriberEmail::parse(r.email).map_err(anyhow::Error::msg)?,
+            kind: r.kind.try_into().map_err(anyhow::Error::msg)?,
+        })
+    })
+    .transpose()
+}
+
+/// Like [`get_data_request`], but the token can't be used again.
+#[tracing::instrument(name = "Consume data request token", skip(token, pool))]
+pub async fn consume_data_request_token(
+    token: &str,
+    pool: &PgPool,
+) -> Result<Option<DataRequest>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        DELETE FROM data_request_tokens
+        WHERE token_hash = $1
+        RETURNING email, kind, expires_at > now() AS "is_valid!"
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to consume a data request token")?;
+
+    row.filter(|r| r.is_valid)
+        .map(|r| {
+            Ok(DataRequest {
+                email: SubscriberEmail::parse(r.email).map_err(anyhow::Error::msg)?,
+                kind: r.kind.try_into().map_err(anyhow::Error::msg)?,
+            })
+        })
+        .transpose()
+}
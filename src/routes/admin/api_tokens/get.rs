@@ -0,0 +1,111 @@
+use std::fmt::Write;
+use std::ops::Deref;
+
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::authentication::{Scope, UserId, list_api_tokens};
+use crate::utils::{e500, escape_html, flash_messages_html};
+
+#[tracing::instrument(
+    name = "Get API tokens page"
+    skip(flash_messages, pool, user_id)
+    fields(user_id = %user_id.deref())
+)]
+pub async fn api_tokens_page(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let msg_html = flash_messages_html(&flash_messages);
+
+    let format_date = |date: Option<DateTime<Utc>>, default: &str| {
+        date.map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_else(|| default.to_string())
+    };
+
+    let mut tokens_html = String::new();
+    for token in list_api_tokens(**user_id, &pool).await.map_err(e500)? {
+        let scopes: Vec<_> = token.scopes.iter().map(Scope::as_str).collect();
+        writeln!(
+            tokens_html,
+            r#"<tr>
+                <td>{name}</td>
+                <td>{scopes}</td>
+                <td>{created_at}</td>
+                <td>{expires_at}</td>
+                <td>{last_used_at}</td>
+                <td>
+                    <form action="/admin/api-tokens/{api_token_id}/delete" method="post">
+                        <button type="submit">revoke</button>
+                    </form>
+                </td>
+            </tr>"#,
+            name = escape_html(&token.name),
+            scopes = scopes.join(", "),
+            created_at = format_date(Some(token.created_at), ""),
+            expires_at = format_date(token.expires_at, "never"),
+            last_used_at = format_date(token.last_used_at, "never"),
+            api_token_id = token.api_token_id,
+        )
+        .unwrap();
+    }
+
+    let mut scopes_html = String::new();
+    for scope in Scope::ALL {
+        writeln!(
+            scopes_html,
+            r#"<label><input type="checkbox" name="{scope}"> {scope}</label>"#
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>API tokens</title>
+    </head>
+    <body>
+        <p>API tokens</p>
+        {msg_html}
+        <table>
+            <tr><th>Name</th><th>Scopes</th><th>Created</th><th>Expires</th><th>Last used</th><th></th></tr>
+            {tokens_html}
+        </table>
+
+        <p>Create a new token</p>
+        <form action="/admin/api-tokens" method="post">
+            <label>Name
+                <input
+                    type="text"
+                    placeholder="What the token is used for"
+                    name="name"
+                >
+            </label>
+
+            {scopes_html}
+
+            <label>Expires
+                <select name="expires_in_days">
+                    <option value="30">in 30 days</option>
+                    <option value="90">in 90 days</option>
+                    <option value="365">in a year</option>
+                    <option value="">never</option>
+                </select>
+            </label>
+
+            <button type="submit">Create token</button>
+        </form>
+
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#
+        )))
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::api_tokens_page;
+pub use post::{delete_api_token, new_api_token};
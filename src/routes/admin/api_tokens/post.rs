@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use actix_web::{HttpRequest, HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::FlashMessage;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditTarget, record_audit_event};
+use crate::authentication::{Scope, UserId, create_api_token, revoke_api_token};
+use crate::consent::RequestMetadata;
+use crate::utils::{e500, escape_html, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    name: String,
+    expires_in_days: String,
+    /// One checkbox per scope, named after it.
+    #[serde(flatten)]
+    checked: HashMap<String, String>,
+}
+
+#[tracing::instrument(
+    name = "Create an API token",
+    skip(form, pool, user_id, request),
+    fields(user_id = %user_id.deref(), name = %form.name)
+)]
+pub async fn new_api_token(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let name = form.name.trim();
+    if name.is_empty() {
+        FlashMessage::error("The token name cannot be empty.").send();
+        return Ok(see_other("/admin/api-tokens"));
+    }
+
+    let scopes: Vec<_> = Scope::ALL
+        .into_iter()
+        .filter(|scope| form.checked.contains_key(scope.as_str()))
+        .collect();
+    if scopes.is_empty() {
+        FlashMessage::error("Select at least one scope.").send();
+        return Ok(see_other("/admin/api-tokens"));
+    }
+
+    let expires_at = match form.expires_in_days.as_str() {
+        "" => None,
+        days => match days.parse() {
+            Ok(days) if days > 0 => Some(Utc::now() + Duration::days(days)),
+            _ => {
+                FlashMessage::error("The expiry must be a positive number of days.").send();
+                return Ok(see_other("/admin/api-tokens"));
+            }
+        },
+    };
+
+    let token = create_api_token(**user_id, name, &scopes, expires_at, &pool)
+        .await
+        .map_err(e500)?;
+    record_audit_event(
+        pool.get_ref(),
+        **user_id,
+        AuditAction::CreateApiToken,
+        None,
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
+
+    // Only a hash of the token is stored, this is the only time it is shown.
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>API tokens</title>
+    </head>
+    <body>
+        <p>Your new token {name}:</p>
+        <p><code>{token}</code></p>
+        <p>
+            Send it in an <code>Authorization: Bearer</code> header.
+            Copy it now, it will not be shown again.
+        </p>
+        <p><a href="/admin/api-tokens">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+            name = escape_html(name),
+        )))
+}
+
+#[tracing::instrument(
+    name = "Revoke an API token",
+    skip(pool, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn delete_api_token(
+    api_token_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let api_token_id = api_token_id.into_inner();
+    if revoke_api_token(**user_id, api_token_id, &pool)
+        .await
+        .map_err(e500)?
+    {
+        record_audit_event(
+            pool.get_ref(),
+            **user_id,
+            AuditAction::RevokeApiToken,
+            Some(AuditTarget::ApiToken(api_token_id)),
+            &RequestMetadata::from_request(&request),
+        )
+        .await
+        .map_err(e500)?;
+        FlashMessage::info("The token has been revoked.").send();
+    } else {
+        FlashMessage::error("The token does not exist.").send();
+    }
+
+    Ok(see_other("/admin/api-tokens"))
+}
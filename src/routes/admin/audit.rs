@@ -0,0 +1,146 @@
+use std::fmt::Write;
+
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use sqlx::PgPool;
+
+use crate::audit::{AuditAction, count_audit_log, list_audit_log};
+use crate::utils::{e500, escape_html};
+
+const PAGE_SIZE: i64 = 50;
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    /// Empty, as sent by the filter form, to show every action.
+    action: Option<String>,
+    page: Option<i64>,
+}
+
+#[tracing::instrument(name = "Get audit log page", skip(query, pool))]
+pub async fn audit_log_page(
+    query: web::Query<QueryParams>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let query = query.into_inner();
+
+    let mut msg_html = String::new();
+    let action = query.action.filter(|action| !action.is_empty());
+    let filter = match action.map(AuditAction::try_from).transpose() {
+        Ok(filter) => filter,
+        Err(e) => {
+            writeln!(msg_html, "<p><i>{}</i></p>", escape_html(&e)).unwrap();
+            None
+        }
+    };
+
+    let n_entries = count_audit_log(filter, &pool).await.map_err(e500)?;
+    let n_pages = ((n_entries + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+    let page = query.page.unwrap_or(1).clamp(1, n_pages);
+    let entries = list_audit_log(filter, PAGE_SIZE, (page - 1) * PAGE_SIZE, &pool)
+        .await
+        .map_err(e500)?;
+
+    let mut rows_html = String::new();
+    for entry in entries {
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td>{occurred_at}</td>
+                <td>{user}</td>
+                <td>{action}</td>
+                <td>{target}</td>
+                <td>{ip_address}</td>
+                <td>{user_agent}</td>
+            </tr>"#,
+            occurred_at = entry.occurred_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            // Deleted users are shown by id.
+            user = escape_html(&entry.username.unwrap_or_else(|| entry.user_id.to_string())),
+            action = escape_html(&entry.action),
+            target = escape_html(entry.target.as_deref().unwrap_or("")),
+            ip_address = escape_html(entry.ip_address.as_deref().unwrap_or("")),
+            user_agent = escape_html(entry.user_agent.as_deref().unwrap_or("")),
+        )
+        .unwrap();
+    }
+
+    let mut action_options = String::from(r#"<option value="">any</option>"#);
+    for option in AuditAction::ALL {
+        let selected = if filter == Some(option) {
+            " selected"
+        } else {
+            ""
+        };
+        write!(
+            action_options,
+            r#"<option value="{option}"{selected}>{option}</option>"#
+        )
+        .unwrap();
+    }
+
+    // The filter is resubmitted with the page number, so it survives paging.
+    let hidden_filter = format!(
+        r#"<input type="hidden" name="action" value="{}">"#,
+        filter.map(|action| action.as_str()).unwrap_or("")
+    );
+    let mut pagination_html = String::new();
+    if page > 1 {
+        writeln!(
+            pagination_html,
+            r#"<form action="/admin/audit" method="get">
+            {hidden_filter}
+            <button type="submit" name="page" value="{}">&lt; Previous</button>
+        </form>"#,
+            page - 1
+        )
+        .unwrap();
+    }
+    if page < n_pages {
+        writeln!(
+            pagination_html,
+            r#"<form action="/admin/audit" method="get">
+            {hidden_filter}
+            <button type="submit" name="page" value="{}">Next &gt;</button>
+        </form>"#,
+            page + 1
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Audit log</title>
+    </head>
+    <body>
+        <p>Audit log</p>
+        {msg_html}
+        <form action="/admin/audit" method="get">
+            <label>Action
+                <select name="action">{action_options}</select>
+            </label>
+            <button type="submit">Filter</button>
+        </form>
+
+        <p>{n_entries} action(s), page {page} of {n_pages}</p>
+        <table>
+            <tr>
+                <th>Time</th>
+                <th>User</th>
+                <th>Action</th>
+                <th>Target</th>
+                <th>IP address</th>
+                <th>User agent</th>
+            </tr>
+            {rows_html}
+        </table>
+        {pagination_html}
+
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#
+        )))
+}
@@ -7,7 +7,7 @@ use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::authentication::UserId;
-use crate::utils::e500;
+use crate::utils::{e500, escape_html};
 
 #[tracing::instrument(
     name = "Get admin dashboard html page"
@@ -20,7 +20,7 @@ pub async fn admin_dashboard(
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
 
-    let username = get_username(*user_id, &pool).await.map_err(e500)?;
+    let username = escape_html(&get_username(*user_id, &pool).await.map_err(e500)?);
 
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
@@ -34,6 +34,12 @@ pub async fn admin_dashboard(
     <body>
         <p>Welcome {username}</p>
         <p><a href="/admin/password">Change password</a></p>
+        <p><a href="/admin/two-factor">Two-factor authentication</a></p>
+        <p><a href="/admin/api-tokens">API tokens</a></p>
+        <p><a href="/admin/issues">Issues</a></p>
+        <p><a href="/admin/subscribers">Subscribers</a></p>
+        <p><a href="/admin/users">Manage users</a></p>
+        <p><a href="/admin/audit">Audit log</a></p>
 
         <form name="logoutForm" action="/admin/logout" method="post">
             <input type="submit" value="Logout">
@@ -0,0 +1,148 @@
+use std::fmt::Write;
+
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::newsletter_issues::{get_delivery_status, get_newsletter_issue, list_newsletter_issues};
+use crate::tracking::{get_link_stats, get_open_stats};
+use crate::utils::{e500, escape_html};
+
+#[tracing::instrument(name = "Get issues page", skip(pool))]
+pub async fn issues_page(pool: web::Data<PgPool>) -> Result<HttpResponse, actix_web::Error> {
+    let issues = list_newsletter_issues(&pool).await.map_err(e500)?;
+
+    let mut rows_html = String::new();
+    for issue in &issues {
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td><a href="/admin/issues/{issue_id}">{title}</a></td>
+                <td>{published_at}</td>
+            </tr>"#,
+            issue_id = issue.newsletter_issue_id,
+            title = escape_html(&issue.title),
+            published_at = issue
+                .published_at
+                .map(|published_at| published_at.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_else(|| "draft".into()),
+        )
+        .unwrap();
+    }
+    if issues.is_empty() {
+        rows_html.push_str(r#"<tr><td colspan="2">There are no issues yet.</td></tr>"#);
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Issues</title>
+    </head>
+    <body>
+        <p>Issues</p>
+        <table>
+            <tr><th>Title</th><th>Published at</th></tr>
+            {rows_html}
+        </table>
+
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#
+        )))
+}
+
+/// Delivery progress of an issue, and how often each of its links was clicked.
+#[tracing::instrument(name = "Get issue stats page", skip(pool))]
+pub async fn issue_stats(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let Some(issue) = get_newsletter_issue(issue_id, pool.get_ref())
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let delivery = get_delivery_status(issue_id, &pool)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e500("The issue was not found"))?;
+    let links = get_link_stats(issue_id, &pool).await.map_err(e500)?;
+    let opens = get_open_stats(issue_id, &pool).await.map_err(e500)?;
+
+    let opens_html = if issue.track_opens {
+        format!(
+            "<p>Opened by {} subscriber(s), {} open(s)</p>",
+            opens.subscribers, opens.opens
+        )
+    } else {
+        "<p>Opens are not tracked for this issue.</p>".to_string()
+    };
+
+    let mut links_html = String::new();
+    for link in &links {
+        writeln!(
+            links_html,
+            r#"<tr>
+                <td>{url}</td>
+                <td>{clicks}</td>
+                <td>{subscribers}</td>
+            </tr>"#,
+            url = escape_html(&link.url),
+            clicks = link.clicks,
+            subscribers = link.subscribers,
+        )
+        .unwrap();
+    }
+    if !issue.track_clicks {
+        links_html
+            .push_str(r#"<tr><td colspan="3">Clicks are not tracked for this issue.</td></tr>"#);
+    } else if links.is_empty() {
+        links_html.push_str(r#"<tr><td colspan="3">No link has been clicked yet.</td></tr>"#);
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Issue stats</title>
+    </head>
+    <body>
+        <p>{title}</p>
+        <p>Published at: {published_at}</p>
+        <p>Recipients: {recipients}</p>
+        <p>Delivered: {delivered}</p>
+        <p>Failed: {failed}</p>
+        <p>Pending: {pending}</p>
+        {opens_html}
+
+        <p>Link clicks</p>
+        <table>
+            <tr><th>Link</th><th>Clicks</th><th>Subscribers</th></tr>
+            {links_html}
+        </table>
+
+        <p><a href="/admin/issues">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+            title = escape_html(&issue.title),
+            published_at = delivery
+                .published_at
+                .map(|published_at| published_at.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                .unwrap_or_else(|| "not yet, this is a draft".into()),
+            recipients = delivery.recipients,
+            delivered = delivery.delivered,
+            failed = delivery.failed,
+            pending = delivery.pending,
+        )))
+}
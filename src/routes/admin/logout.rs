@@ -1,22 +1,36 @@
 use std::ops::Deref;
 
-use actix_web::HttpResponse;
-use actix_web::web::ReqData;
+use actix_web::web::{self, ReqData};
+use actix_web::{HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
 
+use crate::audit::{AuditAction, record_audit_event};
 use crate::authentication::UserId;
+use crate::consent::RequestMetadata;
 use crate::session_state::TypedSession;
-use crate::utils::see_other;
+use crate::utils::{e500, see_other};
 
 #[tracing::instrument(
     name = "Admin user logs out",
-    skip(session, user_id),
+    skip(session, user_id, pool, request),
     fields(user_id = %user_id.deref()))
 ]
 pub async fn log_out(
     session: TypedSession,
     user_id: ReqData<UserId>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
 ) -> Result<HttpResponse, actix_web::Error> {
+    record_audit_event(
+        pool.get_ref(),
+        **user_id,
+        AuditAction::LogOut,
+        None,
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
     session.log_out();
     FlashMessage::info("You have successfully logged out.").send();
     Ok(see_other("/login"))
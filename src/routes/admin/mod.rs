@@ -1,9 +1,21 @@
+mod api_tokens;
+mod audit;
 mod dashboard;
+mod issues;
 mod logout;
 mod newsletters;
 mod password;
+mod subscribers;
+mod two_factor;
+mod users;
 
-pub use dashboard::admin_dashboard;
+pub use api_tokens::*;
+pub use audit::audit_log_page;
+pub use dashboard::{admin_dashboard, get_username};
+pub use issues::*;
 pub use logout::log_out;
 pub use newsletters::*;
 pub use password::*;
+pub use subscribers::*;
+pub use two_factor::*;
+pub use users::*;
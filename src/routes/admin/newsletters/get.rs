@@ -1,19 +1,16 @@
-use std::fmt::Write;
-
 use actix_web::HttpResponse;
 use actix_web::http::header::ContentType;
 use actix_web_flash_messages::IncomingFlashMessages;
 use uuid::Uuid;
 
+use crate::utils::flash_messages_html;
+
 #[tracing::instrument(
     name = "Get publish newsletter form"
     skip(flash_messages) // TODO: May be better to record flash_messages to span
 )]
 pub async fn send_issue_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
-    let mut msg_html = String::new();
-    for m in flash_messages.iter() {
-        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
-    }
+    let msg_html = flash_messages_html(&flash_messages);
 
     let idempotency_key = Uuid::new_v4().to_string();
 
@@ -54,6 +51,15 @@ pub async fn send_issue_form(flash_messages: IncomingFlashMessages) -> HttpRespo
                 ></textarea>
             </label>
 
+            <label>
+                <input type="checkbox" name="no_click_tracking" value="true">
+                Don't track link clicks
+            </label>
+            <label>
+                <input type="checkbox" name="no_open_tracking" value="true">
+                Don't track opens
+            </label>
+
             <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
 
             <button type="submit">Publish</button>
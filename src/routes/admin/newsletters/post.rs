@@ -1,14 +1,16 @@
 use crate::{
+    audit::{AuditAction, AuditTarget, record_audit_event},
     authentication::UserId,
+    consent::RequestMetadata,
     idempotency::{IdempotencyKey, NextAction, save_response, try_processing},
+    newsletter_issues::{IssueTracking, enqueue_delivery_tasks, insert_newsletter_issue},
     utils::{e400, e500, see_other},
 };
-use actix_web::HttpResponse;
 use actix_web::web::{self, ReqData};
+use actix_web::{HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
-use sqlx::{Executor, Postgres, Transaction, postgres::PgPool};
-use uuid::Uuid;
+use sqlx::postgres::PgPool;
 
 #[derive(serde::Deserialize)]
 pub struct FormData {
@@ -16,6 +18,11 @@ pub struct FormData {
     title: String,
     text_content: String,
     html_content: String,
+    /// Set by the opt-out checkboxes.
+    #[serde(default)]
+    no_click_tracking: bool,
+    #[serde(default)]
+    no_open_tracking: bool,
     idempotency_key: String,
 }
 
@@ -23,11 +30,14 @@ pub async fn send_issue(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     user_id: ReqData<UserId>,
+    request: HttpRequest,
 ) -> Result<HttpResponse, actix_web::Error> {
     let FormData {
         title,
         text_content,
         html_content,
+        no_click_tracking,
+        no_open_tracking,
         idempotency_key,
     } = form.0;
     let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(e400)?;
@@ -43,15 +53,34 @@ pub async fn send_issue(
         NextAction::StartProcessing(transaction) => transaction,
     };
 
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
-        .await
-        .context("Failed to store newsletter issue details")
-        .map_err(e500)?;
+    let tracking = IssueTracking {
+        clicks: !no_click_tracking,
+        opens: !no_open_tracking,
+    };
+    let issue_id = insert_newsletter_issue(
+        &mut transaction,
+        &title,
+        &text_content,
+        &html_content,
+        tracking,
+    )
+    .await
+    .context("Failed to store newsletter issue details")
+    .map_err(e500)?;
 
     enqueue_delivery_tasks(&mut transaction, issue_id)
         .await
         .context("Failed to enqueue delivery tasks")
         .map_err(e500)?;
+    record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::PublishIssue,
+        Some(AuditTarget::Issue(issue_id)),
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
 
     FlashMessage::info("The newsletter issue has been accepted!").send();
     let response = see_other("/admin/newsletters");
@@ -60,54 +89,3 @@ pub async fn send_issue(
         .map_err(e500)?;
     Ok(response)
 }
-
-#[tracing::instrument(skip_all)]
-async fn insert_newsletter_issue(
-    transaction: &mut Transaction<'_, Postgres>,
-    title: &str,
-    text_content: &str,
-    html_content: &str,
-) -> Result<Uuid, sqlx::Error> {
-    let newsletters_issue_id = Uuid::new_v4();
-
-    let query = sqlx::query!(
-        r#"
-        INSERT INTO newsletter_issues (
-            newsletter_issue_id,
-            title,
-            text_content,
-            html_content,
-            published_at
-        )
-        VALUES($1, $2, $3, $4, now())
-        "#,
-        newsletters_issue_id,
-        title,
-        text_content,
-        html_content
-    );
-
-    transaction.execute(query).await?;
-
-    Ok(newsletters_issue_id)
-}
-
-#[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(
-    transaction: &mut Transaction<'_, Postgres>,
-    newsletter_issue_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    let query = sqlx::query!(
-        r#"
-    INSERT INTO issue_delivery_queue (
-        newsletter_issue_id,
-        subscriber_email
-    )
-    SELECT $1, email FROM subscriptions WHERE status = 'confirmed'
-    "#,
-        newsletter_issue_id
-    );
-
-    transaction.execute(query).await?;
-    Ok(())
-}
@@ -1,8 +1,8 @@
-use std::fmt::Write;
-
 use actix_web::{HttpResponse, http::header::ContentType};
 use actix_web_flash_messages::IncomingFlashMessages;
 
+use crate::utils::flash_messages_html;
+
 #[tracing::instrument(
     name = "Get change password form"
     skip(flash_messages) // TODO: May be better to record flash_messages to span
@@ -10,10 +10,7 @@ use actix_web_flash_messages::IncomingFlashMessages;
 pub async fn change_password_form(
     flash_messages: IncomingFlashMessages,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let mut msg_html = String::new();
-    for m in flash_messages.iter() {
-        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
-    }
+    let msg_html = flash_messages_html(&flash_messages);
 
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
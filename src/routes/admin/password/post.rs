@@ -1,9 +1,11 @@
-use actix_web::{HttpResponse, web};
+use actix_web::{HttpRequest, HttpResponse, web};
 use actix_web_flash_messages::FlashMessage;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 
+use crate::audit::{AuditAction, record_audit_event};
 use crate::authentication::{self, AuthError, Credentials, UserId, validate_credentials};
+use crate::consent::RequestMetadata;
 use crate::domain::AdminPassword;
 use crate::routes::admin::dashboard::get_username;
 use crate::utils::{e500, see_other};
@@ -19,6 +21,7 @@ pub async fn change_password(
     form: web::Form<FormData>,
     user_id: web::ReqData<UserId>,
     pool: web::Data<PgPool>,
+    request: HttpRequest,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
 
@@ -44,26 +47,30 @@ pub async fn change_password(
                 FlashMessage::error("The current password is incorrect.").send();
                 Ok(see_other("/admin/password"))
             }
-            AuthError::UnexpectedError(e) => Err(e500(e).into()),
+            AuthError::UnexpectedError(e) => Err(e500(e)),
         };
     }
 
-    let new_password = AdminPassword::new(form.0.new_password.expose_secret().clone());
-
-    if let Err(_) = new_password {
-        FlashMessage::error(
-            "The new password must be longer than 12 characters and shorter than 129 characters.",
-        )
-        .send();
-
-        return Ok(see_other("/admin/password"));
-    }
-
-    let new_password = new_password.unwrap();
+    let new_password = match AdminPassword::new(form.0.new_password.expose_secret().clone()) {
+        Ok(new_password) => new_password,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/password"));
+        }
+    };
 
     authentication::change_password(*user_id, new_password, &pool)
         .await
         .map_err(e500)?;
+    record_audit_event(
+        pool.get_ref(),
+        *user_id,
+        AuditAction::ChangePassword,
+        None,
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
 
     FlashMessage::success("Your password has been changed.").send();
 
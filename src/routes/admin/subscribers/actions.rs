@@ -0,0 +1,169 @@
+use std::ops::Deref;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditTarget, record_audit_event};
+use crate::authentication::UserId;
+use crate::consent::RequestMetadata;
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionStatus};
+use crate::email_client::EmailClient;
+use crate::routes::{generate_subscription_token, send_confirmation_email, store_token};
+use crate::startup::ApplicationBaseUrl;
+use crate::subscribers::{delete_subscriber, get_subscriber, update_subscriber};
+use crate::utils::{e500, see_other};
+
+/// Sends a new confirmation link to a subscriber who has not confirmed yet.
+#[tracing::instrument(
+    name = "Resend a confirmation email",
+    skip(pool, email_client, base_url, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn resend_confirmation(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+
+    let Some(subscriber) = get_subscriber(subscriber_id, pool.get_ref())
+        .await
+        .map_err(e500)?
+    else {
+        FlashMessage::error("The subscriber does not exist.").send();
+        return Ok(see_other("/admin/subscribers"));
+    };
+    if subscriber.status != SubscriptionStatus::PendingConfirmation {
+        FlashMessage::error("The subscriber is not waiting for a confirmation.").send();
+        return Ok(see_other("/admin/subscribers"));
+    }
+
+    let new_subscriber = SubscriberEmail::parse(subscriber.email)
+        .and_then(|email| {
+            Ok(NewSubscriber {
+                email,
+                name: SubscriberName::parse(subscriber.name)?,
+            })
+        })
+        .map_err(|e| e500(anyhow::anyhow!(e)))?;
+
+    let subscription_token = generate_subscription_token();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    store_token(&mut transaction, subscriber_id, &subscription_token)
+        .await
+        .context("Failed to store a new confirmation token")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a new confirmation token")
+        .map_err(e500)?;
+
+    send_confirmation_email(
+        email_client.get_ref(),
+        new_subscriber,
+        &base_url.0,
+        &subscription_token,
+    )
+    .await
+    .context("Failed to send a confirmation email")
+    .map_err(e500)?;
+    record_audit_event(
+        pool.get_ref(),
+        **user_id,
+        AuditAction::ResendConfirmation,
+        Some(AuditTarget::Subscriber(subscriber_id)),
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
+
+    FlashMessage::info("A new confirmation email has been sent.").send();
+    Ok(see_other("/admin/subscribers"))
+}
+
+#[tracing::instrument(
+    name = "Unsubscribe a subscriber",
+    skip(pool, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn unsubscribe_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let updated = update_subscriber(
+        pool.get_ref(),
+        subscriber_id,
+        None,
+        Some(SubscriptionStatus::Unsubscribed),
+        None,
+    )
+    .await
+    .map_err(e500)?;
+    if updated {
+        record_audit_event(
+            pool.get_ref(),
+            **user_id,
+            AuditAction::UnsubscribeSubscriber,
+            Some(AuditTarget::Subscriber(subscriber_id)),
+            &RequestMetadata::from_request(&request),
+        )
+        .await
+        .map_err(e500)?;
+    }
+
+    report(updated, "The subscriber has been unsubscribed.")
+}
+
+#[tracing::instrument(
+    name = "Delete a subscriber",
+    skip(pool, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn remove_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let deleted = delete_subscriber(subscriber_id, &pool)
+        .await
+        .map_err(e500)?;
+    if deleted {
+        record_audit_event(
+            pool.get_ref(),
+            **user_id,
+            AuditAction::DeleteSubscriber,
+            Some(AuditTarget::Subscriber(subscriber_id)),
+            &RequestMetadata::from_request(&request),
+        )
+        .await
+        .map_err(e500)?;
+    }
+
+    report(deleted, "The subscriber has been deleted.")
+}
+
+fn report(found: bool, success_message: &str) -> Result<HttpResponse, actix_web::Error> {
+    if found {
+        FlashMessage::info(success_message).send();
+    } else {
+        FlashMessage::error("The subscriber does not exist.").send();
+    }
+
+    Ok(see_other("/admin/subscribers"))
+}
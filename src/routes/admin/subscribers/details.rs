@@ -0,0 +1,87 @@
+use std::fmt::Write;
+
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::consent::list_consent_events;
+use crate::subscribers::get_subscriber;
+use crate::utils::{e500, escape_html};
+
+/// The subscriber with the history of their consent.
+#[tracing::instrument(name = "Get subscriber details page", skip(pool))]
+pub async fn subscriber_details(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let Some(subscriber) = get_subscriber(subscriber_id, pool.get_ref())
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let events = list_consent_events(subscriber_id, &pool)
+        .await
+        .map_err(e500)?;
+
+    let mut events_html = String::new();
+    for event in &events {
+        writeln!(
+            events_html,
+            r#"<tr>
+                <td>{occurred_at}</td>
+                <td>{event}</td>
+                <td>{source}</td>
+                <td>{ip_address}</td>
+                <td>{user_agent}</td>
+            </tr>"#,
+            occurred_at = event.occurred_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            event = event.event,
+            source = event.source.as_deref().unwrap_or(""),
+            ip_address = escape_html(event.ip_address.as_deref().unwrap_or("")),
+            user_agent = escape_html(event.user_agent.as_deref().unwrap_or("")),
+        )
+        .unwrap();
+    }
+    if events.is_empty() {
+        events_html.push_str(r#"<tr><td colspan="5">Nothing was recorded.</td></tr>"#);
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Subscriber</title>
+    </head>
+    <body>
+        <p>{email}</p>
+        <p>Name: {name}</p>
+        <p>Status: {status}</p>
+        <p>Subscribed at: {subscribed_at}</p>
+        <p>Tracking: {tracking}</p>
+
+        <p>Consent history</p>
+        <table>
+            <tr><th>At</th><th>Event</th><th>Source</th><th>IP address</th><th>User agent</th></tr>
+            {events_html}
+        </table>
+
+        <p><a href="/admin/subscribers">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+            email = escape_html(&subscriber.email),
+            name = escape_html(&subscriber.name),
+            status = subscriber.status,
+            tracking = if subscriber.do_not_track {
+                "refused"
+            } else {
+                "allowed"
+            },
+            subscribed_at = subscriber.subscribed_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        )))
+}
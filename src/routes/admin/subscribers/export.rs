@@ -0,0 +1,170 @@
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::web::Bytes;
+use std::ops::Deref;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, record_audit_event};
+use crate::authentication::UserId;
+use crate::consent::RequestMetadata;
+use crate::subscribers::{Subscriber, stream_subscribers};
+use crate::utils::{e400, e500};
+
+/// Rows are sent to the client in chunks of about this size.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    format: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    Csv,
+    NdJson,
+}
+
+impl ExportFormat {
+    fn parse(format: Option<&str>) -> Result<Self, String> {
+        match format {
+            None | Some("csv") => Ok(Self::Csv),
+            Some("ndjson") => Ok(Self::NdJson),
+            Some(other) => Err(format!(
+                "{} is not a valid export format. Use either `csv` or `ndjson`.",
+                other
+            )),
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::NdJson => "application/x-ndjson",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::NdJson => "ndjson",
+        }
+    }
+
+    fn header(&self) -> Vec<u8> {
+        match self {
+            Self::Csv => b"subscriber_id,email,name,status,subscribed_at\n".to_vec(),
+            Self::NdJson => Vec::new(),
+        }
+    }
+
+    fn write(&self, subscriber: &Subscriber, buffer: &mut Vec<u8>) -> Result<(), anyhow::Error> {
+        let subscriber = ExportedSubscriber {
+            subscriber_id: subscriber.subscriber_id,
+            email: &subscriber.email,
+            name: &subscriber.name,
+            status: subscriber.status.as_str(),
+            subscribed_at: subscriber.subscribed_at,
+        };
+        match self {
+            Self::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(buffer);
+                writer.serialize(subscriber)?;
+                writer.flush()?;
+            }
+            Self::NdJson => {
+                serde_json::to_writer(&mut *buffer, &subscriber)?;
+                buffer.push(b'\n');
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ExportedSubscriber<'a> {
+    subscriber_id: Uuid,
+    email: &'a str,
+    name: &'a str,
+    status: &'a str,
+    subscribed_at: DateTime<Utc>,
+}
+
+/// Every subscriber as CSV or newline-delimited JSON. Rows are streamed from the database
+/// as the client reads them, so the size of the table does not matter.
+#[tracing::instrument(
+    name = "Export subscribers",
+    skip(query, pool, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn export_subscribers(
+    query: web::Query<QueryParams>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let format = ExportFormat::parse(query.format.as_deref()).map_err(e400)?;
+    record_audit_event(
+        pool.get_ref(),
+        **user_id,
+        AuditAction::ExportSubscribers,
+        None,
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
+
+    // The bounded channel stops reading from the database while the client is behind.
+    let (sender, receiver) = mpsc::channel::<Result<Bytes, anyhow::Error>>(4);
+    let pool = pool.into_inner();
+    tokio::spawn(async move {
+        let mut rows = stream_subscribers(&pool);
+        let mut buffer = format.header();
+        while let Some(row) = rows.next().await {
+            if let Err(e) = row.and_then(|subscriber| format.write(&subscriber, &mut buffer)) {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to export subscribers"
+                );
+                let _ = sender.send(Err(e)).await;
+                return;
+            }
+            if buffer.len() >= CHUNK_SIZE
+                && sender
+                    .send(Ok(Bytes::from(std::mem::take(&mut buffer))))
+                    .await
+                    .is_err()
+            {
+                // The client went away.
+                return;
+            }
+        }
+        if !buffer.is_empty() {
+            let _ = sender.send(Ok(Bytes::from(buffer))).await;
+        }
+    });
+
+    let body = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    })
+    .map(|chunk| chunk.map_err(actix_web::error::ErrorInternalServerError));
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "subscribers-{}.{}",
+                Utc::now().format("%Y-%m-%d"),
+                format.extension()
+            ))],
+        })
+        .streaming(body))
+}
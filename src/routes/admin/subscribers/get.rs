@@ -0,0 +1,233 @@
+use std::fmt::Write;
+
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use chrono::{Days, NaiveDate, NaiveTime};
+use sqlx::PgPool;
+
+use crate::domain::SubscriptionStatus;
+use crate::subscribers::{SubscriberFilters, count_subscribers, list_subscribers};
+use crate::utils::{e500, escape_html, flash_messages_html};
+
+const PAGE_SIZE: i64 = 50;
+
+/// Empty fields, as sent by the filter form, are ignored.
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    search: Option<String>,
+    status: Option<String>,
+    /// `YYYY-MM-DD`, inclusive.
+    subscribed_from: Option<String>,
+    /// `YYYY-MM-DD`, inclusive.
+    subscribed_to: Option<String>,
+    page: Option<i64>,
+}
+
+#[tracing::instrument(name = "Get subscribers page", skip(query, flash_messages, pool))]
+pub async fn subscribers_page(
+    query: web::Query<QueryParams>,
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let query = query.into_inner();
+
+    let mut msg_html = flash_messages_html(&flash_messages);
+
+    let search = non_empty(query.search);
+    let status = non_empty(query.status);
+    let subscribed_from = non_empty(query.subscribed_from);
+    let subscribed_to = non_empty(query.subscribed_to);
+
+    let mut filters = SubscriberFilters {
+        search: search.clone(),
+        ..Default::default()
+    };
+    if let Some(status) = &status {
+        match SubscriptionStatus::try_from(status.clone()) {
+            Ok(status) => filters.status = Some(status),
+            Err(e) => writeln!(msg_html, "<p><i>{}</i></p>", escape_html(&e)).unwrap(),
+        }
+    }
+    if let Some(date) = &subscribed_from {
+        match parse_date(date) {
+            Ok(date) => filters.subscribed_after = Some(date.and_time(NaiveTime::MIN).and_utc()),
+            Err(e) => writeln!(msg_html, "<p><i>{}</i></p>", escape_html(&e)).unwrap(),
+        }
+    }
+    if let Some(date) = &subscribed_to {
+        match parse_date(date) {
+            Ok(date) => {
+                filters.subscribed_before = date
+                    .checked_add_days(Days::new(1))
+                    .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+            }
+            Err(e) => writeln!(msg_html, "<p><i>{}</i></p>", escape_html(&e)).unwrap(),
+        }
+    }
+
+    let n_subscribers = count_subscribers(&filters, &pool).await.map_err(e500)?;
+    let n_pages = ((n_subscribers + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+    let page = query.page.unwrap_or(1).clamp(1, n_pages);
+    let subscribers = list_subscribers(&filters, PAGE_SIZE, (page - 1) * PAGE_SIZE, &pool)
+        .await
+        .map_err(e500)?;
+
+    let mut rows_html = String::new();
+    for subscriber in subscribers {
+        let subscriber_id = subscriber.subscriber_id;
+        let mut actions_html = String::new();
+        if subscriber.status == SubscriptionStatus::PendingConfirmation {
+            writeln!(
+                actions_html,
+                r#"<form action="/admin/subscribers/{subscriber_id}/resend-confirmation" method="post">
+                        <button type="submit">resend confirmation</button>
+                    </form>"#
+            )
+            .unwrap();
+        }
+        if subscriber.status != SubscriptionStatus::Unsubscribed {
+            writeln!(
+                actions_html,
+                r#"<form action="/admin/subscribers/{subscriber_id}/unsubscribe" method="post">
+                        <button type="submit">unsubscribe</button>
+                    </form>"#
+            )
+            .unwrap();
+        }
+        writeln!(
+            actions_html,
+            r#"<form action="/admin/subscribers/{subscriber_id}/delete" method="post">
+                        <button type="submit">delete</button>
+                    </form>"#
+        )
+        .unwrap();
+
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td><a href="/admin/subscribers/{subscriber_id}">{email}</a></td>
+                <td>{name}</td>
+                <td>{status}</td>
+                <td>{subscribed_at}</td>
+                <td>{actions_html}</td>
+            </tr>"#,
+            email = escape_html(&subscriber.email),
+            name = escape_html(&subscriber.name),
+            status = subscriber.status,
+            subscribed_at = subscriber.subscribed_at.format("%Y-%m-%d %H:%M UTC"),
+        )
+        .unwrap();
+    }
+
+    let search = escape_html(search.as_deref().unwrap_or(""));
+    let subscribed_from = escape_html(subscribed_from.as_deref().unwrap_or(""));
+    let subscribed_to = escape_html(subscribed_to.as_deref().unwrap_or(""));
+
+    let mut status_options = String::from(r#"<option value="">any</option>"#);
+    for option in SubscriptionStatus::ALL {
+        let selected = if status.as_deref() == Some(option.as_str()) {
+            " selected"
+        } else {
+            ""
+        };
+        write!(
+            status_options,
+            r#"<option value="{option}"{selected}>{option}</option>"#
+        )
+        .unwrap();
+    }
+
+    // The current filters are resubmitted with the page number, so they survive paging.
+    let hidden_filters = format!(
+        r#"<input type="hidden" name="search" value="{search}">
+            <input type="hidden" name="status" value="{status}">
+            <input type="hidden" name="subscribed_from" value="{subscribed_from}">
+            <input type="hidden" name="subscribed_to" value="{subscribed_to}">"#,
+        status = escape_html(status.as_deref().unwrap_or("")),
+    );
+    let mut pagination_html = String::new();
+    if page > 1 {
+        writeln!(
+            pagination_html,
+            r#"<form action="/admin/subscribers" method="get">
+            {hidden_filters}
+            <button type="submit" name="page" value="{}">&lt; Previous</button>
+        </form>"#,
+            page - 1
+        )
+        .unwrap();
+    }
+    if page < n_pages {
+        writeln!(
+            pagination_html,
+            r#"<form action="/admin/subscribers" method="get">
+            {hidden_filters}
+            <button type="submit" name="page" value="{}">Next &gt;</button>
+        </form>"#,
+            page + 1
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Subscribers</title>
+    </head>
+    <body>
+        <p>Subscribers</p>
+        {msg_html}
+        <form action="/admin/subscribers" method="get">
+            <label>Search
+                <input
+                    type="search"
+                    placeholder="Email or name"
+                    name="search"
+                    value="{search}"
+                >
+            </label>
+            <label>Status
+                <select name="status">{status_options}</select>
+            </label>
+            <label>Subscribed from
+                <input type="date" name="subscribed_from" value="{subscribed_from}">
+            </label>
+            <label>to
+                <input type="date" name="subscribed_to" value="{subscribed_to}">
+            </label>
+            <button type="submit">Filter</button>
+        </form>
+
+        <p>{n_subscribers} subscriber(s), page {page} of {n_pages}</p>
+        <table>
+            <tr><th>Email</th><th>Name</th><th>Status</th><th>Subscribed at</th><th></th></tr>
+            {rows_html}
+        </table>
+        {pagination_html}
+
+        <p><a href="/admin/subscribers/imports">Import subscribers</a></p>
+        <p><a href="/admin/subscribers/personal-data">Personal data requests</a></p>
+        <p>
+            Export every subscriber as
+            <a href="/admin/subscribers/export?format=csv">CSV</a> or
+            <a href="/admin/subscribers/export?format=ndjson">NDJSON</a>
+        </p>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#
+        )))
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("{} is not a valid date. Use YYYY-MM-DD.", date))
+}
@@ -0,0 +1,272 @@
+use std::fmt::Write;
+use std::ops::Deref;
+
+use actix_multipart::{Field, Multipart};
+use actix_web::http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType};
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use futures_util::TryStreamExt;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditTarget, record_audit_event};
+use crate::authentication::UserId;
+use crate::consent::RequestMetadata;
+use crate::email_client::EmailClient;
+use crate::startup::ApplicationBaseUrl;
+use crate::subscriber_imports::{
+    CsvRecordReader, ImportError, ImportMode, ImportSummary, SubscriberImporter, get_rejected_rows,
+    list_subscriber_imports,
+};
+use crate::utils::{e400, e500, escape_html, flash_messages_html, see_other};
+
+const RECENT_IMPORTS: i64 = 20;
+
+#[tracing::instrument(name = "Get subscriber imports page", skip(flash_messages, pool))]
+pub async fn subscriber_imports_page(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let msg_html = flash_messages_html(&flash_messages);
+
+    let imports = list_subscriber_imports(RECENT_IMPORTS, &pool)
+        .await
+        .map_err(e500)?;
+    let mut rows_html = String::new();
+    for import in imports {
+        let report_html = if import.rejected > 0 {
+            format!(
+                r#"<a href="/admin/subscribers/imports/{}/rejections.csv">rejected rows</a>"#,
+                import.import_id
+            )
+        } else {
+            String::new()
+        };
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td>{created_at}</td>
+                <td>{file_name}</td>
+                <td>{mode}</td>
+                <td>{imported}</td>
+                <td>{rejected}</td>
+                <td>{unsent}</td>
+                <td>{report_html}</td>
+            </tr>"#,
+            created_at = import.created_at.format("%Y-%m-%d %H:%M UTC"),
+            file_name = escape_html(&import.file_name),
+            mode = import.mode,
+            imported = import.imported,
+            rejected = import.rejected,
+            unsent = import.unsent,
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Import subscribers</title>
+    </head>
+    <body>
+        <p>Import subscribers</p>
+        {msg_html}
+        <p>
+            Upload a CSV file whose first row names the columns. The <code>email</code>
+            and <code>name</code> columns are required, any other column is ignored.
+            Addresses which are already subscribed are skipped.
+        </p>
+        <form action="/admin/subscribers/imports" method="post" enctype="multipart/form-data">
+            <label>
+                <input type="radio" name="mode" value="opt_in" checked>
+                Send a confirmation email to every subscriber
+            </label>
+            <label>
+                <input type="radio" name="mode" value="confirmed">
+                Import as confirmed, for subscribers who already opted in
+            </label>
+            <label>CSV file
+                <input type="file" name="file" accept=".csv,text/csv">
+            </label>
+            <button type="submit">Import</button>
+        </form>
+
+        <p>Recent imports</p>
+        <table>
+            <tr>
+                <th>Started at</th><th>File</th><th>Mode</th><th>Imported</th>
+                <th>Rejected</th><th>Confirmations not sent</th><th></th>
+            </tr>
+            {rows_html}
+        </table>
+
+        <p><a href="/admin/subscribers">&lt;- Back</a></p>
+    </body>
+</html>
+            "#
+        )))
+}
+
+/// Expects the `mode` field before the `file` field, which is streamed as it is uploaded.
+#[tracing::instrument(
+    name = "Import subscribers",
+    skip(payload, pool, email_client, base_url, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn import_subscribers(
+    mut payload: Multipart,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut mode = None;
+    let mut summary = None;
+
+    while let Some(field) = payload.try_next().await.map_err(e400)? {
+        match field.name() {
+            Some("mode") => {
+                let value = read_text_field(field).await?;
+                match ImportMode::try_from(value) {
+                    Ok(value) => mode = Some(value),
+                    Err(e) => return Ok(fail(&e)),
+                }
+            }
+            Some("file") => {
+                let Some(mode) = mode else {
+                    return Ok(fail("Choose how to import the subscribers."));
+                };
+                let file_name = field
+                    .content_disposition()
+                    .and_then(|disposition| disposition.get_filename())
+                    .unwrap_or_default()
+                    .to_string();
+                let importer = SubscriberImporter::new(
+                    mode,
+                    file_name,
+                    **user_id,
+                    &pool,
+                    &email_client,
+                    &base_url.0,
+                );
+                match import_file(field, importer).await {
+                    Ok(outcome) => summary = Some(outcome),
+                    Err(ImportError::InvalidHeader(e)) => return Ok(fail(&e)),
+                    Err(e) => return Err(e500(e)),
+                }
+            }
+            _ => {
+                // Unknown fields are skipped.
+                read_text_field(field).await?;
+            }
+        }
+    }
+
+    let Some(summary) = summary else {
+        return Ok(fail("Choose a CSV file to import."));
+    };
+    record_audit_event(
+        pool.get_ref(),
+        **user_id,
+        AuditAction::ImportSubscribers,
+        Some(AuditTarget::SubscriberImport(summary.import_id)),
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
+    FlashMessage::info(format!(
+        "Imported {} subscriber(s), rejected {} row(s).",
+        summary.imported, summary.rejected
+    ))
+    .send();
+    if summary.unsent > 0 {
+        FlashMessage::error(format!(
+            "{} confirmation email(s) could not be sent. \
+            They can be sent again from the subscriber list.",
+            summary.unsent
+        ))
+        .send();
+    }
+
+    Ok(see_other("/admin/subscribers/imports"))
+}
+
+async fn import_file(
+    mut file: Field,
+    mut importer: SubscriberImporter<'_>,
+) -> Result<ImportSummary, ImportError> {
+    let mut reader = CsvRecordReader::default();
+    while let Some(chunk) = file
+        .try_next()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read the uploaded file: {}", e))?
+    {
+        for record in reader.feed(&chunk) {
+            importer.add(record).await?;
+        }
+    }
+    if let Some(record) = reader.finish() {
+        importer.add(record).await?;
+    }
+
+    importer.finish().await
+}
+
+/// Reads a form field which is not a file. Only small values are expected.
+async fn read_text_field(mut field: Field) -> Result<String, actix_web::Error> {
+    const MAX_LENGTH: usize = 1024;
+
+    let mut value = Vec::new();
+    while let Some(chunk) = field.try_next().await.map_err(e400)? {
+        if value.len() + chunk.len() > MAX_LENGTH {
+            return Err(e400("The form field is too long."));
+        }
+        value.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(value).map_err(e400)
+}
+
+fn fail(message: &str) -> HttpResponse {
+    FlashMessage::error(message).send();
+    see_other("/admin/subscribers/imports")
+}
+
+/// The rows which were not imported, with the reason, as a CSV file.
+#[tracing::instrument(name = "Download rejected rows of an import", skip(pool))]
+pub async fn import_rejections_report(
+    import_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let import_id = import_id.into_inner();
+    let Some(rows) = get_rejected_rows(import_id, &pool).await.map_err(e500)? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let mut report = csv::Writer::from_writer(Vec::new());
+    report
+        .write_record(["row", "email", "name", "reason"])
+        .map_err(e500)?;
+    for row in rows {
+        report
+            .write_record([&row.row.to_string(), &row.email, &row.name, &row.reason])
+            .map_err(e500)?;
+    }
+    let report = report.into_inner().map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "import-{}-rejected.csv",
+                import_id
+            ))],
+        })
+        .body(report))
+}
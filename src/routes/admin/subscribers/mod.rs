@@ -0,0 +1,15 @@
+mod actions;
+mod details;
+mod export;
+mod get;
+mod imports;
+mod personal_data;
+
+pub use actions::{remove_subscriber, resend_confirmation, unsubscribe_subscriber};
+pub use details::subscriber_details;
+pub use export::export_subscribers;
+pub use get::subscribers_page;
+pub use imports::{import_rejections_report, import_subscribers, subscriber_imports_page};
+pub use personal_data::{
+    erase_subscriber_data, export_personal_data, personal_data_page, personal_data_response,
+};
@@ -0,0 +1,134 @@
+use std::ops::Deref;
+
+use actix_web::http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType};
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+
+use crate::audit::{AuditAction, AuditTarget, record_audit_event};
+use crate::authentication::UserId;
+use crate::consent::RequestMetadata;
+use crate::personal_data::{PersonalData, collect_personal_data, erase_personal_data};
+use crate::utils::{e500, flash_messages_html, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+}
+
+pub async fn personal_data_page(flash_messages: IncomingFlashMessages) -> HttpResponse {
+    let msg_html = flash_messages_html(&flash_messages);
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Personal data requests</title>
+    </head>
+    <body>
+        <p>Personal data requests</p>
+        {msg_html}
+        <p>Download everything stored about an email address, as JSON.</p>
+        <form action="/admin/subscribers/personal-data/export" method="post">
+            <label>Email
+                <input type="email" placeholder="Enter the email address" name="email">
+            </label>
+            <button type="submit">Download</button>
+        </form>
+
+        <p>Delete everything stored about an email address. This can't be undone.</p>
+        <form action="/admin/subscribers/personal-data/erase" method="post">
+            <label>Email
+                <input type="email" placeholder="Enter the email address" name="email">
+            </label>
+            <button type="submit">Erase</button>
+        </form>
+
+        <p><a href="/admin/subscribers">&lt;- Back</a></p>
+    </body>
+</html>
+            "#
+        ))
+}
+
+#[tracing::instrument(
+    name = "Export personal data",
+    skip(form, pool, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn export_personal_data(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let data = collect_personal_data(form.email.trim(), &pool)
+        .await
+        .map_err(e500)?;
+    if data.is_empty() {
+        FlashMessage::error("Nothing is stored about this email address.").send();
+        return Ok(see_other("/admin/subscribers/personal-data"));
+    }
+    record_audit_event(
+        pool.get_ref(),
+        **user_id,
+        AuditAction::ExportPersonalData,
+        data.subscription
+            .as_ref()
+            .map(|subscription| AuditTarget::Subscriber(subscription.subscriber_id)),
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
+
+    personal_data_response(&data)
+}
+
+/// The audit log records the erasure without the email address, which would outlive it otherwise.
+#[tracing::instrument(
+    name = "Erase personal data",
+    skip(form, pool, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn erase_subscriber_data(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    if erase_personal_data(form.email.trim(), &pool)
+        .await
+        .map_err(e500)?
+    {
+        record_audit_event(
+            pool.get_ref(),
+            **user_id,
+            AuditAction::ErasePersonalData,
+            None,
+            &RequestMetadata::from_request(&request),
+        )
+        .await
+        .map_err(e500)?;
+        FlashMessage::info("Everything stored about this email address has been erased.").send();
+    } else {
+        FlashMessage::error("Nothing is stored about this email address.").send();
+    }
+
+    Ok(see_other("/admin/subscribers/personal-data"))
+}
+
+/// `data` as a JSON file to download.
+pub fn personal_data_response(data: &PersonalData) -> Result<HttpResponse, actix_web::Error> {
+    let body = serde_json::to_vec_pretty(data).map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename("personal-data.json".into())],
+        })
+        .body(body))
+}
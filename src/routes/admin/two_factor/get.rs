@@ -0,0 +1,97 @@
+use std::ops::Deref;
+
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use crate::authentication::{TotpEnrollment, UserId, generate_totp_secret, two_factor_enabled};
+use crate::routes::admin::dashboard::get_username;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, escape_html, flash_messages_html};
+
+#[tracing::instrument(
+    name = "Get two-factor authentication settings"
+    skip(flash_messages, pool, session, user_id)
+    fields(user_id = %user_id.deref())
+)]
+pub async fn two_factor_settings(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+
+    let msg_html = flash_messages_html(&flash_messages);
+
+    let content = if two_factor_enabled(*user_id, &pool).await.map_err(e500)? {
+        r#"<p>Two-factor authentication is enabled.</p>
+        <form action="/admin/two-factor/disable" method="post">
+            <label>Authentication code
+                <input
+                    type="text"
+                    placeholder="Enter a code to disable two-factor authentication"
+                    name="code"
+                    autocomplete="one-time-code"
+                >
+            </label>
+
+            <button type="submit">Disable</button>
+        </form>"#
+            .to_string()
+    } else {
+        // Keep the same secret until the enrollment is confirmed, the user may
+        // have scanned it already.
+        let secret = match session.get_totp_enrollment_secret().map_err(e500)? {
+            Some(secret) => secret,
+            None => {
+                let secret = generate_totp_secret();
+                session
+                    .insert_totp_enrollment_secret(&secret)
+                    .map_err(e500)?;
+                secret
+            }
+        };
+        let username = get_username(*user_id, &pool).await.map_err(e500)?;
+        let enrollment = TotpEnrollment::new(secret, &username).map_err(e500)?;
+
+        format!(
+            r#"<p>Scan this QR code with your authenticator app:</p>
+        {qr_code}
+        <p>Or enter this secret manually: <code>{secret}</code></p>
+        <form action="/admin/two-factor" method="post">
+            <label>Authentication code
+                <input
+                    type="text"
+                    placeholder="Enter the code shown by your app"
+                    name="code"
+                    autocomplete="one-time-code"
+                >
+            </label>
+
+            <button type="submit">Enable</button>
+        </form>"#,
+            qr_code = enrollment.qr_code_svg,
+            secret = escape_html(enrollment.secret.expose_secret()),
+        )
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Two-factor authentication</title>
+    </head>
+    <body>
+        {msg_html}
+        {content}
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#
+        )))
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::two_factor_settings;
+pub use post::{confirm_two_factor_enrollment, turn_off_two_factor};
@@ -0,0 +1,128 @@
+use std::fmt::Write;
+use std::ops::Deref;
+
+use actix_web::{HttpRequest, HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+use crate::audit::{AuditAction, record_audit_event};
+use crate::authentication::{
+    UserId, disable_two_factor, enable_two_factor, two_factor_enabled, verify_enrollment_code,
+    verify_second_factor,
+};
+use crate::consent::RequestMetadata;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    code: String,
+}
+
+#[tracing::instrument(
+    name = "Enable two-factor authentication",
+    skip(form, pool, session, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn confirm_two_factor_enrollment(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+
+    if two_factor_enabled(*user_id, &pool).await.map_err(e500)? {
+        return Ok(see_other("/admin/two-factor"));
+    }
+    let Some(secret) = session.get_totp_enrollment_secret().map_err(e500)? else {
+        return Ok(see_other("/admin/two-factor"));
+    };
+
+    let Some(step) = verify_enrollment_code(&secret, &form.code).map_err(e500)? else {
+        FlashMessage::error("The authentication code is invalid.").send();
+        return Ok(see_other("/admin/two-factor"));
+    };
+
+    let recovery_codes = enable_two_factor(*user_id, &secret, step, &pool)
+        .await
+        .map_err(e500)?;
+    session.remove_totp_enrollment_secret();
+    record_audit_event(
+        pool.get_ref(),
+        *user_id,
+        AuditAction::EnableTwoFactor,
+        None,
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
+
+    // Recovery codes are only stored hashed, this is the only time they are shown.
+    let mut codes_html = String::new();
+    for code in recovery_codes {
+        writeln!(codes_html, "<li><code>{}</code></li>", code).unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Two-factor authentication</title>
+    </head>
+    <body>
+        <p>Two-factor authentication is enabled.</p>
+        <p>
+            Store these recovery codes somewhere safe. Each of them can be used once
+            to log in if you lose access to your authenticator app.
+            They will not be shown again.
+        </p>
+        <ul>
+            {codes_html}
+        </ul>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#
+        )))
+}
+
+#[tracing::instrument(
+    name = "Disable two-factor authentication",
+    skip(form, pool, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn turn_off_two_factor(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+
+    if !verify_second_factor(*user_id, &form.code, &pool)
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::error("The authentication code is invalid.").send();
+        return Ok(see_other("/admin/two-factor"));
+    }
+
+    disable_two_factor(*user_id, &pool).await.map_err(e500)?;
+    record_audit_event(
+        pool.get_ref(),
+        *user_id,
+        AuditAction::DisableTwoFactor,
+        None,
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
+
+    FlashMessage::info("Two-factor authentication has been disabled.").send();
+    Ok(see_other("/admin/two-factor"))
+}
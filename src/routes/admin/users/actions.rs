@@ -0,0 +1,117 @@
+use std::ops::Deref;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditTarget, record_audit_event};
+use crate::authentication::{UpdateUserError, UserId, delete_user, set_user_active};
+use crate::consent::RequestMetadata;
+use crate::utils::{e500, see_other};
+
+#[tracing::instrument(
+    name = "Deactivate a user",
+    skip(pool, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn deactivate_user(
+    target: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let target = target.into_inner();
+    if target == **user_id {
+        FlashMessage::error("You cannot deactivate your own account.").send();
+        return Ok(see_other("/admin/users"));
+    }
+
+    let outcome = set_user_active(target, false, &pool).await;
+    if outcome.is_ok() {
+        record_audit_event(
+            pool.get_ref(),
+            **user_id,
+            AuditAction::DeactivateUser,
+            Some(AuditTarget::User(target)),
+            &RequestMetadata::from_request(&request),
+        )
+        .await
+        .map_err(e500)?;
+    }
+    report(outcome, "The user has been deactivated.")
+}
+
+#[tracing::instrument(
+    name = "Reactivate a user",
+    skip(pool, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn reactivate_user(
+    target: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let target = target.into_inner();
+    let outcome = set_user_active(target, true, &pool).await;
+    if outcome.is_ok() {
+        record_audit_event(
+            pool.get_ref(),
+            **user_id,
+            AuditAction::ReactivateUser,
+            Some(AuditTarget::User(target)),
+            &RequestMetadata::from_request(&request),
+        )
+        .await
+        .map_err(e500)?;
+    }
+    report(outcome, "The user has been reactivated.")
+}
+
+#[tracing::instrument(
+    name = "Delete a user",
+    skip(pool, user_id, request),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn remove_user(
+    target: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let target = target.into_inner();
+    if target == **user_id {
+        FlashMessage::error("You cannot delete your own account.").send();
+        return Ok(see_other("/admin/users"));
+    }
+
+    let outcome = delete_user(target, &pool).await;
+    if outcome.is_ok() {
+        record_audit_event(
+            pool.get_ref(),
+            **user_id,
+            AuditAction::DeleteUser,
+            Some(AuditTarget::User(target)),
+            &RequestMetadata::from_request(&request),
+        )
+        .await
+        .map_err(e500)?;
+    }
+    report(outcome, "The user has been deleted.")
+}
+
+fn report(
+    outcome: Result<(), UpdateUserError>,
+    success_message: &str,
+) -> Result<HttpResponse, actix_web::Error> {
+    match outcome {
+        Ok(()) => FlashMessage::info(success_message).send(),
+        Err(e @ (UpdateUserError::UnknownUser | UpdateUserError::LastActiveOwner)) => {
+            FlashMessage::error(e.to_string()).send()
+        }
+        Err(e) => return Err(e500(e)),
+    }
+
+    Ok(see_other("/admin/users"))
+}
@@ -0,0 +1,107 @@
+use std::fmt::Write;
+
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+use crate::authentication::{list_pending_invitations, list_users};
+use crate::utils::{e500, escape_html, flash_messages_html};
+
+#[tracing::instrument(name = "Get user management page", skip(flash_messages, pool))]
+pub async fn users_page(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let msg_html = flash_messages_html(&flash_messages);
+
+    let mut users_html = String::new();
+    for user in list_users(&pool).await.map_err(e500)? {
+        let (status, toggle) = if user.is_active {
+            ("active", "deactivate")
+        } else {
+            ("deactivated", "reactivate")
+        };
+        writeln!(
+            users_html,
+            r#"<tr>
+                <td>{username}</td>
+                <td>{email}</td>
+                <td>{role}</td>
+                <td>{status}</td>
+                <td>
+                    <form action="/admin/users/{user_id}/{toggle}" method="post">
+                        <button type="submit">{toggle}</button>
+                    </form>
+                    <form action="/admin/users/{user_id}/delete" method="post">
+                        <button type="submit">delete</button>
+                    </form>
+                </td>
+            </tr>"#,
+            username = escape_html(&user.username),
+            email = escape_html(user.email.as_deref().unwrap_or("")),
+            role = user.role,
+            user_id = user.user_id,
+        )
+        .unwrap();
+    }
+
+    let mut invitations_html = String::new();
+    for invitation in list_pending_invitations(&pool).await.map_err(e500)? {
+        writeln!(
+            invitations_html,
+            "<li>{} ({})</li>",
+            escape_html(&invitation.email),
+            invitation.role
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Users</title>
+    </head>
+    <body>
+        <p>Users</p>
+        {msg_html}
+        <table>
+            <tr><th>Username</th><th>Email</th><th>Role</th><th>Status</th><th></th></tr>
+            {users_html}
+        </table>
+
+        <p>Pending invitations</p>
+        <ul>
+            {invitations_html}
+        </ul>
+
+        <p>Invite a new user</p>
+        <form action="/admin/users/invitations" method="post">
+            <label>Email
+                <input
+                    type="email"
+                    placeholder="Enter the email address of the new user"
+                    name="email"
+                >
+            </label>
+
+            <label>Role
+                <select name="role">
+                    <option value="viewer">viewer</option>
+                    <option value="editor">editor</option>
+                    <option value="owner">owner</option>
+                </select>
+            </label>
+
+            <button type="submit">Send invitation</button>
+        </form>
+
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#
+        )))
+}
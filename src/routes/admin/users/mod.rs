@@ -0,0 +1,7 @@
+mod actions;
+mod get;
+mod post;
+
+pub use actions::{deactivate_user, reactivate_user, remove_user};
+pub use get::users_page;
+pub use post::invite_user;
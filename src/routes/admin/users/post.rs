@@ -0,0 +1,114 @@
+use std::ops::Deref;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::audit::{AuditAction, record_audit_event};
+use crate::authentication::{CreateUserError, Role, UserId, create_invitation};
+use crate::consent::RequestMetadata;
+use crate::domain::SubscriberEmail;
+use crate::email_client::{EmailClient, SendEmailError};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+    role: String,
+}
+
+#[tracing::instrument(
+    name = "Invite a new user",
+    skip(form, pool, email_client, base_url, user_id, request),
+    fields(user_id = %user_id.deref(), role = %form.role)
+)]
+pub async fn invite_user(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user_id: web::ReqData<UserId>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData { email, role } = form.0;
+
+    let email = match SubscriberEmail::parse(email) {
+        Ok(email) => email,
+        Err(_) => {
+            FlashMessage::error("Please enter a valid email address.").send();
+            return Ok(see_other("/admin/users"));
+        }
+    };
+    let role = match Role::try_from(role) {
+        Ok(role) => role,
+        Err(_) => {
+            FlashMessage::error("Please choose a valid role.").send();
+            return Ok(see_other("/admin/users"));
+        }
+    };
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let token = match create_invitation(&mut transaction, &email, role, **user_id).await {
+        Ok(token) => token,
+        Err(CreateUserError::EmailTaken(_)) => {
+            FlashMessage::error("A user with this email address already exists.").send();
+            return Ok(see_other("/admin/users"));
+        }
+        Err(e) => return Err(e500(e)),
+    };
+    record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::InviteUser,
+        None,
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a new user invitation")
+        .map_err(e500)?;
+
+    send_invitation_email(&email_client, &email, role, &base_url.0, &token)
+        .await
+        .context("Failed to send an invitation email")
+        .map_err(e500)?;
+
+    FlashMessage::info("The invitation has been sent.").send();
+    Ok(see_other("/admin/users"))
+}
+
+#[tracing::instrument(
+    name = "Send an invitation email to a new user"
+    skip(email_client, recipient, base_url, token)
+)]
+async fn send_invitation_email(
+    email_client: &EmailClient,
+    recipient: &SubscriberEmail,
+    role: Role,
+    base_url: &str,
+    token: &str,
+) -> Result<(), SendEmailError> {
+    let invitation_link = format!("{}/invitations/accept?invitation_token={}", base_url, token);
+
+    let html_body = format!(
+        "You have been invited to help run our newsletter with the {role} role.<br />\
+                Click <a href=\"{invitation_link}\">here</a> to choose your username and password."
+    );
+    let plain_body = format!(
+        "You have been invited to help run our newsletter with the {role} role.\n\
+                Visit {invitation_link} to choose your username and password."
+    );
+
+    email_client
+        .send_email(recipient, "You have been invited", &html_body, &plain_body)
+        .await
+}
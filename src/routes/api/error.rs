@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError, web};
+use utoipa::openapi::{ContentBuilder, RefOr, Response, ResponseBuilder};
+use utoipa::{IntoResponses, PartialSchema};
+
+use crate::routes::error_chain_fmt;
+
+/// Errors returned by the JSON API, rendered as `{"error": "<message>"}`.
+#[derive(thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+#[schema(as = Error)]
+pub struct ErrorBody {
+    /// What went wrong, suitable to show to people.
+    error: String,
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        // The cause chain of unexpected errors is logged, never sent to the client.
+        let error = match self {
+            Self::UnexpectedError(_) => "Something went wrong.".to_string(),
+            e => e.to_string(),
+        };
+        HttpResponse::build(self.status_code()).json(ErrorBody { error })
+    }
+}
+
+/// The errors every endpoint can return. Endpoints document `404` and `409` themselves.
+impl IntoResponses for ApiError {
+    fn responses() -> BTreeMap<String, RefOr<Response>> {
+        BTreeMap::from([
+            error_response(StatusCode::BAD_REQUEST, "The request is invalid"),
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Something went wrong"),
+        ])
+    }
+}
+
+fn error_response(status: StatusCode, description: &str) -> (String, RefOr<Response>) {
+    let response = ResponseBuilder::new()
+        .description(description)
+        .content(
+            "application/json",
+            ContentBuilder::new()
+                .schema(Some(ErrorBody::schema()))
+                .build(),
+        )
+        .build();
+    (status.as_str().to_string(), response.into())
+}
+
+/// Reports malformed request bodies in the same shape as other API errors.
+pub fn api_json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|e, _| ApiError::ValidationError(e.to_string()).into())
+}
+
+/// Reports malformed query strings in the same shape as other API errors.
+pub fn api_query_config() -> web::QueryConfig {
+    web::QueryConfig::default()
+        .error_handler(|e, _| ApiError::ValidationError(e.to_string()).into())
+}
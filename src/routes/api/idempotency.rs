@@ -0,0 +1,68 @@
+use actix_web::{HttpRequest, HttpResponse};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::idempotency::{IdempotencyKey, NextAction, save_response, try_processing};
+
+use super::ApiError;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Documents the optional `Idempotency-Key` header of an endpoint.
+#[derive(utoipa::IntoParams)]
+#[into_params(parameter_in = Header)]
+pub struct IdempotencyKeyHeader {
+    /// Retrying with the same key returns the original response instead of
+    /// repeating the action. Keys are scoped to the user and shorter than 50 characters.
+    #[param(rename = "Idempotency-Key")]
+    #[allow(dead_code)]
+    idempotency_key: Option<String>,
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum ApiNextAction {
+    StartProcessing(Transaction<'static, Postgres>, Option<IdempotencyKey>),
+    ReturnSavedResponse(HttpResponse),
+}
+
+/// Like [`try_processing`], for requests where the `Idempotency-Key` header is optional.
+/// Requests without it are processed every time they are received.
+pub async fn try_processing_request(
+    request: &HttpRequest,
+    pool: &PgPool,
+    user_id: &Uuid,
+) -> Result<ApiNextAction, ApiError> {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        let transaction = pool.begin().await.map_err(anyhow::Error::from)?;
+        return Ok(ApiNextAction::StartProcessing(transaction, None));
+    };
+
+    let key: IdempotencyKey = key
+        .to_str()
+        .map_err(anyhow::Error::from)
+        .and_then(|key| key.to_string().try_into())
+        .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+
+    Ok(match try_processing(pool, &key, user_id).await? {
+        NextAction::StartProcessing(transaction) => {
+            ApiNextAction::StartProcessing(transaction, Some(key))
+        }
+        NextAction::ReturnSavedResponse(response) => ApiNextAction::ReturnSavedResponse(response),
+    })
+}
+
+/// Commits `transaction`, saving `response` first if the request had an idempotency key.
+pub async fn finish_processing_request(
+    user_id: &Uuid,
+    key: Option<IdempotencyKey>,
+    response: HttpResponse,
+    transaction: Transaction<'static, Postgres>,
+) -> Result<HttpResponse, ApiError> {
+    match key {
+        Some(key) => Ok(save_response(user_id, &key, response, transaction).await?),
+        None => {
+            transaction.commit().await.map_err(anyhow::Error::from)?;
+            Ok(response)
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use actix_web::{HttpResponse, web};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::newsletter_issues::{get_delivery_status, get_newsletter_issue, list_newsletter_issues};
+
+use super::{
+    ApiError, ErrorBody, IssueResponse, IssueStatus, IssueSummaryResponse, issue_not_found,
+};
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+struct IssueList {
+    issues: Vec<IssueSummaryResponse>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+#[schema(as = DeliveryStatus)]
+struct DeliveryStatusResponse {
+    issue_id: Uuid,
+    status: IssueStatus,
+    recipients: i32,
+    delivered: i32,
+    failed: i32,
+    pending: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/issues",
+    tag = "issues",
+    responses(
+        (status = 200, description = "Issues, most recently created first", body = IssueList),
+        ApiError
+    )
+)]
+#[tracing::instrument(name = "List issues", skip(pool))]
+pub async fn list_issues(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
+    let issues = list_newsletter_issues(&pool).await?;
+
+    Ok(HttpResponse::Ok().json(IssueList {
+        issues: issues.into_iter().map(Into::into).collect(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/issues/{issue_id}",
+    tag = "issues",
+    params(("issue_id" = Uuid, Path)),
+    responses(
+        (status = 200, body = IssueResponse),
+        (status = 404, description = "There is no such issue", body = ErrorBody),
+        ApiError
+    )
+)]
+#[tracing::instrument(name = "Get an issue", skip(pool))]
+pub async fn get_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let issue_id = issue_id.into_inner();
+    let issue = get_newsletter_issue(issue_id, pool.get_ref())
+        .await?
+        .ok_or_else(|| issue_not_found(issue_id))?;
+
+    Ok(HttpResponse::Ok().json(IssueResponse::from(issue)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/issues/{issue_id}/delivery",
+    tag = "issues",
+    params(("issue_id" = Uuid, Path)),
+    responses(
+        (status = 200, body = DeliveryStatusResponse),
+        (status = 404, description = "There is no such issue", body = ErrorBody),
+        ApiError
+    )
+)]
+#[tracing::instrument(name = "Get the delivery status of an issue", skip(pool))]
+pub async fn issue_delivery_status(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let issue_id = issue_id.into_inner();
+    let delivery = get_delivery_status(issue_id, &pool)
+        .await?
+        .ok_or_else(|| issue_not_found(issue_id))?;
+
+    Ok(HttpResponse::Ok().json(DeliveryStatusResponse {
+        issue_id,
+        status: IssueStatus::of(delivery.published_at),
+        recipients: delivery.recipients,
+        delivered: delivery.delivered,
+        failed: delivery.failed,
+        pending: delivery.pending,
+    }))
+}
@@ -0,0 +1,104 @@
+mod get;
+mod patch;
+mod post;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::newsletter_issues::{NewsletterIssue, NewsletterIssueSummary};
+
+use super::{ApiError, ErrorBody};
+
+pub use get::*;
+pub use patch::*;
+pub use post::*;
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum IssueStatus {
+    Draft,
+    Published,
+}
+
+impl IssueStatus {
+    fn of(published_at: Option<DateTime<Utc>>) -> Self {
+        match published_at {
+            Some(_) => Self::Published,
+            None => Self::Draft,
+        }
+    }
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+#[schema(as = Issue)]
+struct IssueResponse {
+    issue_id: Uuid,
+    title: String,
+    text_content: String,
+    html_content: String,
+    /// Whether links are replaced by redirects recording clicks.
+    track_clicks: bool,
+    /// Whether an image recording opens is added to the HTML content.
+    track_opens: bool,
+    status: IssueStatus,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    published_at: Option<DateTime<Utc>>,
+}
+
+impl From<NewsletterIssue> for IssueResponse {
+    fn from(issue: NewsletterIssue) -> Self {
+        Self {
+            issue_id: issue.newsletter_issue_id,
+            title: issue.title,
+            text_content: issue.text_content,
+            html_content: issue.html_content,
+            track_clicks: issue.track_clicks,
+            track_opens: issue.track_opens,
+            status: IssueStatus::of(issue.published_at),
+            created_at: issue.created_at,
+            updated_at: issue.updated_at,
+            published_at: issue.published_at,
+        }
+    }
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+#[schema(as = IssueSummary)]
+struct IssueSummaryResponse {
+    issue_id: Uuid,
+    title: String,
+    status: IssueStatus,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    published_at: Option<DateTime<Utc>>,
+}
+
+impl From<NewsletterIssueSummary> for IssueSummaryResponse {
+    fn from(issue: NewsletterIssueSummary) -> Self {
+        Self {
+            issue_id: issue.newsletter_issue_id,
+            title: issue.title,
+            status: IssueStatus::of(issue.published_at),
+            created_at: issue.created_at,
+            updated_at: issue.updated_at,
+            published_at: issue.published_at,
+        }
+    }
+}
+
+fn issue_not_found(issue_id: Uuid) -> ApiError {
+    ApiError::NotFound(format!("There is no issue with id {}.", issue_id))
+}
+
+/// Issues can't be sent with an empty subject or body.
+fn validate_field(name: &str, value: String) -> Result<String, ApiError> {
+    if value.trim().is_empty() {
+        Err(ApiError::ValidationError(format!(
+            "The {} cannot be empty.",
+            name
+        )))
+    } else {
+        Ok(value)
+    }
+}
@@ -0,0 +1,107 @@
+use std::ops::Deref;
+
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::newsletter_issues::{IssueTracking, get_newsletter_issue, update_draft_issue};
+
+use super::{ApiError, ErrorBody, IssueResponse, issue_not_found, validate_field};
+
+/// Fields left out are not changed.
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct IssueChanges {
+    title: Option<String>,
+    text_content: Option<String>,
+    html_content: Option<String>,
+    track_clicks: Option<bool>,
+    track_opens: Option<bool>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/issues/{issue_id}",
+    tag = "issues",
+    params(("issue_id" = Uuid, Path)),
+    request_body = IssueChanges,
+    responses(
+        (status = 200, body = IssueResponse),
+        (status = 404, description = "There is no such issue", body = ErrorBody),
+        (status = 409, description = "The issue has already been published", body = ErrorBody),
+        ApiError
+    )
+)]
+#[tracing::instrument(
+    name = "Update a draft issue",
+    skip(body, pool, user_id),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn update_issue(
+    issue_id: web::Path<Uuid>,
+    body: web::Json<IssueChanges>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, ApiError> {
+    let issue_id = issue_id.into_inner();
+    let changes = body.into_inner();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let issue = get_newsletter_issue(issue_id, &mut *transaction)
+        .await?
+        .ok_or_else(|| issue_not_found(issue_id))?;
+    if issue.published_at.is_some() {
+        return Err(ApiError::Conflict(
+            "Published issues can't be changed.".into(),
+        ));
+    }
+
+    let title = match changes.title {
+        Some(title) => validate_field("title", title)?,
+        None => issue.title,
+    };
+    let text_content = match changes.text_content {
+        Some(text_content) => validate_field("text content", text_content)?,
+        None => issue.text_content,
+    };
+    let html_content = match changes.html_content {
+        Some(html_content) => validate_field("HTML content", html_content)?,
+        None => issue.html_content,
+    };
+    let tracking = IssueTracking {
+        clicks: changes.track_clicks.unwrap_or(issue.track_clicks),
+        opens: changes.track_opens.unwrap_or(issue.track_opens),
+    };
+
+    // Published in the meantime.
+    if !update_draft_issue(
+        &mut transaction,
+        issue_id,
+        &title,
+        &text_content,
+        &html_content,
+        tracking,
+    )
+    .await
+    .context("Failed to update the newsletter issue")?
+    {
+        return Err(ApiError::Conflict(
+            "Published issues can't be changed.".into(),
+        ));
+    }
+
+    let issue = get_newsletter_issue(issue_id, &mut *transaction)
+        .await?
+        .context("The updated issue was not found")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to update an issue")?;
+
+    Ok(HttpResponse::Ok().json(IssueResponse::from(issue)))
+}
@@ -0,0 +1,160 @@
+use std::ops::Deref;
+
+use actix_web::http::header::LOCATION;
+use actix_web::{HttpRequest, HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditTarget, record_audit_event};
+use crate::authentication::UserId;
+use crate::consent::RequestMetadata;
+use crate::newsletter_issues::{
+    IssueTracking, enqueue_delivery_tasks, get_newsletter_issue, insert_newsletter_issue,
+    lock_newsletter_issue,
+};
+use crate::routes::api::idempotency::{
+    ApiNextAction, IdempotencyKeyHeader, finish_processing_request, try_processing_request,
+};
+
+use super::{ApiError, ErrorBody, IssueResponse, issue_not_found, validate_field};
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct NewIssue {
+    title: String,
+    text_content: String,
+    html_content: String,
+    /// Whether links are replaced by redirects recording clicks. Defaults to `true`.
+    track_clicks: Option<bool>,
+    /// Whether an image recording opens is added to the HTML content, if open tracking
+    /// is enabled on the server. Defaults to `true`.
+    track_opens: Option<bool>,
+}
+
+/// Stores a draft. It is only sent once published.
+#[utoipa::path(
+    post,
+    path = "/api/v1/issues",
+    tag = "issues",
+    params(IdempotencyKeyHeader),
+    request_body = NewIssue,
+    responses(
+        (status = 201, description = "The draft was created", body = IssueResponse),
+        ApiError
+    )
+)]
+#[tracing::instrument(
+    name = "Create a draft issue",
+    skip(body, request, pool, user_id),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn create_issue(
+    body: web::Json<NewIssue>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, ApiError> {
+    let NewIssue {
+        title,
+        text_content,
+        html_content,
+        track_clicks,
+        track_opens,
+    } = body.into_inner();
+    let title = validate_field("title", title)?;
+    let text_content = validate_field("text content", text_content)?;
+    let html_content = validate_field("HTML content", html_content)?;
+
+    let (mut transaction, idempotency_key) =
+        match try_processing_request(&request, &pool, &user_id).await? {
+            ApiNextAction::ReturnSavedResponse(response) => return Ok(response),
+            ApiNextAction::StartProcessing(transaction, key) => (transaction, key),
+        };
+
+    let tracking = IssueTracking {
+        clicks: track_clicks.unwrap_or(true),
+        opens: track_opens.unwrap_or(true),
+    };
+    let issue_id = insert_newsletter_issue(
+        &mut transaction,
+        &title,
+        &text_content,
+        &html_content,
+        tracking,
+    )
+    .await
+    .context("Failed to store newsletter issue details")?;
+    let issue = get_newsletter_issue(issue_id, &mut *transaction)
+        .await?
+        .context("The new issue was not found")?;
+
+    let response = HttpResponse::Created()
+        .insert_header((LOCATION, format!("/api/v1/issues/{}", issue_id)))
+        .json(IssueResponse::from(issue));
+    finish_processing_request(&user_id, idempotency_key, response, transaction).await
+}
+
+/// Sends the issue to every confirmed subscriber. Progress is reported by
+/// [`super::issue_delivery_status`].
+#[utoipa::path(
+    post,
+    path = "/api/v1/issues/{issue_id}/publish",
+    tag = "issues",
+    params(("issue_id" = Uuid, Path), IdempotencyKeyHeader),
+    responses(
+        (status = 202, description = "The issue is being delivered", body = IssueResponse),
+        (status = 404, description = "There is no such issue", body = ErrorBody),
+        (status = 409, description = "The issue has already been published", body = ErrorBody),
+        ApiError
+    )
+)]
+#[tracing::instrument(
+    name = "Publish an issue",
+    skip(request, pool, user_id),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn publish_issue(
+    issue_id: web::Path<Uuid>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, ApiError> {
+    let issue_id = issue_id.into_inner();
+
+    let (mut transaction, idempotency_key) =
+        match try_processing_request(&request, &pool, &user_id).await? {
+            ApiNextAction::ReturnSavedResponse(response) => return Ok(response),
+            ApiNextAction::StartProcessing(transaction, key) => (transaction, key),
+        };
+
+    match lock_newsletter_issue(&mut transaction, issue_id)
+        .await
+        .context("Failed to lock the newsletter issue")?
+    {
+        None => return Err(issue_not_found(issue_id)),
+        Some(true) => {
+            return Err(ApiError::Conflict(
+                "The issue has already been published.".into(),
+            ));
+        }
+        Some(false) => {}
+    }
+
+    enqueue_delivery_tasks(&mut transaction, issue_id)
+        .await
+        .context("Failed to enqueue delivery tasks")?;
+    record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::PublishIssue,
+        Some(AuditTarget::Issue(issue_id)),
+        &RequestMetadata::from_request(&request),
+    )
+    .await?;
+    let issue = get_newsletter_issue(issue_id, &mut *transaction)
+        .await?
+        .context("The published issue was not found")?;
+
+    let response = HttpResponse::Accepted().json(IssueResponse::from(issue));
+    finish_processing_request(&user_id, idempotency_key, response, transaction).await
+}
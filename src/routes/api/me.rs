@@ -0,0 +1,55 @@
+use std::ops::Deref;
+
+use actix_web::{HttpResponse, web};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{ApiTokenScopes, Scope, UserId, get_role};
+use crate::routes::get_username;
+use crate::utils::e500;
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+struct CurrentClient {
+    user_id: Uuid,
+    username: String,
+    role: Option<&'static str>,
+    /// `None` for session-authenticated requests, which are not restricted.
+    scopes: Option<Vec<&'static str>>,
+}
+
+/// Lets API clients check which user, and which scopes, their token grants.
+#[utoipa::path(
+    get,
+    path = "/api/v1/me",
+    tag = "api",
+    responses((status = 200, body = CurrentClient))
+)]
+#[tracing::instrument(
+    name = "Get current API client",
+    skip(pool, user_id, scopes),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn current_api_client(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    scopes: Option<web::ReqData<ApiTokenScopes>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+
+    let username = get_username(*user_id, &pool).await.map_err(e500)?;
+    let role = get_role(*user_id, &pool).await.map_err(e500)?;
+    let scopes = scopes.map(|scopes| {
+        Scope::ALL
+            .into_iter()
+            .filter(|scope| scopes.allows(*scope))
+            .map(|scope| scope.as_str())
+            .collect()
+    });
+
+    Ok(HttpResponse::Ok().json(CurrentClient {
+        user_id: *user_id,
+        username,
+        role: role.map(|role| role.as_str()),
+        scopes,
+    }))
+}
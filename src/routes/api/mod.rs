@@ -0,0 +1,13 @@
+mod error;
+mod idempotency;
+mod issues;
+mod me;
+mod openapi;
+mod subscribers;
+
+use error::{ApiError, ErrorBody};
+pub use error::{api_json_config, api_query_config};
+pub use issues::*;
+pub use me::*;
+pub use openapi::{ApiDoc, openapi_document, openapi_viewer};
+pub use subscribers::*;
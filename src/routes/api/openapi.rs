@@ -0,0 +1,105 @@
+use actix_web::{HttpResponse, web};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{ResponseBuilder, SecurityRequirement};
+use utoipa::{Modify, OpenApi};
+#[cfg(feature = "openapi-viewer")]
+use utoipa_scalar::{Scalar, Servable};
+
+use crate::routes::{health_check, subscriptions, subscriptions_confirm};
+
+use super::{issues, me, subscribers};
+
+/// The machine-readable contract of the public endpoints and the JSON API.
+/// HTML pages under `/admin` are left out.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Newsletter backend"),
+    paths(
+        health_check::health_check,
+        subscriptions::subscribe,
+        subscriptions_confirm::confirm,
+        me::current_api_client,
+        issues::list_issues,
+        issues::create_issue,
+        issues::get_issue,
+        issues::update_issue,
+        issues::publish_issue,
+        issues::issue_delivery_status,
+        subscribers::list_subscribers,
+        subscribers::create_subscriber,
+        subscribers::get_subscriber,
+        subscribers::update_subscriber,
+        subscribers::delete_subscriber,
+    ),
+    modifiers(&ApiAuthentication)
+)]
+pub struct ApiDoc;
+
+/// Every `/api/v1` endpoint accepts an API token, or the session cookie of a
+/// logged in user, and may reject either.
+struct ApiAuthentication;
+
+impl Modify for ApiAuthentication {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("A token created at /admin/api-tokens"))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("id"))),
+        );
+
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if !path.starts_with("/api/v1/") {
+                continue;
+            }
+            let operations = [
+                &mut item.get,
+                &mut item.post,
+                &mut item.patch,
+                &mut item.delete,
+            ];
+            for operation in operations.into_iter().flatten() {
+                operation.security = Some(vec![
+                    SecurityRequirement::new("api_token", Vec::<String>::new()),
+                    SecurityRequirement::new("session", Vec::<String>::new()),
+                ]);
+                operation.responses.responses.insert(
+                    "401".into(),
+                    ResponseBuilder::new()
+                        .description("No valid API token or session was provided")
+                        .build()
+                        .into(),
+                );
+                operation.responses.responses.insert(
+                    "403".into(),
+                    ResponseBuilder::new()
+                        .description(
+                            "The token lacks the required scope, or its user the required role",
+                        )
+                        .build()
+                        .into(),
+                );
+            }
+        }
+    }
+}
+
+pub async fn openapi_document() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+/// Serves an interactive viewer for [`ApiDoc`] at `/api/docs`, when built with
+/// the `openapi-viewer` feature.
+#[cfg_attr(not(feature = "openapi-viewer"), allow(unused_variables))]
+pub fn openapi_viewer(cfg: &mut web::ServiceConfig) {
+    #[cfg(feature = "openapi-viewer")]
+    cfg.service(Scalar::with_url("/api/docs", ApiDoc::openapi()));
+}
@@ -0,0 +1,51 @@
+use std::ops::Deref;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditTarget, record_audit_event};
+use crate::authentication::UserId;
+use crate::consent::RequestMetadata;
+use crate::subscribers;
+
+use super::{ApiError, ErrorBody, subscriber_not_found};
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/subscribers/{subscriber_id}",
+    tag = "subscribers",
+    params(("subscriber_id" = Uuid, Path)),
+    responses(
+        (status = 204, description = "The subscriber was deleted"),
+        (status = 404, description = "There is no such subscriber", body = ErrorBody),
+        ApiError
+    )
+)]
+#[tracing::instrument(
+    name = "Delete a subscriber through the API",
+    skip(request, pool, user_id),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn delete_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, ApiError> {
+    let subscriber_id = subscriber_id.into_inner();
+
+    if !subscribers::delete_subscriber(subscriber_id, &pool).await? {
+        return Err(subscriber_not_found(subscriber_id));
+    }
+    record_audit_event(
+        pool.get_ref(),
+        **user_id,
+        AuditAction::DeleteSubscriber,
+        Some(AuditTarget::Subscriber(subscriber_id)),
+        &RequestMetadata::from_request(&request),
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,106 @@
+use actix_web::{HttpResponse, web};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::subscribers::{self, SubscriberFilters};
+
+use super::{ApiError, ErrorBody, SubscriberResponse, subscriber_not_found};
+
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 500;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// One of `pending_confirmation`, `confirmed` or `unsubscribed`.
+    status: Option<String>,
+    /// Inclusive.
+    subscribed_after: Option<DateTime<Utc>>,
+    /// Exclusive.
+    subscribed_before: Option<DateTime<Utc>>,
+    /// Matches anywhere in the email or name, ignoring case.
+    search: Option<String>,
+    /// 50 by default, at most 500.
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+struct SubscriberList {
+    subscribers: Vec<SubscriberResponse>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/subscribers",
+    tag = "subscribers",
+    params(ListQuery),
+    responses(
+        (status = 200, description = "Subscribers, most recent first", body = SubscriberList),
+        ApiError
+    )
+)]
+#[tracing::instrument(name = "List subscribers through the API", skip(query, pool))]
+pub async fn list_subscribers(
+    query: web::Query<ListQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let query = query.into_inner();
+
+    let status = query
+        .status
+        .map(TryInto::try_into)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+    let filters = SubscriberFilters {
+        status,
+        subscribed_after: query.subscribed_after,
+        subscribed_before: query.subscribed_before,
+        search: query.search.filter(|search| !search.trim().is_empty()),
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::ValidationError(format!(
+            "The limit must be between 1 and {}.",
+            MAX_LIMIT
+        )));
+    }
+    let offset = query.offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(ApiError::ValidationError(
+            "The offset cannot be negative.".into(),
+        ));
+    }
+
+    let subscribers = subscribers::list_subscribers(&filters, limit, offset, &pool).await?;
+
+    Ok(HttpResponse::Ok().json(SubscriberList {
+        subscribers: subscribers.into_iter().map(Into::into).collect(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/subscribers/{subscriber_id}",
+    tag = "subscribers",
+    params(("subscriber_id" = Uuid, Path)),
+    responses(
+        (status = 200, body = SubscriberResponse),
+        (status = 404, description = "There is no such subscriber", body = ErrorBody),
+        ApiError
+    )
+)]
+#[tracing::instrument(name = "Get a subscriber through the API", skip(pool))]
+pub async fn get_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let subscriber_id = subscriber_id.into_inner();
+    let subscriber = subscribers::get_subscriber(subscriber_id, pool.get_ref())
+        .await?
+        .ok_or_else(|| subscriber_not_found(subscriber_id))?;
+
+    Ok(HttpResponse::Ok().json(SubscriberResponse::from(subscriber)))
+}
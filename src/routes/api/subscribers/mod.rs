@@ -0,0 +1,46 @@
+mod delete;
+mod get;
+mod patch;
+mod post;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::subscribers::Subscriber;
+
+use super::{ApiError, ErrorBody};
+
+pub use delete::*;
+pub use get::*;
+pub use patch::*;
+pub use post::*;
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+#[schema(as = Subscriber)]
+struct SubscriberResponse {
+    subscriber_id: Uuid,
+    email: String,
+    name: String,
+    /// One of `pending_confirmation`, `confirmed` or `unsubscribed`.
+    status: &'static str,
+    subscribed_at: DateTime<Utc>,
+    /// Whether the subscriber asked not to have their opens and clicks tracked.
+    do_not_track: bool,
+}
+
+impl From<Subscriber> for SubscriberResponse {
+    fn from(subscriber: Subscriber) -> Self {
+        Self {
+            subscriber_id: subscriber.subscriber_id,
+            email: subscriber.email,
+            name: subscriber.name,
+            status: subscriber.status.as_str(),
+            subscribed_at: subscriber.subscribed_at,
+            do_not_track: subscriber.do_not_track,
+        }
+    }
+}
+
+fn subscriber_not_found(subscriber_id: Uuid) -> ApiError {
+    ApiError::NotFound(format!("There is no subscriber with id {}.", subscriber_id))
+}
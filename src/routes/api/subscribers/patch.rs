@@ -0,0 +1,100 @@
+use std::ops::Deref;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, AuditTarget, record_audit_event};
+use crate::authentication::UserId;
+use crate::consent::RequestMetadata;
+use crate::domain::{SubscriberName, SubscriptionStatus};
+use crate::subscribers;
+
+use super::{ApiError, ErrorBody, SubscriberResponse, subscriber_not_found};
+
+/// Fields left out are not changed. Email addresses can't be changed, since
+/// subscribers confirmed ownership of theirs.
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct SubscriberChanges {
+    name: Option<String>,
+    /// One of `pending_confirmation`, `confirmed` or `unsubscribed`.
+    status: Option<String>,
+    /// Stops tracking the opens and clicks of the subscriber, as they asked.
+    do_not_track: Option<bool>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/subscribers/{subscriber_id}",
+    tag = "subscribers",
+    params(("subscriber_id" = Uuid, Path)),
+    request_body = SubscriberChanges,
+    responses(
+        (status = 200, body = SubscriberResponse),
+        (status = 404, description = "There is no such subscriber", body = ErrorBody),
+        ApiError
+    )
+)]
+#[tracing::instrument(
+    name = "Update a subscriber through the API",
+    skip(body, request, pool, user_id),
+    fields(user_id = %user_id.deref())
+)]
+pub async fn update_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    body: web::Json<SubscriberChanges>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, ApiError> {
+    let subscriber_id = subscriber_id.into_inner();
+    let changes = body.into_inner();
+
+    let name = changes
+        .name
+        .map(SubscriberName::parse)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+    let status = changes
+        .status
+        .map(SubscriptionStatus::try_from)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    if !subscribers::update_subscriber(
+        &mut *transaction,
+        subscriber_id,
+        name.as_ref(),
+        status,
+        changes.do_not_track,
+    )
+    .await?
+    {
+        return Err(subscriber_not_found(subscriber_id));
+    }
+
+    record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::UpdateSubscriber,
+        Some(AuditTarget::Subscriber(subscriber_id)),
+        &RequestMetadata::from_request(&request),
+    )
+    .await?;
+
+    let subscriber = subscribers::get_subscriber(subscriber_id, &mut *transaction)
+        .await?
+        .context("The updated subscriber was not found")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to update a subscriber")?;
+
+    Ok(HttpResponse::Ok().json(SubscriberResponse::from(subscriber)))
+}
@@ -0,0 +1,144 @@
+use std::ops::Deref;
+
+use actix_web::http::header::LOCATION;
+use actix_web::{HttpRequest, HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::audit::{AuditAction, AuditTarget, record_audit_event};
+use crate::authentication::UserId;
+use crate::consent::{RequestMetadata, SignupSource, record_signup};
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionStatus};
+use crate::email_client::EmailClient;
+use crate::routes::api::idempotency::{
+    ApiNextAction, IdempotencyKeyHeader, finish_processing_request, try_processing_request,
+};
+use crate::routes::{
+    generate_subscription_token, insert_subscriber, send_confirmation_email, store_token,
+};
+use crate::startup::ApplicationBaseUrl;
+use crate::subscribers;
+
+use super::{ApiError, ErrorBody, SubscriberResponse};
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+#[schema(as = NewSubscriber)]
+pub struct NewSubscriberBody {
+    email: String,
+    name: String,
+    /// Adds the subscriber as confirmed instead of sending them a confirmation email.
+    /// Only use it for people who already agreed to receive the newsletter.
+    #[serde(default)]
+    skip_confirmation: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/subscribers",
+    tag = "subscribers",
+    params(IdempotencyKeyHeader),
+    request_body = NewSubscriberBody,
+    responses(
+        (status = 201, description = "The subscriber was added", body = SubscriberResponse),
+        (status = 409, description = "The email is already subscribed", body = ErrorBody),
+        ApiError
+    )
+)]
+#[tracing::instrument(
+    name = "Create a subscriber through the API",
+    skip(body, request, pool, email_client, base_url, user_id),
+    fields(
+        user_id = %user_id.deref(),
+        subscriber_email = %body.email,
+        skip_confirmation = %body.skip_confirmation
+    )
+)]
+pub async fn create_subscriber(
+    body: web::Json<NewSubscriberBody>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, ApiError> {
+    let body = body.into_inner();
+    let new_subscriber = NewSubscriber {
+        email: SubscriberEmail::parse(body.email).map_err(ApiError::ValidationError)?,
+        name: SubscriberName::parse(body.name).map_err(ApiError::ValidationError)?,
+    };
+    let status = if body.skip_confirmation {
+        SubscriptionStatus::Confirmed
+    } else {
+        SubscriptionStatus::PendingConfirmation
+    };
+
+    let (mut transaction, idempotency_key) =
+        match try_processing_request(&request, &pool, &user_id).await? {
+            ApiNextAction::ReturnSavedResponse(response) => return Ok(response),
+            ApiNextAction::StartProcessing(transaction, key) => (transaction, key),
+        };
+
+    let subscriber_id = match insert_subscriber(&mut transaction, &new_subscriber, status).await {
+        Ok(subscriber_id) => subscriber_id,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(ApiError::Conflict(
+                "There already is a subscriber with this email.".into(),
+            ));
+        }
+        Err(e) => {
+            return Err(anyhow::Error::from(e)
+                .context("Failed to insert new subscriber in the database")
+                .into());
+        }
+    };
+
+    let metadata = RequestMetadata::from_request(&request);
+    record_signup(
+        &mut *transaction,
+        subscriber_id,
+        SignupSource::Api,
+        &metadata,
+    )
+    .await?;
+    record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::CreateSubscriber,
+        Some(AuditTarget::Subscriber(subscriber_id)),
+        &metadata,
+    )
+    .await?;
+
+    let subscription_token = match status {
+        SubscriptionStatus::PendingConfirmation => {
+            let subscription_token = generate_subscription_token();
+            store_token(&mut transaction, subscriber_id, &subscription_token)
+                .await
+                .context("Failed to store the confirmation token for a new subscriber")?;
+            Some(subscription_token)
+        }
+        _ => None,
+    };
+
+    let subscriber = subscribers::get_subscriber(subscriber_id, &mut *transaction)
+        .await?
+        .context("The new subscriber was not found")?;
+    let response = HttpResponse::Created()
+        .insert_header((LOCATION, format!("/api/v1/subscribers/{}", subscriber_id)))
+        .json(SubscriberResponse::from(subscriber));
+    let response =
+        finish_processing_request(&user_id, idempotency_key, response, transaction).await?;
+
+    if let Some(subscription_token) = subscription_token {
+        send_confirmation_email(
+            email_client.get_ref(),
+            new_subscriber,
+            &base_url.0,
+            &subscription_token,
+        )
+        .await
+        .context("Failed to send a confirmation email")?;
+    }
+
+    Ok(response)
+}
@@ -0,0 +1,97 @@
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+use crate::personal_data::{DataRequestKind, get_data_request};
+use crate::utils::{e500, escape_html, flash_messages_html};
+
+pub async fn data_request_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
+    let msg_html = flash_messages_html(&flash_messages);
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Your personal data</title>
+    </head>
+    <body>
+        {msg_html}
+        <p>Enter the email address you subscribed with, we will email you a link to complete your request.</p>
+        <form action="/subscriptions/personal-data" method="post">
+            <label>Email
+                <input type="email" placeholder="Enter your email address" name="email">
+            </label>
+            <label>
+                <input type="radio" name="kind" value="access" checked>
+                Send me a copy of my data
+            </label>
+            <label>
+                <input type="radio" name="kind" value="erasure">
+                Delete my data
+            </label>
+
+            <button type="submit">Send link</button>
+        </form>
+    </body>
+</html>
+            "#
+        ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    token: String,
+}
+
+/// The request is only carried out once submitted, so that links opened by mail scanners
+/// do not use up the token.
+#[tracing::instrument(name = "Get data request confirmation form", skip(parameters, pool))]
+pub async fn data_request_confirmation_form(
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(request) = get_data_request(&parameters.token, &pool)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let (description, button) = match request.kind {
+        DataRequestKind::Access => (
+            "Download a copy of everything we store about you.",
+            "Download my data",
+        ),
+        DataRequestKind::Erasure => (
+            "Delete everything we store about you, including your subscription. \
+            This can't be undone.",
+            "Delete my data",
+        ),
+    };
+    let email = escape_html(request.email.as_ref());
+    let token = escape_html(&parameters.token);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Your personal data</title>
+    </head>
+    <body>
+        <p>{email}</p>
+        <p>{description}</p>
+        <form action="/subscriptions/personal-data/confirm" method="post">
+            <input hidden type="text" name="token" value="{token}">
+            <button type="submit">{button}</button>
+        </form>
+    </body>
+</html>
+            "#
+        )))
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::{data_request_confirmation_form, data_request_form};
+pub use post::{complete_data_request, request_personal_data};
@@ -0,0 +1,163 @@
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use tracing::Instrument;
+
+use crate::domain::SubscriberEmail;
+use crate::email_client::{EmailClient, SendEmailError};
+use crate::personal_data::{
+    DataRequestKind, collect_personal_data, consume_data_request_token, create_data_request_token,
+    erase_personal_data,
+};
+use crate::routes::personal_data_response;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+    kind: String,
+}
+
+#[tracing::instrument(
+    name = "Request personal data",
+    skip(form, pool, email_client, base_url),
+    fields(kind = %form.kind)
+)]
+pub async fn request_personal_data(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let FormData { email, kind } = form.into_inner();
+    let Ok(email) = SubscriberEmail::parse(email.trim().to_string()) else {
+        FlashMessage::error("Please enter a valid email address.").send();
+        return see_other("/subscriptions/personal-data");
+    };
+    let Ok(kind) = DataRequestKind::try_from(kind) else {
+        FlashMessage::error("Please choose whether to download or delete your data.").send();
+        return see_other("/subscriptions/personal-data");
+    };
+
+    // As for password resets, neither the response nor its timing reveals whether
+    // the address is subscribed.
+    tokio::spawn(
+        async move {
+            if let Err(e) =
+                send_data_request_link(&email, kind, &pool, &email_client, &base_url.0).await
+            {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to send a data request link"
+                );
+            }
+        }
+        .in_current_span(),
+    );
+
+    FlashMessage::info(
+        "If this address is subscribed, a link to complete your request has been sent to it.",
+    )
+    .send();
+    see_other("/subscriptions/personal-data")
+}
+
+async fn send_data_request_link(
+    email: &SubscriberEmail,
+    kind: DataRequestKind,
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+) -> Result<(), anyhow::Error> {
+    let Some(token) = create_data_request_token(email, kind, pool).await? else {
+        return Ok(());
+    };
+
+    send_data_request_email(email_client, email, kind, base_url, &token)
+        .await
+        .context("Failed to send a data request email")
+}
+
+#[tracing::instrument(
+    name = "Send a data request email"
+    skip(email_client, email, base_url, token)
+)]
+async fn send_data_request_email(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    kind: DataRequestKind,
+    base_url: &str,
+    token: &str,
+) -> Result<(), SendEmailError> {
+    let link = format!(
+        "{}/subscriptions/personal-data/confirm?token={}",
+        base_url, token
+    );
+    let action = match kind {
+        DataRequestKind::Access => "get a copy of the data we store about you",
+        DataRequestKind::Erasure => "delete the data we store about you",
+    };
+
+    let html_body = format!(
+        "Someone asked to {action}.<br />\
+                Click <a href=\"{link}\">here</a> within 24 hours to confirm. \
+                You can ignore this email if you did not ask for it."
+    );
+    let plain_body = format!(
+        "Someone asked to {action}.\n\
+                Visit {link} within 24 hours to confirm. \
+                You can ignore this email if you did not ask for it."
+    );
+
+    email_client
+        .send_email(email, "Your personal data", &html_body, &plain_body)
+        .await
+}
+
+#[derive(serde::Deserialize)]
+pub struct ConfirmationFormData {
+    token: String,
+}
+
+#[tracing::instrument(name = "Complete a data request", skip(form, pool))]
+pub async fn complete_data_request(
+    form: web::Form<ConfirmationFormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(request) = consume_data_request_token(&form.token, &pool)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    match request.kind {
+        DataRequestKind::Access => {
+            let data = collect_personal_data(request.email.as_ref(), &pool)
+                .await
+                .map_err(e500)?;
+            personal_data_response(&data)
+        }
+        DataRequestKind::Erasure => {
+            erase_personal_data(request.email.as_ref(), &pool)
+                .await
+                .map_err(e500)?;
+            Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+                r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Your personal data</title>
+    </head>
+    <body>
+        <p>Your data has been deleted.</p>
+    </body>
+</html>
+            "#,
+            ))
+        }
+    }
+}
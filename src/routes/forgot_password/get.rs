@@ -0,0 +1,29 @@
+use actix_web::{HttpResponse, http::header::ContentType};
+
+pub async fn forgot_password_form() -> HttpResponse {
+    HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Forgot password</title>
+    </head>
+    <body>
+        <p>Enter your username, we will email you a link to choose a new password.</p>
+        <form action="/forgot-password" method="post">
+            <label>Username
+                <input
+                    type="text"
+                    placeholder="Enter Username"
+                    name="username"
+                >
+            </label>
+
+            <button type="submit">Send reset link</button>
+        </form>
+        <p><a href="/login">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+    )
+}
@@ -0,0 +1,120 @@
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use tracing::Instrument;
+
+use crate::authentication::{
+    PasswordResetRequest, create_password_reset_token, password_reset_lockout,
+    record_password_reset_request,
+};
+use crate::configuration::LoginThrottleSettings;
+use crate::email_client::{EmailClient, SendEmailError};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    username: String,
+}
+
+#[tracing::instrument(
+    name = "Request a password reset",
+    skip(form, pool, email_client, base_url, request, throttle),
+    fields(username = %form.username)
+)]
+pub async fn forgot_password(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    request: HttpRequest,
+    throttle: web::Data<LoginThrottleSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Forwarding headers can be forged, only the address of the peer is trusted.
+    let client_ip = request.peer_addr().map(|addr| addr.ip());
+
+    // Requests are counted whether the username exists or not, so a lockout
+    // does not reveal anything about the account.
+    if password_reset_lockout(&form.username, client_ip, &throttle, &pool)
+        .await
+        .map_err(e500)?
+        .is_some()
+    {
+        FlashMessage::error("Too many password reset requests. Please try again later.").send();
+        return Ok(see_other("/login"));
+    }
+    record_password_reset_request(&form.username, client_ip, &throttle, &pool)
+        .await
+        .map_err(e500)?;
+
+    // The lookup and the email are handled in the background, so that neither the
+    // response nor its timing reveals whether the username exists.
+    tokio::spawn(
+        async move {
+            if let Err(e) =
+                send_password_reset_link(&form.username, &pool, &email_client, &base_url.0).await
+            {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to send a password reset link"
+                );
+            }
+        }
+        .in_current_span(),
+    );
+
+    FlashMessage::info(
+        "If this account has an email address, a link to reset its password has been sent to it.",
+    )
+    .send();
+    Ok(see_other("/login"))
+}
+
+async fn send_password_reset_link(
+    username: &str,
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+) -> Result<(), anyhow::Error> {
+    let Some(request) = create_password_reset_token(username, pool).await? else {
+        return Ok(());
+    };
+
+    send_password_reset_email(email_client, &request, base_url)
+        .await
+        .context("Failed to send a password reset email")
+}
+
+#[tracing::instrument(
+    name = "Send a password reset email"
+    skip(email_client, request, base_url)
+)]
+async fn send_password_reset_email(
+    email_client: &EmailClient,
+    request: &PasswordResetRequest,
+    base_url: &str,
+) -> Result<(), SendEmailError> {
+    let reset_link = format!("{}/reset-password?reset_token={}", base_url, request.token);
+
+    let html_body = format!(
+        "Someone asked to reset the password of your newsletter admin account.<br />\
+                Click <a href=\"{reset_link}\">here</a> within 30 minutes to choose a new one. \
+                You can ignore this email if you did not ask for it."
+    );
+    let plain_body = format!(
+        "Someone asked to reset the password of your newsletter admin account.\n\
+                Visit {reset_link} within 30 minutes to choose a new one. \
+                You can ignore this email if you did not ask for it."
+    );
+
+    email_client
+        .send_email(
+            &request.email,
+            "Reset your password",
+            &html_body,
+            &plain_body,
+        )
+        .await
+}
@@ -1,5 +1,11 @@
 use actix_web::{HttpRequest, HttpResponse, Responder};
 
+#[utoipa::path(
+    get,
+    path = "/health_check",
+    tag = "health",
+    responses((status = 200, description = "The application is up"))
+)]
 pub async fn health_check(_req: HttpRequest) -> impl Responder {
     HttpResponse::Ok().finish()
 }
@@ -0,0 +1,75 @@
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+use crate::authentication::get_pending_invitation;
+use crate::utils::{e500, escape_html, flash_messages_html};
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    invitation_token: String,
+}
+
+#[tracing::instrument(name = "Get invitation form", skip(flash_messages, parameters, pool))]
+pub async fn invitation_form(
+    flash_messages: IncomingFlashMessages,
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = &parameters.invitation_token;
+    let Some(invitation) = get_pending_invitation(token, &pool).await.map_err(e500)? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let msg_html = flash_messages_html(&flash_messages);
+
+    let email = escape_html(&invitation.email);
+    let role = invitation.role;
+    let token = escape_html(token);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Accept invitation</title>
+    </head>
+    <body>
+        <p>{email} has been invited with the {role} role</p>
+        {msg_html}
+        <form action="/invitations/accept" method="post">
+            <input hidden type="text" name="invitation_token" value="{token}">
+
+            <label>Username
+                <input
+                    type="text"
+                    placeholder="Enter Username"
+                    name="username"
+                >
+            </label>
+
+            <label>Password
+                <input
+                    type="password"
+                    placeholder="Enter Password"
+                    name="password"
+                >
+            </label>
+
+            <label>Confirm password
+                <input
+                    type="password"
+                    placeholder="Confirm Password"
+                    name="password_check"
+                >
+            </label>
+
+            <button type="submit">Create account</button>
+        </form>
+    </body>
+</html>
+            "#
+        )))
+}
@@ -0,0 +1,75 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::authentication::{CreateUserError, accept_invitation, get_pending_invitation};
+use crate::domain::AdminPassword;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    invitation_token: String,
+    username: String,
+    password: Secret<String>,
+    password_check: Secret<String>,
+}
+
+#[tracing::instrument(
+    name = "Accept an invitation",
+    skip(form, pool),
+    fields(username = %form.username)
+)]
+pub async fn join(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = &form.invitation_token;
+    if get_pending_invitation(token, &pool)
+        .await
+        .map_err(e500)?
+        .is_none()
+    {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    // The token was found in the database, so it is a plain alphanumeric string.
+    let form_url = format!("/invitations/accept?invitation_token={}", token);
+
+    let username = form.username.trim();
+    if username.is_empty() {
+        FlashMessage::error("The username cannot be empty.").send();
+        return Ok(see_other(&form_url));
+    }
+
+    if form.password.expose_secret() != form.password_check.expose_secret() {
+        FlashMessage::error("You entered two different passwords - the field values must match.")
+            .send();
+        return Ok(see_other(&form_url));
+    }
+
+    let password = match AdminPassword::new(form.password.expose_secret().clone()) {
+        Ok(password) => password,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(&form_url));
+        }
+    };
+
+    match accept_invitation(token, username, password, &pool).await {
+        Ok(Some(_)) => {
+            FlashMessage::info("Your account has been created. You can now log in.").send();
+            Ok(see_other("/login"))
+        }
+        // The link was used in the meantime, or expired.
+        Ok(None) => Ok(HttpResponse::NotFound().finish()),
+        Err(CreateUserError::UsernameTaken(_)) => {
+            FlashMessage::error("This username is already taken.").send();
+            Ok(see_other(&form_url))
+        }
+        Err(CreateUserError::EmailTaken(_)) => {
+            FlashMessage::error("A user with this email address already exists.").send();
+            Ok(see_other(&form_url))
+        }
+        Err(e) => Err(e500(e)),
+    }
+}
@@ -1,14 +1,10 @@
 use actix_web::{HttpResponse, http::header::ContentType};
 use actix_web_flash_messages::IncomingFlashMessages;
 
-use std::fmt::Write;
+use crate::utils::flash_messages_html;
 
 pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
-    let mut error = String::new();
-
-    for m in flash_messages.iter() {
-        writeln!(error, "<p><i>{}</i></p>", m.content()).unwrap();
-    }
+    let error = flash_messages_html(&flash_messages);
 
     HttpResponse::Ok()
         .content_type(ContentType::html())
@@ -40,6 +36,7 @@ pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
 
             <button type="submit">Login</button>
         </form>
+        <p><a href="/forgot-password">Forgot your password?</a></p>
     </body>
 </html>
             "#
@@ -1,11 +1,19 @@
+use std::time::Duration;
+
 use actix_web::error::InternalError;
 use actix_web::http::header::LOCATION;
-use actix_web::{HttpResponse, web};
+use actix_web::{HttpRequest, HttpResponse, web};
 use actix_web_flash_messages::FlashMessage;
 use secrecy::Secret;
 use sqlx::PgPool;
 
-use crate::authentication::{AuthError, Credentials, validate_credentials};
+use crate::audit::{AuditAction, record_audit_event};
+use crate::authentication::{
+    AuthError, Credentials, clear_login_failures, login_lockout, record_login_failure,
+    two_factor_enabled, validate_credentials,
+};
+use crate::configuration::LoginThrottleSettings;
+use crate::consent::RequestMetadata;
 use crate::routes::error_chain_fmt;
 use crate::session_state::TypedSession;
 
@@ -16,37 +24,86 @@ pub struct FormData {
 }
 
 #[tracing::instrument(
-    skip(form, pool, session),
+    skip(form, pool, session, request, throttle),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn login(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     session: TypedSession,
+    request: HttpRequest,
+    throttle: web::Data<LoginThrottleSettings>,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
+    let username = form.0.username;
+    // Forwarding headers can be forged, only the address of the peer is trusted.
+    let client_ip = request.peer_addr().map(|addr| addr.ip());
+
+    tracing::Span::current().record("username", tracing::field::display(&username));
+
+    // Failures are counted whether the username exists or not, so a lockout
+    // does not reveal anything about the account.
+    if let Some(lockout) = login_lockout(&username, client_ip, &throttle, &pool)
+        .await
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?
+    {
+        metrics::counter!("locked_login_attempts_total").increment(1);
+        return Err(login_redirect(LoginError::TooManyAttempts(lockout)));
+    }
+
     let credentials = Credentials {
-        username: form.0.username,
+        username: username.clone(),
         password: form.0.password,
     };
 
-    tracing::Span::current().record("username", &tracing::field::display(&credentials.username));
-
     match validate_credentials(credentials, &pool).await {
         Ok(user_id) => {
-            tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
+            tracing::Span::current().record("user_id", tracing::field::display(&user_id));
 
             session.renew(); // Rotate session token, preventing using anonymous session token
 
+            let needs_second_factor = two_factor_enabled(user_id, &pool)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+
+            if needs_second_factor {
+                session
+                    .insert_pending_user_id(user_id)
+                    .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+                return Ok(HttpResponse::SeeOther()
+                    .insert_header((LOCATION, "/login/two-factor"))
+                    .finish());
+            }
+
+            // Failures are only forgotten once the second factor is verified.
+            clear_login_failures(&username, &pool)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+
             session
                 .insert_user_id(user_id)
                 .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+            record_audit_event(
+                pool.get_ref(),
+                user_id,
+                AuditAction::LogIn,
+                None,
+                &RequestMetadata::from_request(&request),
+            )
+            .await
+            .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
             Ok(HttpResponse::SeeOther()
                 .insert_header((LOCATION, "/admin/dashboard"))
                 .finish())
         }
         Err(e) => {
             let e = match e {
-                AuthError::InvalidCredentials(_) => LoginError::AuthError(e.into()),
+                AuthError::InvalidCredentials(_) => {
+                    match record_login_failure(&username, client_ip, &throttle, &pool).await {
+                        Ok(Some(lockout)) => LoginError::TooManyAttempts(lockout),
+                        Ok(None) => LoginError::AuthError(e.into()),
+                        Err(e) => LoginError::UnexpectedError(e),
+                    }
+                }
                 AuthError::UnexpectedError(_) => LoginError::UnexpectedError(e.into()),
             };
 
@@ -69,6 +126,11 @@ fn login_redirect(e: LoginError) -> InternalError<LoginError> {
 pub enum LoginError {
     #[error("Authentication failed")]
     AuthError(#[source] anyhow::Error),
+    #[error(
+        "Too many failed login attempts. Please try again in {}.",
+        describe_wait(*.0)
+    )]
+    TooManyAttempts(Duration),
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -78,3 +140,18 @@ impl std::fmt::Debug for LoginError {
         error_chain_fmt(self, f)
     }
 }
+
+fn describe_wait(wait: Duration) -> String {
+    let seconds = wait.as_secs().max(1);
+    let (amount, unit) = if seconds < 60 {
+        (seconds, "second")
+    } else {
+        (seconds.div_ceil(60), "minute")
+    };
+
+    if amount == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", amount, unit)
+    }
+}
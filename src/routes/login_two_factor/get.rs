@@ -0,0 +1,44 @@
+use actix_web::{HttpResponse, http::header::ContentType};
+use actix_web_flash_messages::IncomingFlashMessages;
+
+use crate::session_state::TypedSession;
+use crate::utils::{e500, flash_messages_html, see_other};
+
+pub async fn second_factor_form(
+    flash_messages: IncomingFlashMessages,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_pending_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let msg_html = flash_messages_html(&flash_messages);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Two-factor authentication</title>
+    </head>
+    <body>
+        {msg_html}
+        <form action="/login/two-factor" method="post">
+            <label>Authentication code
+                <input
+                    type="text"
+                    placeholder="Enter the code from your app, or a recovery code"
+                    name="code"
+                    autocomplete="one-time-code"
+                >
+            </label>
+
+            <button type="submit">Verify</button>
+        </form>
+    </body>
+</html>
+            "#
+        )))
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::second_factor_form;
+pub use post::submit_second_factor;
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+use crate::audit::{AuditAction, record_audit_event};
+use crate::authentication::{
+    clear_login_failures, login_lockout, record_login_failure, verify_second_factor,
+};
+use crate::configuration::LoginThrottleSettings;
+use crate::consent::RequestMetadata;
+use crate::routes::get_username;
+use crate::routes::login::LoginError;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+/// Invalid codes accepted before the user has to enter their password again.
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    code: String,
+}
+
+#[tracing::instrument(
+    name = "Verify the second factor of a login",
+    skip(form, pool, session, request, throttle),
+    fields(user_id = tracing::field::Empty)
+)]
+pub async fn submit_second_factor(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    throttle: web::Data<LoginThrottleSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(user_id) = session.get_pending_user_id().map_err(e500)? else {
+        return Ok(see_other("/login"));
+    };
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+
+    // Invalid codes count towards the same lockout as invalid passwords.
+    let username = get_username(user_id, &pool).await.map_err(e500)?;
+    let client_ip = request.peer_addr().map(|addr| addr.ip());
+    if let Some(lockout) = login_lockout(&username, client_ip, &throttle, &pool)
+        .await
+        .map_err(e500)?
+    {
+        metrics::counter!("locked_login_attempts_total").increment(1);
+        return Ok(locked_out(session, lockout));
+    }
+
+    if verify_second_factor(user_id, &form.code, &pool)
+        .await
+        .map_err(e500)?
+    {
+        clear_login_failures(&username, &pool).await.map_err(e500)?;
+        session.complete_login(user_id).map_err(e500)?;
+        record_audit_event(
+            pool.get_ref(),
+            user_id,
+            AuditAction::LogIn,
+            None,
+            &RequestMetadata::from_request(&request),
+        )
+        .await
+        .map_err(e500)?;
+        return Ok(see_other("/admin/dashboard"));
+    }
+
+    if let Some(lockout) = record_login_failure(&username, client_ip, &throttle, &pool)
+        .await
+        .map_err(e500)?
+    {
+        return Ok(locked_out(session, lockout));
+    }
+    if session.record_failed_second_factor().map_err(e500)? >= MAX_FAILED_ATTEMPTS {
+        session.log_out();
+        FlashMessage::error("Too many invalid codes, please log in again.").send();
+        return Ok(see_other("/login"));
+    }
+
+    FlashMessage::error("The authentication code is invalid.").send();
+    Ok(see_other("/login/two-factor"))
+}
+
+fn locked_out(session: TypedSession, lockout: Duration) -> HttpResponse {
+    session.log_out();
+    FlashMessage::error(LoginError::TooManyAttempts(lockout).to_string()).send();
+    see_other("/login")
+}
@@ -1,13 +1,29 @@
 mod admin;
+mod api;
+mod data_requests;
+mod forgot_password;
 mod health_check;
 mod home;
+mod invitations;
 mod login;
+mod login_two_factor;
+mod reset_password;
+mod setup;
 mod subscriptions;
 mod subscriptions_confirm;
+mod tracking;
 
 pub use admin::*;
+pub use api::*;
+pub use data_requests::*;
+pub use forgot_password::*;
 pub use health_check::*;
 pub use home::*;
+pub use invitations::*;
 pub use login::*;
+pub use login_two_factor::*;
+pub use reset_password::*;
+pub use setup::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use tracking::*;
@@ -0,0 +1,70 @@
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+use crate::authentication::password_reset_token_is_valid;
+use crate::utils::{e500, escape_html, flash_messages_html};
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    reset_token: String,
+}
+
+#[tracing::instrument(
+    name = "Get reset password form",
+    skip(flash_messages, parameters, pool)
+)]
+pub async fn reset_password_form(
+    flash_messages: IncomingFlashMessages,
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = &parameters.reset_token;
+    if !password_reset_token_is_valid(token, &pool)
+        .await
+        .map_err(e500)?
+    {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let msg_html = flash_messages_html(&flash_messages);
+
+    let token = escape_html(token);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Reset password</title>
+    </head>
+    <body>
+        {msg_html}
+        <form action="/reset-password" method="post">
+            <input hidden type="text" name="reset_token" value="{token}">
+
+            <label>New password
+                <input
+                    type="password"
+                    placeholder="Enter new password"
+                    name="new_password"
+                >
+            </label>
+
+            <label>Confirm new password
+                <input
+                    type="password"
+                    placeholder="Type the new password again"
+                    name="new_password_check"
+                >
+            </label>
+
+            <button type="submit">Reset password</button>
+        </form>
+    </body>
+</html>
+            "#
+        )))
+}
@@ -0,0 +1,76 @@
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::audit::{AuditAction, record_audit_event};
+use crate::authentication::{
+    change_password, consume_password_reset_token, password_reset_token_is_valid,
+};
+use crate::consent::RequestMetadata;
+use crate::domain::AdminPassword;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    reset_token: String,
+    new_password: Secret<String>,
+    new_password_check: Secret<String>,
+}
+
+#[tracing::instrument(name = "Reset a forgotten password", skip(form, pool, request))]
+pub async fn reset_password(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = &form.reset_token;
+    if !password_reset_token_is_valid(token, &pool)
+        .await
+        .map_err(e500)?
+    {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    // The token was found in the database, so it is a plain alphanumeric string.
+    let form_url = format!("/reset-password?reset_token={}", token);
+
+    if form.new_password.expose_secret() != form.new_password_check.expose_secret() {
+        FlashMessage::error(
+            "You entered two different new passwords - the field values must match.",
+        )
+        .send();
+        return Ok(see_other(&form_url));
+    }
+
+    let password = match AdminPassword::new(form.new_password.expose_secret().clone()) {
+        Ok(password) => password,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(&form_url));
+        }
+    };
+
+    // The link was used in the meantime, or expired.
+    let Some(user_id) = consume_password_reset_token(token, &pool)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    change_password(user_id, password, &pool)
+        .await
+        .map_err(e500)?;
+    record_audit_event(
+        pool.get_ref(),
+        user_id,
+        AuditAction::ResetPassword,
+        None,
+        &RequestMetadata::from_request(&request),
+    )
+    .await
+    .map_err(e500)?;
+
+    FlashMessage::info("Your password has been reset. You can now log in.").send();
+    Ok(see_other("/login"))
+}
@@ -0,0 +1,89 @@
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+use crate::authentication::users_exist;
+use crate::startup::BootstrapToken;
+use crate::utils::{e500, flash_messages_html};
+
+#[tracing::instrument(
+    name = "Get first-run setup form"
+    skip(flash_messages, pool, bootstrap_token)
+)]
+pub async fn setup_form(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    bootstrap_token: web::Data<BootstrapToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if users_exist(pool.get_ref()).await.map_err(e500)? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let msg_html = flash_messages_html(&flash_messages);
+
+    let token_input = if bootstrap_token.0.is_some() {
+        r#"<label>Bootstrap token
+                <input
+                    type="password"
+                    placeholder="Enter the bootstrap token from the configuration"
+                    name="bootstrap_token"
+                >
+            </label>"#
+    } else {
+        ""
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Setup</title>
+    </head>
+    <body>
+        <p>Create the first admin account</p>
+        {msg_html}
+        <form action="/setup" method="post">
+            {token_input}
+
+            <label>Username
+                <input
+                    type="text"
+                    placeholder="Enter Username"
+                    name="username"
+                >
+            </label>
+
+            <label>Email (optional, used to reset your password)
+                <input
+                    type="email"
+                    placeholder="Enter Email"
+                    name="email"
+                >
+            </label>
+
+            <label>Password
+                <input
+                    type="password"
+                    placeholder="Enter Password"
+                    name="password"
+                >
+            </label>
+
+            <label>Confirm password
+                <input
+                    type="password"
+                    placeholder="Confirm Password"
+                    name="password_check"
+                >
+            </label>
+
+            <button type="submit">Create admin</button>
+        </form>
+    </body>
+</html>
+            "#
+        )))
+}
@@ -0,0 +1,87 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::authentication::{create_first_user, users_exist};
+use crate::domain::{AdminPassword, SubscriberEmail};
+use crate::startup::BootstrapToken;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    username: String,
+    email: Option<String>,
+    password: Secret<String>,
+    password_check: Secret<String>,
+    bootstrap_token: Option<Secret<String>>,
+}
+
+#[tracing::instrument(
+    name = "Create the first admin user",
+    skip(form, pool, bootstrap_token),
+    fields(username = %form.username)
+)]
+pub async fn setup(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    bootstrap_token: web::Data<BootstrapToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if users_exist(pool.get_ref()).await.map_err(e500)? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    if let Some(expected_token) = &bootstrap_token.0 {
+        let is_valid = form
+            .bootstrap_token
+            .as_ref()
+            .is_some_and(|token| token.expose_secret() == expected_token.expose_secret());
+
+        if !is_valid {
+            FlashMessage::error("The bootstrap token is incorrect.").send();
+            return Ok(see_other("/setup"));
+        }
+    }
+
+    let username = form.username.trim();
+    if username.is_empty() {
+        FlashMessage::error("The username cannot be empty.").send();
+        return Ok(see_other("/setup"));
+    }
+
+    let email = match form.email.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(email) => match SubscriberEmail::parse(email.to_string()) {
+            Ok(email) => Some(email),
+            // The input is not echoed back, flash messages are rendered as HTML.
+            Err(_) => {
+                FlashMessage::error("Please enter a valid email address.").send();
+                return Ok(see_other("/setup"));
+            }
+        },
+    };
+
+    if form.password.expose_secret() != form.password_check.expose_secret() {
+        FlashMessage::error("You entered two different passwords - the field values must match.")
+            .send();
+        return Ok(see_other("/setup"));
+    }
+
+    let password = match AdminPassword::new(form.password.expose_secret().clone()) {
+        Ok(password) => password,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/setup"));
+        }
+    };
+
+    match create_first_user(username, email.as_ref(), password, &pool).await {
+        Ok(Some(_)) => {
+            FlashMessage::info("Your admin account has been created. You can now log in.").send();
+            Ok(see_other("/login"))
+        }
+        // Someone else completed the setup in the meantime.
+        Ok(None) => Ok(HttpResponse::NotFound().finish()),
+        Err(e) => Err(e500(e)),
+    }
+}
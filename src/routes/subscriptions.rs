@@ -1,22 +1,31 @@
+use std::collections::BTreeMap;
 use std::fmt::Display;
 
-use actix_web::{HttpResponse, ResponseError, web};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
 use anyhow::Context;
 use chrono::Utc;
 use rand::distributions::Alphanumeric;
 use rand::{Rng, thread_rng};
 use reqwest::StatusCode;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
+use utoipa::IntoResponses;
+use utoipa::openapi::{RefOr, Response, ResponseBuilder};
 use uuid::Uuid;
 
-use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
-use crate::email_client::EmailClient;
+use crate::consent::{RequestMetadata, SignupSource, record_signup};
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionStatus};
+use crate::email_client::{EmailClient, SendEmailError};
 use crate::startup::ApplicationBaseUrl;
+use crate::subscribers::update_subscriber;
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+#[schema(as = SubscriptionForm)]
 pub struct FormData {
     email: String,
     name: String,
+    /// Asks not to have opens and clicks of issues tracked.
+    #[serde(default)]
+    do_not_track: bool,
 }
 
 impl TryFrom<FormData> for NewSubscriber {
@@ -52,9 +61,40 @@ impl ResponseError for SubscribeError {
     }
 }
 
+impl IntoResponses for SubscribeError {
+    fn responses() -> BTreeMap<String, RefOr<Response>> {
+        BTreeMap::from([
+            (
+                StatusCode::BAD_REQUEST.as_str().to_string(),
+                ResponseBuilder::new()
+                    .description("The email or name is invalid")
+                    .build()
+                    .into(),
+            ),
+            (
+                StatusCode::INTERNAL_SERVER_ERROR.as_str().to_string(),
+                ResponseBuilder::new()
+                    .description("The subscriber could not be stored or emailed")
+                    .build()
+                    .into(),
+            ),
+        ])
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/subscriptions",
+    tag = "subscriptions",
+    request_body(content = FormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The subscriber was sent a confirmation email"),
+        SubscribeError
+    )
+)]
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool, email_client, base_url),
+    skip(form, request, pool, email_client, base_url),
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name
@@ -62,10 +102,12 @@ impl ResponseError for SubscribeError {
 )]
 pub async fn subscribe(
     form: web::Form<FormData>,
+    request: HttpRequest,
     pool: web::Data<PgPool>,
     email_client: web::Data<EmailClient>,
     base_url: web::Data<ApplicationBaseUrl>,
 ) -> Result<HttpResponse, SubscribeError> {
+    let do_not_track = form.do_not_track;
     let new_subscriber = form.0.try_into().map_err(SubscribeError::ValidationError)?;
 
     let mut transaction = pool
@@ -73,9 +115,24 @@ pub async fn subscribe(
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
 
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
-        .await
-        .context("Failed to insert new subscriber in the database")?;
+    let subscriber_id = insert_subscriber(
+        &mut transaction,
+        &new_subscriber,
+        SubscriptionStatus::PendingConfirmation,
+    )
+    .await
+    .context("Failed to insert new subscriber in the database")?;
+    if do_not_track {
+        update_subscriber(&mut *transaction, subscriber_id, None, None, Some(true)).await?;
+    }
+
+    record_signup(
+        &mut *transaction,
+        subscriber_id,
+        SignupSource::Form,
+        &RequestMetadata::from_request(&request),
+    )
+    .await?;
 
     let subscription_token = generate_subscription_token();
 
@@ -107,17 +164,19 @@ pub async fn subscribe(
 pub async fn insert_subscriber(
     transaction: &mut Transaction<'static, Postgres>,
     form: &NewSubscriber,
+    status: SubscriptionStatus,
 ) -> Result<Uuid, sqlx::Error> {
     let subscriber_id = Uuid::new_v4();
 
     let query = sqlx::query!(
         r#"INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        VALUES ($1, $2, $3, $4, $5)
         "#,
         subscriber_id,
         form.email.as_ref(),
         form.name.as_ref(),
         Utc::now(),
+        status.as_str(),
     );
 
     transaction.execute(query).await?;
@@ -141,10 +200,7 @@ pub async fn store_token(
         subscriber_id
     );
 
-    transaction
-        .execute(query)
-        .await
-        .map_err(|e| StoreTokenError(e))?;
+    transaction.execute(query).await.map_err(StoreTokenError)?;
 
     Ok(())
 }
@@ -158,7 +214,23 @@ pub async fn send_confirmation_email(
     new_subscriber: NewSubscriber,
     base_url: &str,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), SendEmailError> {
+    let (html_body, plain_body) = confirmation_email_bodies(base_url, subscription_token);
+
+    email_client
+        .send_email(
+            &new_subscriber.email,
+            CONFIRMATION_EMAIL_SUBJECT,
+            &html_body,
+            &plain_body,
+        )
+        .await
+}
+
+pub const CONFIRMATION_EMAIL_SUBJECT: &str = "Welcome!";
+
+/// HTML and plain text bodies of the email asking a new subscriber to confirm.
+pub fn confirmation_email_bodies(base_url: &str, subscription_token: &str) -> (String, String) {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token
@@ -174,12 +246,10 @@ pub async fn send_confirmation_email(
         confirmation_link
     );
 
-    email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
-        .await
+    (html_body, plain_body)
 }
 
-fn generate_subscription_token() -> String {
+pub fn generate_subscription_token() -> String {
     let mut rng = thread_rng();
     std::iter::repeat_with(|| rng.sample(Alphanumeric))
         .map(char::from)
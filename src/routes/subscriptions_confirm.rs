@@ -1,18 +1,38 @@
-use actix_web::{HttpResponse, web};
+use actix_web::{HttpRequest, HttpResponse, web};
 use sqlx::postgres::PgPool;
+use sqlx::{Postgres, Transaction};
 use uuid::Uuid;
 
-#[derive(serde::Deserialize, Debug)]
+use crate::consent::{RequestMetadata, record_confirmation};
+
+#[derive(serde::Deserialize, Debug, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct Parameters {
+    /// Sent to the subscriber in their confirmation email.
     subscription_token: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/subscriptions/confirm",
+    tag = "subscriptions",
+    params(Parameters),
+    responses(
+        (status = 200, description = "The subscription is confirmed"),
+        (status = 401, description = "The subscription token is unknown"),
+        (status = 500, description = "The subscription could not be confirmed")
+    )
+)]
 #[tracing::instrument(
     name = "Confirm a pending subscriber"
-    skip(pool)
+    skip(request, pool)
     fields(subscription_token = %parameters.subscription_token)
 )]
-pub async fn confirm(parameters: web::Query<Parameters>, pool: web::Data<PgPool>) -> HttpResponse {
+pub async fn confirm(
+    parameters: web::Query<Parameters>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
     let option_id =
         match get_subscriber_id_from_token(&pool, &parameters.0.subscription_token).await {
             Ok(option) => option,
@@ -24,30 +44,59 @@ pub async fn confirm(parameters: web::Query<Parameters>, pool: web::Data<PgPool>
         None => return HttpResponse::Unauthorized().finish(),
     };
 
-    if confirm_subscriber(&pool, subscriber_id).await.is_err() {
+    let Ok(mut transaction) = pool.begin().await else {
+        return HttpResponse::InternalServerError().finish();
+    };
+
+    let was_pending = match confirm_subscriber(&mut transaction, subscriber_id).await {
+        Ok(was_pending) => was_pending,
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+
+    // Following the link again does not count as another confirmation.
+    if was_pending {
+        let metadata = RequestMetadata::from_request(&request);
+        if let Err(e) = record_confirmation(&mut *transaction, subscriber_id, &metadata).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to record a confirmation"
+            );
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    if transaction.commit().await.is_err() {
         return HttpResponse::InternalServerError().finish();
     }
 
     HttpResponse::Ok().finish()
 }
 
+/// Returns whether the subscriber was waiting for a confirmation.
 #[tracing::instrument(
     name = "Mark susbscriber as confirmed"
-    skip(db_pool)
+    skip(transaction)
 )]
-async fn confirm_subscriber(db_pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id=$1"#,
+async fn confirm_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE subscriptions SET status = 'confirmed'
+        WHERE id = $1 AND status = 'pending_confirmation'
+        "#,
         subscriber_id
     )
-    .execute(db_pool)
+    .execute(&mut **transaction)
     .await
     .map_err(|e| {
         tracing::error!("Failed to execute query {:?}", e);
         e
     })?;
 
-    Ok(())
+    Ok(result.rows_affected() == 1)
 }
 
 #[tracing::instrument(
@@ -0,0 +1,67 @@
+use actix_web::http::header::{CacheControl, CacheDirective, LOCATION};
+use actix_web::{HttpResponse, web};
+use sqlx::PgPool;
+
+use crate::newsletter_issues::get_newsletter_issue;
+use crate::tracking::{OPEN_PIXEL, Tracker, record_click, record_open};
+use crate::utils::e500;
+
+/// Records a click on a link of an issue, then redirects to the link as it was sent.
+#[tracing::instrument(name = "Follow a tracked link", skip(token, pool, tracker))]
+pub async fn follow_link(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+    tracker: web::Data<Tracker>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(link) = tracker.verify_click_token(&token) else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if get_newsletter_issue(link.newsletter_issue_id, pool.get_ref())
+        .await
+        .map_err(e500)?
+        .is_none()
+    {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    // Readers are sent on their way even if the click could not be recorded.
+    if let Err(e) = record_click(&link, &pool).await {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to record a click"
+        );
+    }
+
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, link.url))
+        .finish())
+}
+
+/// Records an open of an issue, unless open tracking has been disabled since it was
+/// sent. The image is never cached, so every open reaches us.
+#[tracing::instrument(name = "Load an open tracking image", skip(token, pool, tracker))]
+pub async fn open_pixel(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+    tracker: web::Data<Tracker>,
+) -> HttpResponse {
+    let Some(open) = tracker.verify_open_token(&token) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    if tracker.tracks_opens()
+        && let Err(e) = record_open(&open, &pool).await
+    {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to record an open"
+        );
+    }
+
+    HttpResponse::Ok()
+        .content_type("image/gif")
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(OPEN_PIXEL)
+}
@@ -2,12 +2,16 @@ use std::future::{Ready, ready};
 
 use actix_session::{Session, SessionExt, SessionGetError, SessionInsertError};
 use actix_web::FromRequest;
+use secrecy::{ExposeSecret, Secret};
 use uuid::Uuid;
 
 pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    const PENDING_USER_ID_KEY: &'static str = "pending_user_id";
+    const FAILED_SECOND_FACTORS_KEY: &'static str = "failed_second_factors";
+    const TOTP_ENROLLMENT_SECRET_KEY: &'static str = "totp_enrollment_secret";
 
     pub fn renew(&self) {
         self.0.renew();
@@ -21,6 +25,53 @@ impl TypedSession {
         self.0.get(Self::USER_ID_KEY)
     }
 
+    /// Remembers a user who entered a valid password but still has to provide
+    /// their second factor. They are not logged in until then.
+    pub fn insert_pending_user_id(&self, user_id: Uuid) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::PENDING_USER_ID_KEY, user_id)
+    }
+
+    pub fn get_pending_user_id(&self) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get(Self::PENDING_USER_ID_KEY)
+    }
+
+    /// Returns the number of invalid second factors entered so far.
+    pub fn record_failed_second_factor(&self) -> Result<u32, anyhow::Error> {
+        let failures = self
+            .0
+            .get::<u32>(Self::FAILED_SECOND_FACTORS_KEY)?
+            .unwrap_or(0)
+            + 1;
+        self.0.insert(Self::FAILED_SECOND_FACTORS_KEY, failures)?;
+
+        Ok(failures)
+    }
+
+    /// Completes a login started with [`Self::insert_pending_user_id`].
+    pub fn complete_login(&self, user_id: Uuid) -> Result<(), SessionInsertError> {
+        self.0.remove(Self::PENDING_USER_ID_KEY);
+        self.0.remove(Self::FAILED_SECOND_FACTORS_KEY);
+        self.renew();
+        self.insert_user_id(user_id)
+    }
+
+    pub fn insert_totp_enrollment_secret(
+        &self,
+        secret: &Secret<String>,
+    ) -> Result<(), SessionInsertError> {
+        self.0
+            .insert(Self::TOTP_ENROLLMENT_SECRET_KEY, secret.expose_secret())
+    }
+
+    pub fn get_totp_enrollment_secret(&self) -> Result<Option<Secret<String>>, SessionGetError> {
+        let secret = self.0.get::<String>(Self::TOTP_ENROLLMENT_SECRET_KEY)?;
+        Ok(secret.map(Secret::new))
+    }
+
+    pub fn remove_totp_enrollment_secret(&self) {
+        self.0.remove(Self::TOTP_ENROLLMENT_SECRET_KEY);
+    }
+
     pub fn log_out(self) {
         self.0.purge();
     }
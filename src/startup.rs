@@ -9,17 +9,22 @@ use actix_web::{App, HttpServer, web};
 use actix_web_flash_messages::FlashMessagesFramework;
 use actix_web_flash_messages::storage::CookieMessageStore;
 use actix_web_metrics::ActixWebMetrics;
-use anyhow::Ok;
+use anyhow::{Context, Ok};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
+use tokio_util::sync::CancellationToken;
 use tracing_actix_web::TracingLogger;
 
-use crate::authentication::reject_anonymous_users;
-use crate::configuration::Settings;
+use crate::authentication::{
+    Permission, Scope, reject_anonymous_api_clients, reject_anonymous_users, require_permission,
+    require_scope,
+};
+use crate::configuration::{ApplicationSettings, Settings};
 use crate::email_client::EmailClient;
 use crate::metrics::get_metrics_middleware;
 use crate::routes::*;
+use crate::tracking::Tracker;
 
 pub struct Application {
     port: u16,
@@ -45,8 +50,7 @@ impl Application {
             listener,
             connection,
             email_client,
-            configuration.application.base_url,
-            configuration.application.hmac_secret,
+            configuration.application,
             configuration.redis_uri,
             metrics,
         )
@@ -59,7 +63,15 @@ impl Application {
         self.port
     }
 
-    pub async fn run_until_stopped(self) -> Result<(), anyhow::Error> {
+    /// Runs until `shutdown` is cancelled, then stops accepting connections and waits
+    /// for in-flight requests to complete.
+    pub async fn run_until_stopped(self, shutdown: CancellationToken) -> Result<(), anyhow::Error> {
+        let handle = self.server.handle();
+        tokio::spawn(async move {
+            shutdown.cancelled().await;
+            handle.stop(true).await;
+        });
+
         self.server.await?;
 
         Ok(())
@@ -74,27 +86,47 @@ pub fn get_connection_pool(connection_string: String) -> PgPool {
     PgPoolOptions::new().connect_lazy_with(options)
 }
 
+pub async fn run_migrations(pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::migrate!("./migrations")
+        .run(pool)
+        .await
+        .context("Failed to run database migrations")?;
+
+    Ok(())
+}
+
 #[derive(Debug)]
 pub struct ApplicationBaseUrl(pub String);
 
 #[derive(Clone)]
 pub struct HmacSecret(pub Secret<String>);
 
+/// Required to complete the first-run setup when set in the configuration.
+pub struct BootstrapToken(pub Option<Secret<String>>);
+
 async fn run(
     listener: TcpListener,
     connection: PgPool,
     email_client: EmailClient,
-    base_url: String,
-    hmac_secret: Secret<String>,
+    application: ApplicationSettings,
     redis_uri: Secret<String>,
     metrics: ActixWebMetrics,
 ) -> Result<Server, anyhow::Error> {
+    let shutdown_timeout = application.shutdown_timeout();
+
     //Data
     let connection = web::Data::new(connection);
     let email_client = web::Data::new(email_client);
-    let base_url = web::Data::new(ApplicationBaseUrl(base_url));
+    let tracker = web::Data::new(Tracker::new(
+        application.base_url.clone(),
+        application.hmac_secret.clone(),
+        application.track_opens,
+    ));
+    let base_url = web::Data::new(ApplicationBaseUrl(application.base_url));
+    let bootstrap_token = web::Data::new(BootstrapToken(application.bootstrap_token));
+    let login_throttle = web::Data::new(application.login_throttle);
 
-    let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
+    let secret_key = Key::from(application.hmac_secret.expose_secret().as_bytes());
 
     //Middlewares
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
@@ -114,23 +146,279 @@ async fn run(
             .route("/health_check", web::get().to(health_check))
             .route("/subscriptions", web::post().to(subscribe))
             .route("/subscriptions/confirm", web::get().to(confirm))
+            .route(
+                "/subscriptions/personal-data",
+                web::get().to(data_request_form),
+            )
+            .route(
+                "/subscriptions/personal-data",
+                web::post().to(request_personal_data),
+            )
+            .route(
+                "/subscriptions/personal-data/confirm",
+                web::get().to(data_request_confirmation_form),
+            )
+            .route(
+                "/subscriptions/personal-data/confirm",
+                web::post().to(complete_data_request),
+            )
             .service(
                 web::scope("/admin")
                     .wrap(from_fn(reject_anonymous_users))
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
                     .route("/logout", web::post().to(log_out))
+                    .route("/api-tokens", web::get().to(api_tokens_page))
+                    .route("/api-tokens", web::post().to(new_api_token))
+                    .route(
+                        "/api-tokens/{api_token_id}/delete",
+                        web::post().to(delete_api_token),
+                    )
+                    .route("/two-factor", web::get().to(two_factor_settings))
+                    .route("/two-factor", web::post().to(confirm_two_factor_enrollment))
+                    .route("/two-factor/disable", web::post().to(turn_off_two_factor))
                     .route("/dashboard", web::get().to(admin_dashboard))
-                    .route("/newsletters", web::get().to(send_issue_form))
-                    .route("/newsletters", web::post().to(send_issue)),
+                    .route(
+                        "/newsletters",
+                        web::get().to(send_issue_form).wrap(from_fn(|req, next| {
+                            require_permission(Permission::DraftIssues, req, next)
+                        })),
+                    )
+                    .route(
+                        "/newsletters",
+                        web::post().to(send_issue).wrap(from_fn(|req, next| {
+                            require_permission(Permission::PublishIssues, req, next)
+                        })),
+                    )
+                    .service(
+                        web::scope("/issues")
+                            .wrap(from_fn(|req, next| {
+                                require_permission(Permission::ViewStats, req, next)
+                            }))
+                            .route("", web::get().to(issues_page))
+                            .route("/{issue_id}", web::get().to(issue_stats)),
+                    )
+                    .service(
+                        web::scope("/subscribers")
+                            .route(
+                                "",
+                                web::get().to(subscribers_page).wrap(from_fn(|req, next| {
+                                    require_permission(Permission::ViewSubscribers, req, next)
+                                })),
+                            )
+                            .route(
+                                "/export",
+                                web::get().to(export_subscribers).wrap(from_fn(|req, next| {
+                                    require_permission(Permission::ManageSubscribers, req, next)
+                                })),
+                            )
+                            .service(
+                                web::scope("/personal-data")
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::ManageSubscribers, req, next)
+                                    }))
+                                    .route("", web::get().to(personal_data_page))
+                                    .route("/export", web::post().to(export_personal_data))
+                                    .route("/erase", web::post().to(erase_subscriber_data)),
+                            )
+                            .service(
+                                web::scope("/imports")
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::ManageSubscribers, req, next)
+                                    }))
+                                    .route("", web::get().to(subscriber_imports_page))
+                                    .route("", web::post().to(import_subscribers))
+                                    .route(
+                                        "/{import_id}/rejections.csv",
+                                        web::get().to(import_rejections_report),
+                                    ),
+                            )
+                            .route(
+                                "/{subscriber_id}",
+                                web::get().to(subscriber_details).wrap(from_fn(|req, next| {
+                                    require_permission(Permission::ViewSubscribers, req, next)
+                                })),
+                            )
+                            .service(
+                                web::scope("/{subscriber_id}")
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::ManageSubscribers, req, next)
+                                    }))
+                                    .route(
+                                        "/resend-confirmation",
+                                        web::post().to(resend_confirmation),
+                                    )
+                                    .route("/unsubscribe", web::post().to(unsubscribe_subscriber))
+                                    .route("/delete", web::post().to(remove_subscriber)),
+                            ),
+                    )
+                    .service(
+                        web::scope("/users")
+                            .wrap(from_fn(|req, next| {
+                                require_permission(Permission::ManageUsers, req, next)
+                            }))
+                            .route("", web::get().to(users_page))
+                            .route("/invitations", web::post().to(invite_user))
+                            .route("/{user_id}/deactivate", web::post().to(deactivate_user))
+                            .route("/{user_id}/reactivate", web::post().to(reactivate_user))
+                            .route("/{user_id}/delete", web::post().to(remove_user)),
+                    )
+                    .route(
+                        "/audit",
+                        web::get().to(audit_log_page).wrap(from_fn(|req, next| {
+                            require_permission(Permission::ManageUsers, req, next)
+                        })),
+                    ),
+            )
+            .route("/api/openapi.json", web::get().to(openapi_document))
+            .configure(openapi_viewer)
+            .service(
+                web::scope("/api/v1")
+                    .wrap(from_fn(reject_anonymous_api_clients))
+                    .app_data(api_json_config())
+                    .app_data(api_query_config())
+                    .route("/me", web::get().to(current_api_client))
+                    .service(
+                        web::scope("/issues")
+                            .route(
+                                "",
+                                web::get().to(list_issues).wrap(from_fn(|req, next| {
+                                    require_scope(Scope::IssuesRead, req, next)
+                                })),
+                            )
+                            .route(
+                                "",
+                                web::post()
+                                    .to(create_issue)
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::DraftIssues, req, next)
+                                    }))
+                                    .wrap(from_fn(|req, next| {
+                                        require_scope(Scope::IssuesWrite, req, next)
+                                    })),
+                            )
+                            .route(
+                                "/{issue_id}",
+                                web::get().to(get_issue).wrap(from_fn(|req, next| {
+                                    require_scope(Scope::IssuesRead, req, next)
+                                })),
+                            )
+                            .route(
+                                "/{issue_id}",
+                                web::patch()
+                                    .to(update_issue)
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::DraftIssues, req, next)
+                                    }))
+                                    .wrap(from_fn(|req, next| {
+                                        require_scope(Scope::IssuesWrite, req, next)
+                                    })),
+                            )
+                            .route(
+                                "/{issue_id}/publish",
+                                web::post()
+                                    .to(publish_issue)
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::PublishIssues, req, next)
+                                    }))
+                                    .wrap(from_fn(|req, next| {
+                                        require_scope(Scope::IssuesWrite, req, next)
+                                    })),
+                            )
+                            .route(
+                                "/{issue_id}/delivery",
+                                web::get()
+                                    .to(issue_delivery_status)
+                                    .wrap(from_fn(|req, next| {
+                                        require_scope(Scope::IssuesRead, req, next)
+                                    })),
+                            ),
+                    )
+                    .service(
+                        web::scope("/subscribers")
+                            .route(
+                                "",
+                                web::get()
+                                    .to(list_subscribers)
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::ViewSubscribers, req, next)
+                                    }))
+                                    .wrap(from_fn(|req, next| {
+                                        require_scope(Scope::SubscribersRead, req, next)
+                                    })),
+                            )
+                            .route(
+                                "",
+                                web::post()
+                                    .to(create_subscriber)
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::ManageSubscribers, req, next)
+                                    }))
+                                    .wrap(from_fn(|req, next| {
+                                        require_scope(Scope::SubscribersWrite, req, next)
+                                    })),
+                            )
+                            .route(
+                                "/{subscriber_id}",
+                                web::get()
+                                    .to(get_subscriber)
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::ViewSubscribers, req, next)
+                                    }))
+                                    .wrap(from_fn(|req, next| {
+                                        require_scope(Scope::SubscribersRead, req, next)
+                                    })),
+                            )
+                            .route(
+                                "/{subscriber_id}",
+                                web::patch()
+                                    .to(update_subscriber)
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::ManageSubscribers, req, next)
+                                    }))
+                                    .wrap(from_fn(|req, next| {
+                                        require_scope(Scope::SubscribersWrite, req, next)
+                                    })),
+                            )
+                            .route(
+                                "/{subscriber_id}",
+                                web::delete()
+                                    .to(delete_subscriber)
+                                    .wrap(from_fn(|req, next| {
+                                        require_permission(Permission::ManageSubscribers, req, next)
+                                    }))
+                                    .wrap(from_fn(|req, next| {
+                                        require_scope(Scope::SubscribersWrite, req, next)
+                                    })),
+                            ),
+                    ),
             )
             .route("/login", web::get().to(login_form))
             .route("/login", web::post().to(login))
+            .route("/login/two-factor", web::get().to(second_factor_form))
+            .route("/login/two-factor", web::post().to(submit_second_factor))
+            .route("/forgot-password", web::get().to(forgot_password_form))
+            .route("/forgot-password", web::post().to(forgot_password))
+            .route("/reset-password", web::get().to(reset_password_form))
+            .route("/reset-password", web::post().to(reset_password))
+            .route("/setup", web::get().to(setup_form))
+            .route("/setup", web::post().to(setup))
+            .route("/invitations/accept", web::get().to(invitation_form))
+            .route("/invitations/accept", web::post().to(join))
+            .route("/t/c/{token}", web::get().to(follow_link))
+            .route("/t/o/{token}", web::get().to(open_pixel))
             .route("/", web::get().to(home))
             .app_data(connection.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(tracker.clone())
+            .app_data(bootstrap_token.clone())
+            .app_data(login_throttle.clone())
     })
+    // Signals are handled by the caller, which coordinates the shutdown with the worker.
+    .disable_signals()
+    // actix-web only takes whole seconds, round up so a sub-second timeout is not zero.
+    .shutdown_timeout(shutdown_timeout.as_millis().div_ceil(1000) as u64)
     .listen(listener)?
     .run();
 
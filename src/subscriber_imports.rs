@@ -0,0 +1,668 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use csv_core::ReadRecordResult;
+use sqlx::{Executor, PgPool};
+use uuid::Uuid;
+
+use crate::consent::record_imported_signups;
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionStatus};
+use crate::email_client::{BatchEmail, DeliveryOutcome, EmailClient, MAX_BATCH_SIZE};
+use crate::routes::{
+    CONFIRMATION_EMAIL_SUBJECT, confirmation_email_bodies, error_chain_fmt,
+    generate_subscription_token,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Subscribers are confirmed straight away, e.g. when they already opted in elsewhere.
+    Confirmed,
+    /// Subscribers are sent a confirmation email, as when they sign up themselves.
+    OptIn,
+}
+
+impl ImportMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportMode::Confirmed => "confirmed",
+            ImportMode::OptIn => "opt_in",
+        }
+    }
+}
+
+impl TryFrom<String> for ImportMode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "confirmed" => Ok(ImportMode::Confirmed),
+            "opt_in" => Ok(ImportMode::OptIn),
+            other => Err(format!(
+                "{} is not a valid import mode. Use either `confirmed` or `opt_in`.",
+                other
+            )),
+        }
+    }
+}
+
+/// A record of a CSV file. `row` counts records from 1, the header included.
+pub struct CsvRecord {
+    pub row: i64,
+    /// `Err` when the record is not valid UTF-8.
+    pub fields: Result<Vec<String>, String>,
+}
+
+/// Parses a CSV file fed in chunks of any size, so uploads never have to be held in memory.
+pub struct CsvRecordReader {
+    reader: csv_core::Reader,
+    output: Vec<u8>,
+    ends: Vec<usize>,
+    n_output: usize,
+    n_ends: usize,
+    n_records: i64,
+}
+
+impl Default for CsvRecordReader {
+    fn default() -> Self {
+        Self {
+            reader: csv_core::Reader::new(),
+            output: vec![0; 1024],
+            ends: vec![0; 16],
+            n_output: 0,
+            n_ends: 0,
+            n_records: 0,
+        }
+    }
+}
+
+impl CsvRecordReader {
+    /// Returns the records completed by `input`. The rest is kept for the next call.
+    pub fn feed(&mut self, input: &[u8]) -> Vec<CsvRecord> {
+        let mut records = Vec::new();
+        // An empty input tells the parser the file is over.
+        if !input.is_empty() {
+            self.read(input, &mut records);
+        }
+        records
+    }
+
+    /// Returns the last record, when the file does not end with a line break.
+    pub fn finish(&mut self) -> Option<CsvRecord> {
+        let mut records = Vec::new();
+        self.read(&[], &mut records);
+        records.pop()
+    }
+
+    fn read(&mut self, mut input: &[u8], records: &mut Vec<CsvRecord>) {
+        loop {
+            let (result, n_input, n_output, n_ends) = self.reader.read_record(
+                input,
+                &mut self.output[self.n_output..],
+                &mut self.ends[self.n_ends..],
+            );
+            input = &input[n_input..];
+            self.n_output += n_output;
+            self.n_ends += n_ends;
+
+            match result {
+                ReadRecordResult::InputEmpty | ReadRecordResult::End => return,
+                ReadRecordResult::OutputFull => self.output.resize(self.output.len() * 2, 0),
+                ReadRecordResult::OutputEndsFull => self.ends.resize(self.ends.len() * 2, 0),
+                ReadRecordResult::Record => records.push(self.take_record()),
+            }
+        }
+    }
+
+    fn take_record(&mut self) -> CsvRecord {
+        self.n_records += 1;
+
+        let mut start = 0;
+        let fields = self.ends[..self.n_ends]
+            .iter()
+            .map(|&end| {
+                let field = String::from_utf8(self.output[start..end].to_vec());
+                start = end;
+                field
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| "The row is not valid UTF-8.".to_string());
+
+        self.n_output = 0;
+        self.n_ends = 0;
+
+        CsvRecord {
+            row: self.n_records,
+            fields,
+        }
+    }
+}
+
+/// Positions of the columns read from the file, found by name in its header.
+struct Columns {
+    email: usize,
+    name: usize,
+}
+
+impl Columns {
+    fn from_header(header: &[String]) -> Result<Self, ImportError> {
+        let position = |column: &str| {
+            header
+                .iter()
+                .position(|field| field.trim().eq_ignore_ascii_case(column))
+                .ok_or_else(|| {
+                    ImportError::InvalidHeader(format!(
+                        "The first row of the file must name an `{}` column.",
+                        column
+                    ))
+                })
+        };
+
+        Ok(Self {
+            email: position("email")?,
+            name: position("name")?,
+        })
+    }
+
+    fn parse(&self, fields: &[String]) -> Result<NewSubscriber, String> {
+        let field = |i: usize| {
+            fields
+                .get(i)
+                .map(|field| field.trim().to_string())
+                .filter(|field| !field.is_empty())
+        };
+
+        let email = field(self.email).ok_or("The email is missing.")?;
+        let name = field(self.name).ok_or("The name is missing.")?;
+
+        Ok(NewSubscriber {
+            email: SubscriberEmail::parse(email)?,
+            name: SubscriberName::parse(name)?,
+        })
+    }
+}
+
+struct Rejection {
+    row: i64,
+    email: String,
+    name: String,
+    reason: String,
+}
+
+#[derive(Default)]
+pub struct ImportSummary {
+    pub import_id: Uuid,
+    pub imported: i64,
+    pub rejected: i64,
+    /// Confirmation emails the provider did not accept. The subscribers are imported
+    /// regardless, and can be sent a new link from the subscriber list.
+    pub unsent: i64,
+}
+
+pub struct SubscriberImport {
+    pub import_id: Uuid,
+    pub file_name: String,
+    pub mode: String,
+    pub created_at: DateTime<Utc>,
+    pub imported: i32,
+    pub rejected: i32,
+    pub unsent: i32,
+}
+
+pub struct RejectedRow {
+    pub row: i64,
+    pub email: String,
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(thiserror::Error)]
+pub enum ImportError {
+    #[error("{0}")]
+    InvalidHeader(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Imports the records of a CSV file as they are read, [`MAX_BATCH_SIZE`] rows at a time.
+///
+/// Rows that can't be imported, including subscribers who already exist, are stored
+/// with the reason so they can be downloaded as a report.
+pub struct SubscriberImporter<'a> {
+    mode: ImportMode,
+    file_name: String,
+    user_id: Uuid,
+    pool: &'a PgPool,
+    email_client: &'a EmailClient,
+    base_url: &'a str,
+    columns: Option<Columns>,
+    seen: HashSet<String>,
+    subscribers: Vec<(i64, NewSubscriber)>,
+    rejections: Vec<Rejection>,
+    summary: ImportSummary,
+}
+
+impl<'a> SubscriberImporter<'a> {
+    pub fn new(
+        mode: ImportMode,
+        file_name: String,
+        user_id: Uuid,
+        pool: &'a PgPool,
+        email_client: &'a EmailClient,
+        base_url: &'a str,
+    ) -> Self {
+        Self {
+            mode,
+            file_name,
+            user_id,
+            pool,
+            email_client,
+            base_url,
+            columns: None,
+            seen: HashSet::new(),
+            subscribers: Vec::new(),
+            rejections: Vec::new(),
+            summary: ImportSummary::default(),
+        }
+    }
+
+    /// The first record is the header, naming the `email` and `name` columns.
+    pub async fn add(&mut self, record: CsvRecord) -> Result<(), ImportError> {
+        let Some(columns) = &self.columns else {
+            let header = record.fields.map_err(ImportError::InvalidHeader)?;
+            self.columns = Some(Columns::from_header(&header)?);
+            self.summary.import_id = self.start().await?;
+            return Ok(());
+        };
+
+        let outcome = record
+            .fields
+            .as_deref()
+            .map_err(|e| e.clone())
+            .and_then(|fields| columns.parse(fields));
+        match outcome {
+            Ok(subscriber) if !self.seen.insert(subscriber.email.as_ref().to_string()) => self
+                .reject(
+                    record.row,
+                    &subscriber,
+                    "The email appears earlier in the file.",
+                ),
+            Ok(subscriber) => self.subscribers.push((record.row, subscriber)),
+            Err(reason) => {
+                let fields = record.fields.unwrap_or_default();
+                let field = |i: usize| fields.get(i).cloned().unwrap_or_default();
+                self.rejections.push(Rejection {
+                    row: record.row,
+                    email: field(columns.email),
+                    name: field(columns.name),
+                    reason,
+                });
+            }
+        }
+
+        if self.subscribers.len() >= MAX_BATCH_SIZE || self.rejections.len() >= MAX_BATCH_SIZE {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn finish(mut self) -> Result<ImportSummary, ImportError> {
+        if self.columns.is_none() {
+            return Err(ImportError::InvalidHeader("The file is empty.".into()));
+        }
+        self.flush().await?;
+
+        Ok(self.summary)
+    }
+
+    fn reject(&mut self, row: i64, subscriber: &NewSubscriber, reason: &str) {
+        self.rejections.push(Rejection {
+            row,
+            email: subscriber.email.to_string(),
+            name: subscriber.name.as_ref().to_string(),
+            reason: reason.into(),
+        });
+    }
+
+    async fn start(&self) -> Result<Uuid, anyhow::Error> {
+        let import_id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO subscriber_imports (import_id, user_id, file_name, mode, created_at)
+            VALUES ($1, $2, $3, $4, now())
+            "#,
+            import_id,
+            self.user_id,
+            self.file_name,
+            self.mode.as_str()
+        )
+        .execute(self.pool)
+        .await
+        .context("Failed to store a new subscriber import")?;
+
+        Ok(import_id)
+    }
+
+    #[tracing::instrument(
+        name = "Import a batch of subscribers",
+        skip(self),
+        fields(import_id = %self.summary.import_id)
+    )]
+    async fn flush(&mut self) -> Result<(), anyhow::Error> {
+        let subscribers = std::mem::take(&mut self.subscribers);
+
+        let ids: Vec<Uuid> = subscribers.iter().map(|_| Uuid::new_v4()).collect();
+        let emails: Vec<&str> = subscribers.iter().map(|(_, s)| s.email.as_ref()).collect();
+        let names: Vec<&str> = subscribers.iter().map(|(_, s)| s.name.as_ref()).collect();
+        let status = match self.mode {
+            ImportMode::Confirmed => SubscriptionStatus::Confirmed,
+            ImportMode::OptIn => SubscriptionStatus::PendingConfirmation,
+        };
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to acquire a Postgres connection from the pool")?;
+
+        let inserted: HashSet<Uuid> = sqlx::query!(
+            r#"
+            INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+            SELECT id, email, name, now(), $4
+            FROM UNNEST($1::uuid[], $2::text[], $3::text[]) AS t(id, email, name)
+            ON CONFLICT (email) DO NOTHING
+            RETURNING id
+            "#,
+            &ids,
+            &emails as &[&str],
+            &names as &[&str],
+            status.as_str()
+        )
+        .fetch_all(&mut *transaction)
+        .await
+        .context("Failed to insert imported subscribers")?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+
+        let mut imported = Vec::with_capacity(inserted.len());
+        for ((row, subscriber), id) in subscribers.into_iter().zip(&ids) {
+            if inserted.contains(id) {
+                imported.push((*id, subscriber));
+            } else {
+                self.reject(row, &subscriber, "The email is already subscribed.");
+            }
+        }
+
+        let subscriber_ids: Vec<Uuid> = imported.iter().map(|(id, _)| *id).collect();
+        record_imported_signups(&mut *transaction, &subscriber_ids).await?;
+
+        let tokens: Vec<String> = match self.mode {
+            ImportMode::Confirmed => Vec::new(),
+            ImportMode::OptIn => imported
+                .iter()
+                .map(|_| generate_subscription_token())
+                .collect(),
+        };
+        if !tokens.is_empty() {
+            let query = sqlx::query!(
+                r#"
+                INSERT INTO subscription_tokens (subscription_token, subscriber_id)
+                SELECT * FROM UNNEST($1::text[], $2::uuid[])
+                "#,
+                &tokens,
+                &subscriber_ids
+            );
+            transaction
+                .execute(query)
+                .await
+                .context("Failed to store the confirmation tokens of imported subscribers")?;
+        }
+
+        let rejections = std::mem::take(&mut self.rejections);
+        let query = sqlx::query!(
+            r#"
+            INSERT INTO subscriber_import_rejections (import_id, csv_row, email, name, reason)
+            SELECT $1, * FROM UNNEST($2::int8[], $3::text[], $4::text[], $5::text[])
+            "#,
+            self.summary.import_id,
+            &rejections.iter().map(|r| r.row).collect::<Vec<_>>(),
+            &rejections
+                .iter()
+                .map(|r| r.email.clone())
+                .collect::<Vec<_>>(),
+            &rejections
+                .iter()
+                .map(|r| r.name.clone())
+                .collect::<Vec<_>>(),
+            &rejections
+                .iter()
+                .map(|r| r.reason.clone())
+                .collect::<Vec<_>>()
+        );
+        transaction
+            .execute(query)
+            .await
+            .context("Failed to store rejected rows of a subscriber import")?;
+
+        let query = sqlx::query!(
+            r#"
+            UPDATE subscriber_imports
+            SET n_imported = n_imported + $2, n_rejected = n_rejected + $3
+            WHERE import_id = $1
+            "#,
+            self.summary.import_id,
+            imported.len() as i32,
+            rejections.len() as i32
+        );
+        transaction
+            .execute(query)
+            .await
+            .context("Failed to update the progress of a subscriber import")?;
+
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to import subscribers")?;
+
+        self.summary.imported += imported.len() as i64;
+        self.summary.rejected += rejections.len() as i64;
+
+        if !tokens.is_empty() {
+            self.send_confirmation_emails(&imported, &tokens).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_confirmation_emails(
+        &mut self,
+        subscribers: &[(Uuid, NewSubscriber)],
+        tokens: &[String],
+    ) -> Result<(), anyhow::Error> {
+        let bodies: Vec<_> = tokens
+            .iter()
+            .map(|token| confirmation_email_bodies(self.base_url, token))
+            .collect();
+        let emails: Vec<_> = subscribers
+            .iter()
+            .zip(&bodies)
+            .map(|((_, subscriber), (html_body, plain_body))| BatchEmail {
+                recipient: &subscriber.email,
+                subject: CONFIRMATION_EMAIL_SUBJECT,
+                html_content: html_body,
+                text_content: plain_body,
+            })
+            .collect();
+
+        let unsent = match self.email_client.send_batch(&emails).await {
+            Ok(results) => results
+                .into_iter()
+                .map(|result| result.outcome())
+                .filter(|outcome| !matches!(outcome, DeliveryOutcome::Delivered))
+                .count(),
+            Err(e) => {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to send confirmation emails to imported subscribers"
+                );
+                emails.len()
+            }
+        };
+        if unsent == 0 {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE subscriber_imports
+            SET n_unsent = n_unsent + $2
+            WHERE import_id = $1
+            "#,
+            self.summary.import_id,
+            unsent as i32
+        )
+        .execute(self.pool)
+        .await
+        .context("Failed to update the progress of a subscriber import")?;
+        self.summary.unsent += unsent as i64;
+
+        Ok(())
+    }
+}
+
+/// Most recent first.
+#[tracing::instrument(name = "List subscriber imports", skip(pool))]
+pub async fn list_subscriber_imports(
+    limit: i64,
+    pool: &PgPool,
+) -> Result<Vec<SubscriberImport>, anyhow::Error> {
+    sqlx::query_as!(
+        SubscriberImport,
+        r#"
+        SELECT
+            import_id,
+            file_name,
+            mode,
+            created_at,
+            n_imported AS imported,
+            n_rejected AS rejected,
+            n_unsent AS unsent
+        FROM subscriber_imports
+        ORDER BY created_at DESC
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to list subscriber imports")
+}
+
+/// In the order of the file. Returns `None` if there is no such import.
+#[tracing::instrument(name = "Get rejected rows of an import", skip(pool))]
+pub async fn get_rejected_rows(
+    import_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<Vec<RejectedRow>>, anyhow::Error> {
+    let exists = sqlx::query!(
+        "SELECT import_id FROM subscriber_imports WHERE import_id = $1",
+        import_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve a subscriber import")?
+    .is_some();
+    if !exists {
+        return Ok(None);
+    }
+
+    let rows = sqlx::query_as!(
+        RejectedRow,
+        r#"
+        SELECT csv_row AS row, email, name, reason
+        FROM subscriber_import_rejections
+        WHERE import_id = $1
+        ORDER BY csv_row
+        "#,
+        import_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve the rejected rows of an import")?;
+
+    Ok(Some(rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CsvRecord, CsvRecordReader};
+
+    fn read_all(chunks: &[&[u8]]) -> Vec<CsvRecord> {
+        let mut reader = CsvRecordReader::default();
+        let mut records: Vec<_> = chunks.iter().flat_map(|c| reader.feed(c)).collect();
+        records.extend(reader.finish());
+        records
+    }
+
+    fn fields(records: &[CsvRecord]) -> Vec<Vec<String>> {
+        records.iter().map(|r| r.fields.clone().unwrap()).collect()
+    }
+
+    #[test]
+    fn records_split_across_chunks_are_reassembled() {
+        let records = read_all(&[
+            b"email,na",
+            b"me\r\nursula@exa",
+            b"mple.com,\"Le Guin",
+            b", Ursula\"\r\n",
+        ]);
+
+        assert_eq!(
+            fields(&records),
+            vec![
+                vec!["email", "name"],
+                vec!["ursula@example.com", "Le Guin, Ursula"]
+            ]
+        );
+        assert_eq!(records[1].row, 2);
+    }
+
+    #[test]
+    fn the_last_record_does_not_need_a_line_break() {
+        let records = read_all(&[b"email,name\nted@example.com,Ted"]);
+
+        assert_eq!(fields(&records)[1], vec!["ted@example.com", "Ted"]);
+    }
+
+    #[test]
+    fn quoted_fields_can_span_lines_and_grow_the_buffers() {
+        let long_name = "a".repeat(5000);
+        let input = format!("\"multi\nline\",{long_name},{}\n", vec!["x"; 40].join(","));
+
+        let records = read_all(&[input.as_bytes()]);
+
+        assert_eq!(records.len(), 1);
+        let fields = records[0].fields.as_ref().unwrap();
+        assert_eq!(fields[0], "multi\nline");
+        assert_eq!(fields[1], long_name);
+        assert_eq!(fields.len(), 42);
+    }
+
+    #[test]
+    fn invalid_utf8_is_reported_per_record() {
+        let records = read_all(&[b"email,name\n\xff@example.com,Ted\nted@example.com,Ted\n"]);
+
+        assert!(records[1].fields.is_err());
+        assert!(records[2].fields.is_ok());
+        assert_eq!(records[2].row, 3);
+    }
+}
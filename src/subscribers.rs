@@ -0,0 +1,269 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures_util::{Stream, StreamExt};
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::domain::{SubscriberName, SubscriptionStatus};
+
+pub struct Subscriber {
+    pub subscriber_id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub status: SubscriptionStatus,
+    pub subscribed_at: DateTime<Utc>,
+    /// Whether the subscriber asked not to have their opens and clicks tracked.
+    pub do_not_track: bool,
+}
+
+/// Criteria left as `None` match every subscriber.
+#[derive(Default)]
+pub struct SubscriberFilters {
+    pub status: Option<SubscriptionStatus>,
+    pub subscribed_after: Option<DateTime<Utc>>,
+    pub subscribed_before: Option<DateTime<Utc>>,
+    /// Matches anywhere in the email or name, ignoring case.
+    pub search: Option<String>,
+}
+
+/// Most recent subscribers first.
+#[tracing::instrument(name = "List subscribers", skip(filters, pool))]
+pub async fn list_subscribers(
+    filters: &SubscriberFilters,
+    limit: i64,
+    offset: i64,
+    pool: &PgPool,
+) -> Result<Vec<Subscriber>, anyhow::Error> {
+    let search = filters
+        .search
+        .as_deref()
+        .map(|search| format!("%{}%", escape_like_pattern(search)));
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, email, name, status, subscribed_at, do_not_track
+        FROM subscriptions
+        WHERE ($1::text IS NULL OR status = $1)
+            AND ($2::timestamptz IS NULL OR subscribed_at >= $2)
+            AND ($3::timestamptz IS NULL OR subscribed_at < $3)
+            AND ($4::text IS NULL OR email ILIKE $4 OR name ILIKE $4)
+        ORDER BY subscribed_at DESC, id
+        LIMIT $5
+        OFFSET $6
+        "#,
+        filters.status.map(|status| status.as_str()),
+        filters.subscribed_after,
+        filters.subscribed_before,
+        search,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to list subscribers")?;
+
+    rows.into_iter()
+        .map(|r| {
+            Ok(Subscriber {
+                subscriber_id: r.id,
+                email: r.email,
+                name: r.name,
+                status: r.status.try_into().map_err(anyhow::Error::msg)?,
+                subscribed_at: r.subscribed_at,
+                do_not_track: r.do_not_track,
+            })
+        })
+        .collect()
+}
+
+/// Every subscriber, oldest first, read from the database as the stream is consumed.
+pub fn stream_subscribers(
+    pool: &PgPool,
+) -> impl Stream<Item = Result<Subscriber, anyhow::Error>> + '_ {
+    sqlx::query!(
+        r#"
+        SELECT id, email, name, status, subscribed_at, do_not_track
+        FROM subscriptions
+        ORDER BY subscribed_at, id
+        "#
+    )
+    .fetch(pool)
+    .map(|row| {
+        let r = row.context("Failed to read a subscriber from the database")?;
+        Ok(Subscriber {
+            subscriber_id: r.id,
+            email: r.email,
+            name: r.name,
+            status: r.status.try_into().map_err(anyhow::Error::msg)?,
+            subscribed_at: r.subscribed_at,
+            do_not_track: r.do_not_track,
+        })
+    })
+}
+
+#[tracing::instrument(name = "Count subscribers", skip(filters, pool))]
+pub async fn count_subscribers(
+    filters: &SubscriberFilters,
+    pool: &PgPool,
+) -> Result<i64, anyhow::Error> {
+    let search = filters
+        .search
+        .as_deref()
+        .map(|search| format!("%{}%", escape_like_pattern(search)));
+
+    let row = sqlx::query!(
+        r#"
+        SELECT count(*) AS "count!"
+        FROM subscriptions
+        WHERE ($1::text IS NULL OR status = $1)
+            AND ($2::timestamptz IS NULL OR subscribed_at >= $2)
+            AND ($3::timestamptz IS NULL OR subscribed_at < $3)
+            AND ($4::text IS NULL OR email ILIKE $4 OR name ILIKE $4)
+        "#,
+        filters.status.map(|status| status.as_str()),
+        filters.subscribed_after,
+        filters.subscribed_before,
+        search
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform a query to count subscribers")?;
+
+    Ok(row.count)
+}
+
+#[tracing::instrument(name = "Get subscriber", skip(executor))]
+pub async fn get_subscriber<'e>(
+    subscriber_id: Uuid,
+    executor: impl Executor<'e, Database = Postgres>,
+) -> Result<Option<Subscriber>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id, email, name, status, subscribed_at, do_not_track
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to perform a query to retrieve a subscriber")?;
+
+    row.map(|r| {
+        Ok(Subscriber {
+            subscriber_id: r.id,
+            email: r.email,
+            name: r.name,
+            status: r.status.try_into().map_err(anyhow::Error::msg)?,
+            subscribed_at: r.subscribed_at,
+            do_not_track: r.do_not_track,
+        })
+    })
+    .transpose()
+}
+
+/// Fields left as `None` are not changed. Returns `false` if there is no such subscriber.
+#[tracing::instrument(name = "Update subscriber", skip(name, executor))]
+pub async fn update_subscriber<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    subscriber_id: Uuid,
+    name: Option<&SubscriberName>,
+    status: Option<SubscriptionStatus>,
+    do_not_track: Option<bool>,
+) -> Result<bool, anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET
+            name = COALESCE($2, name),
+            status = COALESCE($3, status),
+            do_not_track = COALESCE($4, do_not_track)
+        WHERE id = $1
+        "#,
+        subscriber_id,
+        name.map(|name| name.as_ref()),
+        status.map(|status| status.as_str()),
+        do_not_track
+    );
+
+    let result = executor
+        .execute(query)
+        .await
+        .context("Failed to update a subscriber")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Deletes the subscriber with their pending confirmation tokens and deliveries.
+/// Returns `false` if there is no such subscriber.
+#[tracing::instrument(name = "Delete subscriber", skip(pool))]
+pub async fn delete_subscriber(subscriber_id: Uuid, pool: &PgPool) -> Result<bool, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let query = sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+        subscriber_id
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete the subscription tokens of a subscriber")?;
+
+    let query = sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE subscriber_email = (SELECT email FROM subscriptions WHERE id = $1)
+        "#,
+        subscriber_id
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete the pending deliveries of a subscriber")?;
+
+    let query = sqlx::query!(r#"DELETE FROM subscriptions WHERE id = $1"#, subscriber_id);
+    let result = transaction
+        .execute(query)
+        .await
+        .context("Failed to delete a subscriber")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to delete a subscriber")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// `%` and `_` in user input are matched literally.
+fn escape_like_pattern(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape_like_pattern;
+
+    #[test]
+    fn like_wildcards_are_escaped() {
+        assert_eq!(escape_like_pattern("100%_a\\b"), "100\\%\\_a\\\\b");
+    }
+
+    #[test]
+    fn plain_text_is_left_unchanged() {
+        assert_eq!(
+            escape_like_pattern("ursula@example.com"),
+            "ursula@example.com"
+        );
+    }
+}
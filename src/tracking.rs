@@ -0,0 +1,563 @@
+use std::ops::Range;
+
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::escape_html;
+
+/// Bytes of the HMAC kept in tracking tokens, enough to make forging one impractical
+/// while keeping links short.
+const SIGNATURE_LENGTH: usize = 16;
+/// Tokens start with their kind, so a token can't be used as another kind of token.
+const CLICK_TOKEN: u8 = b'c';
+const OPEN_TOKEN: u8 = b'o';
+
+/// A transparent 1x1 GIF.
+pub const OPEN_PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// Builds and checks the links used to track recipients. Tokens are signed rather
+/// than stored, so delivering an issue does not write a row per recipient and link.
+#[derive(Clone)]
+pub struct Tracker {
+    base_url: String,
+    hmac_secret: Secret<String>,
+    track_opens: bool,
+}
+
+/// A link of an issue, as sent to one of its recipients.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrackedLink {
+    pub newsletter_issue_id: Uuid,
+    pub subscriber_id: Uuid,
+    /// Where the link pointed when it was sent. It is signed with the rest of the token,
+    /// so changing the issue afterwards can't send readers somewhere else.
+    pub url: String,
+}
+
+/// An issue, as sent to one of its recipients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrackedOpen {
+    pub newsletter_issue_id: Uuid,
+    pub subscriber_id: Uuid,
+}
+
+impl Tracker {
+    /// Opens are only tracked if `track_opens` is set, as `application.track_opens`.
+    pub fn new(base_url: String, hmac_secret: Secret<String>, track_opens: bool) -> Self {
+        Self {
+            base_url,
+            hmac_secret,
+            track_opens,
+        }
+    }
+
+    pub fn tracks_opens(&self) -> bool {
+        self.track_opens
+    }
+
+    /// Redirects to the link once the click is recorded.
+    pub fn click_url(&self, link: &TrackedLink) -> String {
+        let mut payload = Vec::with_capacity(32 + link.url.len());
+        payload.extend_from_slice(link.newsletter_issue_id.as_bytes());
+        payload.extend_from_slice(link.subscriber_id.as_bytes());
+        payload.extend_from_slice(link.url.as_bytes());
+
+        format!("{}/t/c/{}", self.base_url, self.sign(CLICK_TOKEN, payload))
+    }
+
+    /// Returns `None` unless `token` was issued by [`Tracker::click_url`].
+    pub fn verify_click_token(&self, token: &str) -> Option<TrackedLink> {
+        let payload = self.verify(CLICK_TOKEN, token)?;
+        let (issue_id, rest) = payload.split_first_chunk::<16>()?;
+        let (subscriber_id, url) = rest.split_first_chunk::<16>()?;
+
+        Some(TrackedLink {
+            newsletter_issue_id: Uuid::from_bytes(*issue_id),
+            subscriber_id: Uuid::from_bytes(*subscriber_id),
+            url: String::from_utf8(url.to_vec()).ok()?,
+        })
+    }
+
+    /// Serves [`OPEN_PIXEL`] once the open is recorded.
+    pub fn open_url(&self, open: &TrackedOpen) -> String {
+        let mut payload = Vec::with_capacity(32);
+        payload.extend_from_slice(open.newsletter_issue_id.as_bytes());
+        payload.extend_from_slice(open.subscriber_id.as_bytes());
+
+        format!("{}/t/o/{}", self.base_url, self.sign(OPEN_TOKEN, payload))
+    }
+
+    /// Returns `None` unless `token` was issued by [`Tracker::open_url`].
+    pub fn verify_open_token(&self, token: &str) -> Option<TrackedOpen> {
+        let payload = self.verify(OPEN_TOKEN, token)?;
+        let (issue_id, subscriber_id) = payload.split_first_chunk::<16>()?;
+
+        Some(TrackedOpen {
+            newsletter_issue_id: Uuid::from_bytes(*issue_id),
+            subscriber_id: Uuid::from_bytes(subscriber_id.try_into().ok()?),
+        })
+    }
+
+    fn mac(&self, kind: u8, payload: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.hmac_secret.expose_secret().as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.update(&[kind]);
+        mac.update(payload);
+        mac
+    }
+
+    fn sign(&self, kind: u8, mut payload: Vec<u8>) -> String {
+        let signature = self.mac(kind, &payload).finalize().into_bytes();
+        payload.extend_from_slice(&signature[..SIGNATURE_LENGTH]);
+        URL_SAFE_NO_PAD.encode(payload)
+    }
+
+    fn verify(&self, kind: u8, token: &str) -> Option<Vec<u8>> {
+        let mut payload = URL_SAFE_NO_PAD.decode(token).ok()?;
+        let signature = payload.split_off(payload.len().checked_sub(SIGNATURE_LENGTH)?);
+        self.mac(kind, &payload)
+            .verify_truncated_left(&signature)
+            .ok()?;
+        Some(payload)
+    }
+}
+
+/// The links of `html` which can be tracked, in order: the absolute `http` and `https`
+/// links of `<a>` tags. Other links, such as `mailto:` or anchors, are left alone.
+pub fn tracked_links(html: &str) -> Vec<String> {
+    find_links(html).into_iter().map(|link| link.url).collect()
+}
+
+/// Replaces every link returned by [`tracked_links`] with `rewrite(index, link)`.
+pub fn rewrite_links(html: &str, mut rewrite: impl FnMut(u32, &str) -> String) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut end = 0;
+    for (index, link) in find_links(html).into_iter().enumerate() {
+        rewritten.push_str(&html[end..link.value.start]);
+        rewritten.push_str(&escape_html(&rewrite(index as u32, &link.url)));
+        end = link.value.end;
+    }
+    rewritten.push_str(&html[end..]);
+    rewritten
+}
+
+/// Adds an image loading `url` at the end of the body of `html`.
+pub fn add_open_pixel(html: &str, url: &str) -> String {
+    let pixel = format!(
+        r#"<img src="{}" width="1" height="1" alt="" style="border:0">"#,
+        escape_html(url)
+    );
+    let end = html
+        .to_ascii_lowercase()
+        .rfind("</body>")
+        .unwrap_or(html.len());
+
+    let mut html = html.to_string();
+    html.insert_str(end, &pixel);
+    html
+}
+
+struct Link {
+    /// Where the value of the `href` attribute is in the document, without quotes.
+    value: Range<usize>,
+    url: String,
+}
+
+/// A tolerant scan of the `href` attributes of `<a>` tags, which is all we need
+/// from the HTML written by editors.
+fn find_links(html: &str) -> Vec<Link> {
+    let bytes = html.as_bytes();
+    let mut links = Vec::new();
+    let mut position = 0;
+
+    while let Some(offset) = html[position..].find('<') {
+        let start = position + offset;
+        if html[start..].starts_with("<!--") {
+            match html[start..].find("-->") {
+                Some(end) => {
+                    position = start + end + 3;
+                    continue;
+                }
+                None => break,
+            }
+        }
+
+        let is_anchor = bytes
+            .get(start + 1)
+            .is_some_and(|c| c.eq_ignore_ascii_case(&b'a'))
+            && bytes
+                .get(start + 2)
+                .is_some_and(|c| c.is_ascii_whitespace() || *c == b'>' || *c == b'/');
+        if !is_anchor {
+            position = start + 1;
+            continue;
+        }
+
+        position = start + 2;
+        // Attributes, until the end of the tag.
+        loop {
+            while bytes
+                .get(position)
+                .is_some_and(|c| c.is_ascii_whitespace() || *c == b'/')
+            {
+                position += 1;
+            }
+            match bytes.get(position) {
+                None => return links,
+                Some(b'>') => {
+                    position += 1;
+                    break;
+                }
+                _ => {}
+            }
+
+            let name_start = position;
+            while bytes
+                .get(position)
+                .is_some_and(|c| !c.is_ascii_whitespace() && !b"=>/".contains(c))
+            {
+                position += 1;
+            }
+            let name = &html[name_start..position];
+
+            while bytes.get(position).is_some_and(u8::is_ascii_whitespace) {
+                position += 1;
+            }
+            if bytes.get(position) != Some(&b'=') {
+                // An attribute without a value.
+                continue;
+            }
+            position += 1;
+            while bytes.get(position).is_some_and(u8::is_ascii_whitespace) {
+                position += 1;
+            }
+
+            let value = match bytes.get(position) {
+                Some(quote @ (b'"' | b'\'')) => {
+                    let Some(length) = html[position + 1..].find(*quote as char) else {
+                        return links;
+                    };
+                    let value = position + 1..position + 1 + length;
+                    position = value.end + 1;
+                    value
+                }
+                _ => {
+                    let value_start = position;
+                    while bytes
+                        .get(position)
+                        .is_some_and(|c| !c.is_ascii_whitespace() && *c != b'>')
+                    {
+                        position += 1;
+                    }
+                    value_start..position
+                }
+            };
+
+            if name.eq_ignore_ascii_case("href") {
+                let url = unescape_html(html[value.clone()].trim());
+                let lowercase = url.to_ascii_lowercase();
+                if lowercase.starts_with("http://") || lowercase.starts_with("https://") {
+                    links.push(Link { value, url });
+                }
+            }
+        }
+    }
+
+    links
+}
+
+/// Only the entities which are likely in a link are decoded.
+fn unescape_html(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Clicks on one of the links of an issue.
+pub struct LinkStats {
+    pub url: String,
+    pub clicks: i64,
+    /// Subscribers who clicked at least once.
+    pub subscribers: i64,
+}
+
+/// Opens of an issue.
+pub struct OpenStats {
+    /// Recipients who opened the issue at least once.
+    pub subscribers: i64,
+    pub opens: i64,
+}
+
+/// Nothing is recorded if the subscriber has been deleted since the issue was sent,
+/// or asked not to be tracked.
+#[tracing::instrument(name = "Record click", skip(pool))]
+pub async fn record_click(link: &TrackedLink, pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO link_clicks (link_click_id, newsletter_issue_id, subscriber_id, url, clicked_at)
+        SELECT $1, $2, id, $4, now()
+        FROM subscriptions
+        WHERE id = $3 AND NOT do_not_track
+        "#,
+        Uuid::new_v4(),
+        link.newsletter_issue_id,
+        link.subscriber_id,
+        link.url
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record a click")?;
+
+    Ok(())
+}
+
+/// The first open of an issue by a recipient adds a row, later ones are counted in it.
+/// As for clicks, nothing is recorded for deleted subscribers, or those who asked not
+/// to be tracked.
+#[tracing::instrument(name = "Record open", skip(pool))]
+pub async fn record_open(open: &TrackedOpen, pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO email_opens (
+            newsletter_issue_id,
+            subscriber_id,
+            first_opened_at,
+            last_opened_at,
+            n_opens
+        )
+        SELECT $1, id, now(), now(), 1
+        FROM subscriptions
+        WHERE id = $2 AND NOT do_not_track
+        ON CONFLICT (newsletter_issue_id, subscriber_id) DO UPDATE
+        SET last_opened_at = now(), n_opens = email_opens.n_opens + 1
+        "#,
+        open.newsletter_issue_id,
+        open.subscriber_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record an open")?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Get open stats", skip(pool))]
+pub async fn get_open_stats(
+    newsletter_issue_id: Uuid,
+    pool: &PgPool,
+) -> Result<OpenStats, anyhow::Error> {
+    sqlx::query_as!(
+        OpenStats,
+        r#"
+        SELECT
+            count(*) AS "subscribers!",
+            coalesce(sum(n_opens), 0) AS "opens!"
+        FROM email_opens
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform a query to retrieve open stats")
+}
+
+/// Most clicked first. Links which were never clicked are left out.
+#[tracing::instrument(name = "Get link stats", skip(pool))]
+pub async fn get_link_stats(
+    newsletter_issue_id: Uuid,
+    pool: &PgPool,
+) -> Result<Vec<LinkStats>, anyhow::Error> {
+    sqlx::query_as!(
+        LinkStats,
+        r#"
+        SELECT
+            url,
+            count(*) AS "clicks!",
+            count(DISTINCT subscriber_id) AS "subscribers!"
+        FROM link_clicks
+        WHERE newsletter_issue_id = $1
+        GROUP BY url
+        ORDER BY 2 DESC, url
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve link stats")
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use super::{
+        SIGNATURE_LENGTH, TrackedLink, TrackedOpen, Tracker, add_open_pixel, rewrite_links,
+        tracked_links,
+    };
+
+    fn tracker() -> Tracker {
+        Tracker::new(
+            "https://example.com".into(),
+            Secret::new("secret".into()),
+            true,
+        )
+    }
+
+    fn link() -> TrackedLink {
+        TrackedLink {
+            newsletter_issue_id: Uuid::new_v4(),
+            subscriber_id: Uuid::new_v4(),
+            url: "https://example.org/article?a=1&b=2".into(),
+        }
+    }
+
+    fn token(url: &str) -> &str {
+        url.strip_prefix("https://example.com/t/c/").unwrap()
+    }
+
+    #[test]
+    fn click_tokens_round_trip() {
+        let link = link();
+        let url = tracker().click_url(&link);
+
+        assert_eq!(tracker().verify_click_token(token(&url)), Some(link));
+    }
+
+    #[test]
+    fn the_url_of_a_click_token_cannot_be_changed() {
+        let link = link();
+        let url = tracker().click_url(&link);
+        let other = tracker().click_url(&TrackedLink {
+            url: "https://example.net".into(),
+            ..link
+        });
+
+        let mut payload = URL_SAFE_NO_PAD.decode(token(&url)).unwrap();
+        let mut other_payload = URL_SAFE_NO_PAD.decode(token(&other)).unwrap();
+        // Keep the signature of the original token, with the other URL.
+        let signature = payload.split_off(payload.len() - SIGNATURE_LENGTH);
+        other_payload.truncate(other_payload.len() - SIGNATURE_LENGTH);
+        other_payload.extend_from_slice(&signature);
+
+        assert_eq!(
+            tracker().verify_click_token(&URL_SAFE_NO_PAD.encode(other_payload)),
+            None
+        );
+    }
+
+    #[test]
+    fn tampered_click_tokens_are_rejected() {
+        let url = tracker().click_url(&link());
+        let mut token = token(&url).to_string();
+        let last = if token.ends_with('A') { "B" } else { "A" };
+        token.replace_range(token.len() - 1.., last);
+
+        assert_eq!(tracker().verify_click_token(&token), None);
+        assert_eq!(tracker().verify_click_token("garbage"), None);
+        assert_eq!(tracker().verify_click_token(""), None);
+    }
+
+    #[test]
+    fn click_tokens_signed_with_another_secret_are_rejected() {
+        let other = Tracker::new(
+            "https://example.com".into(),
+            Secret::new("other".into()),
+            true,
+        );
+        let url = other.click_url(&link());
+
+        assert_eq!(tracker().verify_click_token(token(&url)), None);
+    }
+
+    #[test]
+    fn open_tokens_round_trip_and_are_not_click_tokens() {
+        let open = TrackedOpen {
+            newsletter_issue_id: Uuid::new_v4(),
+            subscriber_id: Uuid::new_v4(),
+        };
+        let url = tracker().open_url(&open);
+        let open_token = url.strip_prefix("https://example.com/t/o/").unwrap();
+
+        assert_eq!(tracker().verify_open_token(open_token), Some(open));
+        assert_eq!(tracker().verify_click_token(open_token), None);
+        let click_url = tracker().click_url(&link());
+        assert_eq!(tracker().verify_open_token(token(&click_url)), None);
+    }
+
+    #[test]
+    fn the_open_pixel_is_added_at_the_end_of_the_body() {
+        let pixel =
+            r#"<img src="https://t/o?a&amp;b" width="1" height="1" alt="" style="border:0">"#;
+
+        assert_eq!(
+            add_open_pixel("<html><BODY><p>Hi</p></BODY></html>", "https://t/o?a&b"),
+            format!("<html><BODY><p>Hi</p>{}</BODY></html>", pixel)
+        );
+        assert_eq!(
+            add_open_pixel("<p>Hi</p>", "https://t/o?a&b"),
+            format!("<p>Hi</p>{}", pixel)
+        );
+    }
+
+    #[test]
+    fn only_absolute_web_links_are_tracked() {
+        let html = r##"<p>
+            <a href="https://example.com/a?x=1&amp;y=2">A</a>
+            <A class="b" HREF='http://example.com/b'>B</A>
+            <a href=https://example.com/c>C</a>
+            <a href="mailto:someone@example.com">mail</a>
+            <a href="#top">top</a>
+            <abbr href="https://example.com/abbr">not a link</abbr>
+            <!-- <a href="https://example.com/commented">D</a> -->
+            <link href="https://example.com/style.css">
+        </p>"##;
+
+        assert_eq!(
+            tracked_links(html),
+            vec![
+                "https://example.com/a?x=1&y=2",
+                "http://example.com/b",
+                "https://example.com/c",
+            ]
+        );
+    }
+
+    #[test]
+    fn links_are_rewritten_in_place() {
+        let html = r#"<a title="x" href="https://example.com/a">A</a> and <a href="https://example.com/b">B</a>"#;
+
+        let rewritten = rewrite_links(html, |index, url| {
+            format!("https://t/{}?u={}&v", index, url)
+        });
+
+        assert_eq!(
+            rewritten,
+            r#"<a title="x" href="https://t/0?u=https://example.com/a&amp;v">A</a> and <a href="https://t/1?u=https://example.com/b&amp;v">B</a>"#
+        );
+    }
+
+    #[test]
+    fn unterminated_tags_are_left_alone() {
+        let html = r#"<p>Hi</p><a href="https://example.com"#;
+
+        assert!(tracked_links(html).is_empty());
+        assert_eq!(rewrite_links(html, |_, _| unreachable!()), html);
+    }
+}
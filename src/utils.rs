@@ -1,5 +1,13 @@
-use actix_web::HttpResponse;
+use actix_web::dev::ServiceRequest;
 use actix_web::http::header::LOCATION;
+use actix_web_flash_messages::IncomingFlashMessages;
+use std::fmt::Write;
+
+use actix_web::{HttpMessage, HttpResponse};
+use futures_util::StreamExt;
+use rand::distributions::Alphanumeric;
+use rand::{Rng, thread_rng};
+use sha2::{Digest, Sha256};
 
 pub fn e500<T>(e: T) -> actix_web::Error
 where
@@ -20,3 +28,59 @@ where
 {
     actix_web::error::ErrorBadRequest(e)
 }
+
+/// Bodies larger than this are not read when a request is refused, the connection
+/// is closed instead.
+const MAX_DISCARDED_PAYLOAD_BYTES: usize = 64 * 1024;
+
+/// Reads what is left of the body of a request refused by a middleware. The
+/// connection cannot be reused by the client if the body is left unread.
+pub async fn discard_payload(req: &mut ServiceRequest) {
+    let mut payload = req.take_payload();
+    let mut discarded = 0;
+    while let Some(Ok(chunk)) = payload.next().await {
+        discarded += chunk.len();
+        if discarded > MAX_DISCARDED_PAYLOAD_BYTES {
+            break;
+        }
+    }
+}
+
+/// Random alphanumeric token, suitable for links sent by email.
+pub fn generate_token() -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(32)
+        .collect()
+}
+
+/// Tokens granting access are stored hashed, so a database leak does not expose them.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Renders flash messages as HTML paragraphs. Their content is escaped, as it
+/// may mention what the user entered.
+pub fn flash_messages_html(flash_messages: &IncomingFlashMessages) -> String {
+    let mut html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(html, "<p><i>{}</i></p>", escape_html(m.content())).unwrap();
+    }
+    html
+}
+
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,241 @@
+use newsletter_backend::audit::{AuditAction, AuditTarget, record_audit_event};
+use newsletter_backend::authentication::{Role, create_user};
+use newsletter_backend::consent::RequestMetadata;
+use newsletter_backend::domain::AdminPassword;
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_audit_log() {
+    let app = spawn_app().await;
+
+    let response = app.get_audit_log(&[]).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn only_owners_can_see_the_audit_log() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    for role in ["editor", "viewer"] {
+        app.set_test_user_role(role).await;
+
+        assert_eq!(app.get_audit_log(&[]).await.status().as_u16(), 403);
+    }
+}
+
+#[tokio::test]
+async fn logging_in_and_out_is_recorded_with_the_request_metadata() {
+    let app = spawn_app().await;
+
+    app.login_user().await;
+    app.post_logout().await;
+
+    let entries = sqlx::query!(
+        "SELECT user_id, action, ip_address FROM audit_log ORDER BY occurred_at, audit_log_id"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(entries.len(), 2);
+    for (entry, action) in entries.iter().zip(["log_in", "log_out"]) {
+        assert_eq!(entry.user_id, app.test_user.user_id);
+        assert_eq!(entry.action, action);
+        assert_eq!(entry.ip_address.as_deref(), Some("127.0.0.1"));
+    }
+}
+
+#[tokio::test]
+async fn failed_logins_are_not_recorded() {
+    let app = spawn_app().await;
+
+    app.post_login(&serde_json::json!({
+        "username": app.test_user.username,
+        "password": "wrong-password"
+    }))
+    .await;
+
+    assert!(app.audit_entries().await.is_empty());
+}
+
+#[tokio::test]
+async fn changing_the_password_is_recorded() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let new_password = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    let entries = app.audit_entries().await;
+    assert_eq!(entries.last().unwrap(), &("change_password".into(), None));
+}
+
+#[tokio::test]
+async fn publishing_an_issue_records_the_issue() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_user().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_send_issue(serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    let entries = app.audit_entries().await;
+    assert_eq!(
+        entries.last().unwrap(),
+        &("publish_issue".into(), Some(format!("issue {}", issue_id)))
+    );
+}
+
+#[tokio::test]
+async fn subscriber_edits_record_the_subscriber_id() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    app.login_user().await;
+
+    app.post_subscriber_action(subscriber_id, "unsubscribe")
+        .await;
+    app.post_subscriber_action(subscriber_id, "delete").await;
+
+    let target = Some(format!("subscriber {}", subscriber_id));
+    let entries = app.audit_entries().await;
+    assert_eq!(
+        &entries[1..],
+        &[
+            ("unsubscribe_subscriber".into(), target.clone()),
+            ("delete_subscriber".into(), target),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn actions_on_unknown_subscribers_are_not_recorded() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    app.post_subscriber_action(Uuid::new_v4(), "delete").await;
+
+    assert_eq!(app.audit_entries().await, vec![("log_in".into(), None)]);
+}
+
+#[tokio::test]
+async fn user_management_records_the_target_user() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let editor_id = create_user(
+        &Uuid::new_v4().to_string(),
+        None,
+        AdminPassword::new(Uuid::new_v4().to_string()).unwrap(),
+        Role::Editor,
+        &app.db_pool,
+    )
+    .await
+    .unwrap();
+
+    app.post_invite_user(&serde_json::json!({
+        "email": "new.user@example.com",
+        "role": "editor"
+    }))
+    .await;
+    app.post_user_action(editor_id, "deactivate").await;
+    // Unknown users are not recorded.
+    app.post_user_action(Uuid::new_v4(), "delete").await;
+
+    let entries = app.audit_entries().await;
+    assert_eq!(
+        &entries[1..],
+        &[
+            ("invite_user".into(), None),
+            (
+                "deactivate_user".into(),
+                Some(format!("user {}", editor_id))
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn the_audit_log_page_lists_entries_and_filters_them_by_action() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    app.post_logout().await;
+    app.login_user().await;
+
+    let html_page = app.get_audit_log_html(&[]).await;
+    assert!(html_page.contains("3 action(s), page 1 of 1"));
+    assert!(html_page.contains(&app.test_user.username));
+    assert!(html_page.contains("127.0.0.1"));
+
+    let html_page = app.get_audit_log_html(&[("action", "log_out")]).await;
+    assert!(html_page.contains("1 action(s), page 1 of 1"));
+}
+
+#[tokio::test]
+async fn unknown_actions_are_reported_on_the_audit_log_page() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let html_page = app.get_audit_log_html(&[("action", "launch")]).await;
+
+    assert!(html_page.contains("launch is not a recorded action."));
+    assert!(html_page.contains("1 action(s), page 1 of 1"));
+}
+
+#[tokio::test]
+async fn entries_of_deleted_users_are_kept() {
+    let app = spawn_app().await;
+    let deleted_user = Uuid::new_v4();
+    let issue_id = Uuid::new_v4();
+    record_audit_event(
+        &app.db_pool,
+        deleted_user,
+        AuditAction::PublishIssue,
+        Some(AuditTarget::Issue(issue_id)),
+        &RequestMetadata::default(),
+    )
+    .await
+    .unwrap();
+    app.login_user().await;
+
+    let html_page = app.get_audit_log_html(&[("action", "publish_issue")]).await;
+
+    assert!(html_page.contains(&deleted_user.to_string()));
+    assert!(html_page.contains(&format!("issue {}", issue_id)));
+}
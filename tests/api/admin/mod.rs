@@ -1,3 +1,9 @@
+mod audit;
 mod change_password;
 mod dashboard;
 mod newsletters;
+mod personal_data;
+mod subscriber_export;
+mod subscriber_imports;
+mod subscribers;
+mod users;
@@ -1,11 +1,19 @@
-use std::time::Duration;
+use std::num::{NonZeroU32, NonZeroU64};
+use std::time::{Duration, Instant};
 
+use newsletter_backend::configuration::RateLimitSettings;
+use newsletter_backend::issue_delivery_worker::DELIVERY_QUEUE_CHANNEL;
+use sqlx::postgres::PgListener;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 use wiremock::Mock;
 use wiremock::ResponseTemplate;
 use wiremock::matchers::{method, path};
 
-use crate::helpers::{ConfirmationLinks, TestApp, assert_is_redirect_to, spawn_app};
+use crate::helpers::{
+    assert_is_redirect_to, batch_accepted_response, create_confirmed_subscriber,
+    create_unconfirmed_subscriber, queue_delivery, spawn_app,
+};
 
 #[tokio::test]
 async fn must_be_logged_in_to_get_send_issue_form() {
@@ -31,6 +39,47 @@ async fn logged_user_can_get_send_issuer_form() {
     assert!(html_page.contains("<p>Send new issue</p>"))
 }
 
+#[tokio::test]
+async fn viewers_cannot_get_send_issue_form() {
+    let app = spawn_app().await;
+    app.set_test_user_role("viewer").await;
+    app.login_user().await;
+
+    let response = app.get_send_issue().await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn editors_can_get_send_issue_form_but_cannot_publish() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.set_test_user_role("editor").await;
+    app.login_user().await;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(batch_accepted_response(1))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.get_send_issue().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+    // The refused form is read, so the connection can be reused right away.
+    for _ in 0..10 {
+        let response = app.post_send_issue(newsletter_request_body.clone()).await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+
+    app.dispatch_all_pending_emails().await;
+}
+
 #[tokio::test]
 async fn must_be_logged_in_to_send_an_issue() {
     let app = spawn_app().await;
@@ -53,7 +102,7 @@ async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
 
     app.login_user().await;
 
-    let _mock_guard = Mock::given(path("/email"))
+    let _mock_guard = Mock::given(path("/email/batch"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
         .expect(0)
@@ -83,9 +132,9 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
 
     app.login_user().await;
 
-    let _mock_guard = Mock::given(path("/email"))
+    let _mock_guard = Mock::given(path("/email/batch"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(batch_accepted_response(1))
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -106,36 +155,6 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     app.dispatch_all_pending_emails().await;
 }
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let body = "name=diego&email=diego20@gmail.com";
-
-    let _mock_guard = Mock::given(path("/email"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .expect(1)
-        .mount_as_scoped(&app.email_server)
-        .await;
-
-    app.post_subscription(body.into())
-        .await
-        .error_for_status()
-        .unwrap();
-
-    let email_request = &app.email_server.received_requests().await.unwrap()[0];
-
-    app.get_confirmation_links(&email_request)
-}
-
-async fn create_confirmed_subscriber(app: &TestApp) {
-    let confirmation_link = create_unconfirmed_subscriber(app).await;
-
-    reqwest::get(confirmation_link.plain_text)
-        .await
-        .unwrap()
-        .error_for_status()
-        .unwrap();
-}
-
 #[tokio::test]
 async fn newsletters_return_400_for_invalid_data() {
     let app = spawn_app().await;
@@ -178,9 +197,9 @@ async fn newsletter_creation_is_idempotent() {
     create_confirmed_subscriber(&app).await;
     app.login_user().await;
 
-    Mock::given(path("/email"))
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(batch_accepted_response(1))
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -215,9 +234,9 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     create_confirmed_subscriber(&app).await;
     app.login_user().await;
 
-    Mock::given(path("/email"))
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+        .respond_with(batch_accepted_response(1).set_delay(Duration::from_secs(2)))
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -241,3 +260,296 @@ async fn concurrent_form_submission_is_handled_gracefully() {
 
     app.dispatch_all_pending_emails().await;
 }
+
+#[tokio::test]
+async fn sending_an_issue_notifies_the_delivery_workers() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_user().await;
+
+    let mut listener = PgListener::connect_with(&app.db_pool).await.unwrap();
+    listener.listen(DELIVERY_QUEUE_CHANNEL).await.unwrap();
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+
+    let response = app.post_send_issue(newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let notification = tokio::time::timeout(Duration::from_secs(5), listener.recv())
+        .await
+        .expect("No notification was received")
+        .unwrap();
+    assert_eq!(notification.channel(), DELIVERY_QUEUE_CHANNEL);
+}
+
+#[tokio::test]
+async fn retryable_delivery_failures_are_kept_in_the_queue() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            {"ErrorCode": 100, "Message": "Maintenance"}
+        ])))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+    app.post_send_issue(newsletter_request_body).await;
+
+    app.dispatch_all_pending_emails().await;
+
+    let task = sqlx::query!(
+        "SELECT n_retries, execute_after > now() AS delayed FROM issue_delivery_queue"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(task.n_retries, 1);
+    assert_eq!(task.delayed, Some(true));
+}
+
+#[tokio::test]
+async fn rejected_deliveries_are_removed_from_the_queue() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            {"ErrorCode": 406, "Message": "Inactive recipient"}
+        ])))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+    app.post_send_issue(newsletter_request_body).await;
+
+    app.dispatch_all_pending_emails().await;
+
+    let n_tasks = sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_tasks, 0);
+}
+
+#[tokio::test]
+async fn throttled_deliveries_are_paused_instead_of_failed() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+    app.post_send_issue(newsletter_request_body).await;
+
+    let started_at = std::time::Instant::now();
+    app.dispatch_all_pending_emails().await;
+
+    assert!(started_at.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn concurrent_workers_deliver_batches_in_parallel() {
+    let app = spawn_app().await;
+    queue_delivery(&app, "ursula_le_guin@gmail.com").await;
+    queue_delivery(&app, "octavia_butler@gmail.com").await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted_response(1).set_delay(Duration::from_secs(2)))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let shutdown = CancellationToken::new();
+    let worker = app.spawn_worker(
+        |c| {
+            c.worker.concurrency = 2;
+            c.worker.batch_size = 1;
+        },
+        shutdown.clone(),
+    );
+
+    // Each batch takes 2s to be accepted, so both can only be in flight at once
+    // if they are sent by different workers.
+    let started_at = Instant::now();
+    while app.email_server.received_requests().await.unwrap().len() < 2 {
+        assert!(
+            started_at.elapsed() < Duration::from_millis(1500),
+            "The second batch was not sent while the first one was in flight"
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    shutdown.cancel();
+    worker.await.unwrap().unwrap();
+    let n_pending = sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_pending, 0);
+}
+
+#[tokio::test]
+async fn throttled_workers_back_off_without_locking_tasks() {
+    let app = spawn_app().await;
+    queue_delivery(&app, "ursula_le_guin@gmail.com").await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "30"))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let shutdown = CancellationToken::new();
+    let worker = app.spawn_worker(|_| {}, shutdown.clone());
+    while app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .is_empty()
+    {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // The worker is waiting for the provider, the task must be free for others.
+    let mut transaction = app.db_pool.begin().await.unwrap();
+    let n_unlocked = sqlx::query!("SELECT n_retries FROM issue_delivery_queue FOR UPDATE NOWAIT")
+        .fetch_all(&mut *transaction)
+        .await
+        .expect("The task is still locked")
+        .len();
+    assert_eq!(n_unlocked, 1);
+    transaction.rollback().await.unwrap();
+
+    shutdown.cancel();
+    worker.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn rate_limited_workers_send_what_they_can_and_stop_when_asked() {
+    let app = spawn_app().await;
+    queue_delivery(&app, "ursula_le_guin@gmail.com").await;
+    queue_delivery(&app, "octavia_butler@gmail.com").await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let shutdown = CancellationToken::new();
+    let worker = app.spawn_worker(
+        |c| {
+            c.email_client.rate_limit = Some(RateLimitSettings {
+                messages: NonZeroU32::new(1).unwrap(),
+                period_milliseconds: NonZeroU64::new(60_000).unwrap(),
+            });
+        },
+        shutdown.clone(),
+    );
+
+    // The batch is capped at what the limit allows, instead of waiting for a full one.
+    let started_at = Instant::now();
+    while app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .is_empty()
+    {
+        assert!(
+            started_at.elapsed() < Duration::from_secs(2),
+            "The worker waited for the rate limiter instead of sending a smaller batch"
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let batch: Vec<serde_json::Value> = app.email_server.received_requests().await.unwrap()[0]
+        .body_json()
+        .unwrap();
+    assert_eq!(batch.len(), 1);
+
+    // The worker is now waiting for the rate limiter, which must not delay a shutdown.
+    let stopped_at = Instant::now();
+    shutdown.cancel();
+    worker.await.unwrap().unwrap();
+    assert!(stopped_at.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn stopped_workers_finish_the_batch_in_flight() {
+    let app = spawn_app().await;
+    queue_delivery(&app, "ursula_le_guin@gmail.com").await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted_response(1).set_delay(Duration::from_secs(1)))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let shutdown = CancellationToken::new();
+    let worker = app.spawn_worker(|_| {}, shutdown.clone());
+    while app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .is_empty()
+    {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    // The batch is still waiting for the provider when the worker is asked to stop.
+    shutdown.cancel();
+    worker.await.unwrap().unwrap();
+
+    let n_pending = sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_pending, 0);
+}
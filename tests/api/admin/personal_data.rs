@@ -0,0 +1,98 @@
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, create_unconfirmed_subscriber, queue_delivery, spawn_app,
+};
+
+const EMAIL: &str = "diego20@gmail.com";
+
+async fn rows_mentioning(app: &TestApp, email: &str) -> i64 {
+    sqlx::query!(
+        r#"
+        SELECT
+            (SELECT count(*) FROM subscriptions WHERE email = $1)
+            + (SELECT count(*) FROM subscription_tokens)
+            + (SELECT count(*) FROM issue_delivery_queue WHERE subscriber_email = $1)
+            AS "count!"
+        "#,
+        email
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count
+}
+
+#[tokio::test]
+async fn everything_stored_about_an_email_can_be_downloaded() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    create_unconfirmed_subscriber(&app).await;
+    let newsletter_issue_id = queue_delivery(&app, EMAIL).await;
+
+    let response = app.post_personal_data_action("export", EMAIL).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        response.headers()["Content-Disposition"]
+            .to_str()
+            .unwrap()
+            .starts_with("attachment")
+    );
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["email"], EMAIL);
+    assert_eq!(data["subscription"]["name"], "diego");
+    assert_eq!(data["subscription"]["status"], "pending_confirmation");
+    assert_eq!(data["subscription_tokens"].as_array().unwrap().len(), 1);
+    assert_eq!(data["consent_events"][0]["event"], "signup");
+    assert_eq!(data["consent_events"][0]["source"], "form");
+    assert_eq!(
+        data["pending_deliveries"][0]["newsletter_issue_id"],
+        newsletter_issue_id.to_string()
+    );
+}
+
+#[tokio::test]
+async fn unknown_emails_have_nothing_to_download() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let response = app
+        .post_personal_data_action("export", "nobody@example.com")
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/subscribers/personal-data");
+    let html = app.get_personal_data_html().await;
+    assert!(html.contains("Nothing is stored about this email address."));
+}
+
+#[tokio::test]
+async fn everything_stored_about_an_email_can_be_erased() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    create_unconfirmed_subscriber(&app).await;
+    queue_delivery(&app, EMAIL).await;
+    assert_eq!(rows_mentioning(&app, EMAIL).await, 3);
+
+    let response = app.post_personal_data_action("erase", EMAIL).await;
+
+    assert_is_redirect_to(&response, "/admin/subscribers/personal-data");
+    let html = app.get_personal_data_html().await;
+    assert!(html.contains("Everything stored about this email address has been erased."));
+    assert_eq!(rows_mentioning(&app, EMAIL).await, 0);
+}
+
+#[tokio::test]
+async fn only_owners_can_handle_personal_data_requests() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    create_unconfirmed_subscriber(&app).await;
+
+    for role in ["editor", "viewer"] {
+        app.set_test_user_role(role).await;
+
+        for action in ["export", "erase"] {
+            let response = app.post_personal_data_action(action, EMAIL).await;
+            assert_eq!(response.status().as_u16(), 403);
+        }
+    }
+    assert_eq!(rows_mentioning(&app, EMAIL).await, 2);
+}
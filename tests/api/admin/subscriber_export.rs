@@ -0,0 +1,117 @@
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, spawn_app};
+
+async fn insert_subscriber(app: &TestApp, email: &str, name: &str, status: &str) {
+    sqlx::query!(
+        "INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, $3, now(), $4)",
+        Uuid::new_v4(),
+        email,
+        name,
+        status
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn subscribers_can_be_exported_as_csv() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    insert_subscriber(&app, "ursula@example.com", "Le Guin, Ursula", "confirmed").await;
+
+    let response = app.get_subscriber_export("csv").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "text/csv; charset=utf-8"
+    );
+    assert!(
+        response.headers()["Content-Disposition"]
+            .to_str()
+            .unwrap()
+            .starts_with("attachment")
+    );
+    let body = response.text().await.unwrap();
+    let mut reader = csv::Reader::from_reader(body.as_bytes());
+    assert_eq!(
+        reader.headers().unwrap(),
+        vec!["subscriber_id", "email", "name", "status", "subscribed_at"]
+    );
+    let rows: Vec<_> = reader.records().map(|r| r.unwrap()).collect();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(&rows[0][1], "ursula@example.com");
+    assert_eq!(&rows[0][2], "Le Guin, Ursula");
+    assert_eq!(&rows[0][3], "confirmed");
+}
+
+#[tokio::test]
+async fn subscribers_can_be_exported_as_ndjson() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    insert_subscriber(&app, "ursula@example.com", "Ursula", "confirmed").await;
+    insert_subscriber(&app, "ted@example.com", "Ted", "pending_confirmation").await;
+
+    let response = app.get_subscriber_export("ndjson").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "application/x-ndjson");
+    let body = response.text().await.unwrap();
+    let subscribers: Vec<serde_json::Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(subscribers.len(), 2);
+    assert_eq!(subscribers[0]["email"], "ursula@example.com");
+    assert_eq!(subscribers[1]["status"], "pending_confirmation");
+    assert!(subscribers[1]["subscribed_at"].is_string());
+}
+
+#[tokio::test]
+async fn exports_larger_than_a_chunk_are_complete() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    sqlx::query!(
+        "INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        SELECT gen_random_uuid(), 'reader' || i || '@example.com', 'Reader ' || i, now(), 'confirmed'
+        FROM generate_series(1, 3000) AS i"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let body = app
+        .get_subscriber_export("ndjson")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(body.lines().count(), 3000);
+}
+
+#[tokio::test]
+async fn unknown_export_formats_are_rejected() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let response = app.get_subscriber_export("xml").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn only_owners_can_export_subscribers() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    for role in ["editor", "viewer"] {
+        app.set_test_user_role(role).await;
+
+        let response = app.get_subscriber_export("csv").await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+}
@@ -0,0 +1,221 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, batch_accepted_response, create_confirmed_subscriber, spawn_app,
+};
+
+async fn statuses(app: &TestApp) -> Vec<(String, String)> {
+    sqlx::query!("SELECT email, status FROM subscriptions ORDER BY email")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.email, r.status))
+        .collect()
+}
+
+async fn rejections_report(app: &TestApp) -> reqwest::Response {
+    let import_id = sqlx::query!("SELECT import_id FROM subscriber_imports")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .import_id;
+
+    app.get_import_rejections(import_id).await
+}
+
+#[tokio::test]
+async fn subscribers_can_be_imported_as_confirmed() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    Mock::given(path("/email/batch"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let csv =
+        "Name,Email,Plan\r\nUrsula,ursula@example.com,gold\r\n\"Chiang, Ted\",ted@example.com,free";
+    let response = app.post_subscriber_import(Some("confirmed"), csv).await;
+
+    assert_is_redirect_to(&response, "/admin/subscribers/imports");
+    let html = app.get_subscriber_imports_html().await;
+    assert!(html.contains("Imported 2 subscriber(s), rejected 0 row(s)."));
+    assert!(html.contains("subscribers.csv"));
+    assert_eq!(
+        statuses(&app).await,
+        vec![
+            ("ted@example.com".into(), "confirmed".into()),
+            ("ursula@example.com".into(), "confirmed".into()),
+        ]
+    );
+    let sources: Vec<_> = sqlx::query!("SELECT event, source, ip_address FROM consent_events")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.event, r.source, r.ip_address))
+        .collect();
+    assert_eq!(
+        sources,
+        vec![("signup".to_string(), Some("import".to_string()), None); 2]
+    );
+}
+
+#[tokio::test]
+async fn imported_subscribers_can_be_sent_an_opt_in_email() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted_response(2))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let csv = "email,name\nursula@example.com,Ursula\nted@example.com,Ted\n";
+    let response = app.post_subscriber_import(Some("opt_in"), csv).await;
+    assert_is_redirect_to(&response, "/admin/subscribers/imports");
+
+    assert_eq!(
+        statuses(&app).await,
+        vec![
+            ("ted@example.com".into(), "pending_confirmation".into()),
+            ("ursula@example.com".into(), "pending_confirmation".into()),
+        ]
+    );
+
+    // The links in the batch confirm the subscribers they were sent to.
+    let request = &app.email_server.received_requests().await.unwrap()[0];
+    let emails: Vec<serde_json::Value> = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(emails.len(), 2);
+    let link = linkify::LinkFinder::new()
+        .links(emails[0]["TextBody"].as_str().unwrap())
+        .next()
+        .unwrap()
+        .as_str()
+        .to_owned();
+    let mut link = reqwest::Url::parse(&link).unwrap();
+    link.set_port(Some(app.port)).unwrap();
+    reqwest::get(link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let recipient = emails[0]["To"].as_str().unwrap();
+    assert!(
+        statuses(&app)
+            .await
+            .contains(&(recipient.to_string(), "confirmed".to_string()))
+    );
+}
+
+#[tokio::test]
+async fn invalid_and_duplicate_rows_are_reported() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    create_confirmed_subscriber(&app).await;
+
+    let csv = "email,name\n\
+        ursula@example.com,Ursula\n\
+        not-an-email,Ted\n\
+        ted@example.com,\n\
+        ursula@example.com,Ursula again\n\
+        diego20@gmail.com,Diego\n\
+        lonely@example.com\n";
+    let response = app.post_subscriber_import(Some("confirmed"), csv).await;
+    assert_is_redirect_to(&response, "/admin/subscribers/imports");
+
+    let html = app.get_subscriber_imports_html().await;
+    assert!(html.contains("Imported 1 subscriber(s), rejected 5 row(s)."));
+    assert!(html.contains("rejected rows"));
+
+    let response = rejections_report(&app).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        response.headers()["Content-Disposition"]
+            .to_str()
+            .unwrap()
+            .starts_with("attachment")
+    );
+    let report = response.text().await.unwrap();
+    let lines: Vec<_> = report.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "row,email,name,reason",
+            "3,not-an-email,Ted,not-an-email is not a valid subscriber email.",
+            "4,ted@example.com,,The name is missing.",
+            "5,ursula@example.com,Ursula again,The email appears earlier in the file.",
+            "6,diego20@gmail.com,Diego,The email is already subscribed.",
+            "7,lonely@example.com,,The name is missing.",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn files_without_the_required_columns_are_refused() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let response = app
+        .post_subscriber_import(
+            Some("confirmed"),
+            "address,name\nursula@example.com,Ursula\n",
+        )
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/subscribers/imports");
+    let html = app.get_subscriber_imports_html().await;
+    assert!(html.contains("The first row of the file must name an `email` column."));
+    assert!(statuses(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn the_import_mode_must_be_chosen() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let response = app
+        .post_subscriber_import(None, "email,name\nursula@example.com,Ursula\n")
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/subscribers/imports");
+    let html = app.get_subscriber_imports_html().await;
+    assert!(html.contains("Choose how to import the subscribers."));
+    assert!(statuses(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn large_files_are_imported_in_batches() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let mut csv = String::from("email,name\n");
+    for i in 0..1200 {
+        csv.push_str(&format!("reader{i}@example.com,Reader {i}\n"));
+    }
+    let response = app.post_subscriber_import(Some("confirmed"), &csv).await;
+
+    assert_is_redirect_to(&response, "/admin/subscribers/imports");
+    let html = app.get_subscriber_imports_html().await;
+    assert!(html.contains("Imported 1200 subscriber(s), rejected 0 row(s)."));
+    assert_eq!(statuses(&app).await.len(), 1200);
+}
+
+#[tokio::test]
+async fn only_owners_can_import_subscribers() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    for role in ["editor", "viewer"] {
+        app.set_test_user_role(role).await;
+
+        let response = app
+            .post_subscriber_import(Some("confirmed"), "email,name\nursula@example.com,Ursula\n")
+            .await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+    assert!(statuses(&app).await.is_empty());
+}
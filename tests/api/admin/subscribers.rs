@@ -0,0 +1,315 @@
+use chrono::{TimeZone, Utc};
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, create_unconfirmed_subscriber, spawn_app};
+
+async fn insert_subscriber(app: &TestApp, email: &str, name: &str, status: &str) -> Uuid {
+    let subscriber_id = Uuid::new_v4();
+    sqlx::query!(
+        "INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, $3, now(), $4)",
+        subscriber_id,
+        email,
+        name,
+        status
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    subscriber_id
+}
+
+async fn subscriber_status(app: &TestApp, subscriber_id: Uuid) -> Option<String> {
+    sqlx::query!(
+        "SELECT status FROM subscriptions WHERE id = $1",
+        subscriber_id
+    )
+    .fetch_optional(&app.db_pool)
+    .await
+    .unwrap()
+    .map(|r| r.status)
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_browse_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app.get_admin_subscribers(&[]).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_can_be_searched_by_email_or_name_and_filtered_by_status() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    insert_subscriber(&app, "ursula@example.com", "Ursula", "confirmed").await;
+    insert_subscriber(
+        &app,
+        "le.guin@example.com",
+        "Ursula Le Guin",
+        "unsubscribed",
+    )
+    .await;
+    insert_subscriber(&app, "ted@example.com", "Ted Chiang", "confirmed").await;
+
+    let html = app
+        .get_admin_subscribers_html(&[("search", "URSULA")])
+        .await;
+    assert!(html.contains("ursula@example.com"));
+    assert!(html.contains("le.guin@example.com"));
+    assert!(!html.contains("ted@example.com"));
+
+    let html = app
+        .get_admin_subscribers_html(&[("search", "ursula"), ("status", "confirmed")])
+        .await;
+    assert!(html.contains("ursula@example.com"));
+    assert!(!html.contains("le.guin@example.com"));
+    assert!(html.contains("1 subscriber(s)"));
+}
+
+#[tokio::test]
+async fn subscribers_can_be_filtered_by_signup_date() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let old_subscriber = insert_subscriber(&app, "old@example.com", "Old", "confirmed").await;
+    insert_subscriber(&app, "new@example.com", "New", "confirmed").await;
+    sqlx::query!(
+        "UPDATE subscriptions SET subscribed_at = $2 WHERE id = $1",
+        old_subscriber,
+        Utc.with_ymd_and_hms(2020, 3, 15, 23, 59, 0).unwrap()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let html = app
+        .get_admin_subscribers_html(&[
+            ("subscribed_from", "2020-03-15"),
+            ("subscribed_to", "2020-03-15"),
+        ])
+        .await;
+    assert!(html.contains("old@example.com"));
+    assert!(!html.contains("new@example.com"));
+
+    let html = app
+        .get_admin_subscribers_html(&[("subscribed_from", "2020-03-16")])
+        .await;
+    assert!(!html.contains("old@example.com"));
+    assert!(html.contains("new@example.com"));
+}
+
+#[tokio::test]
+async fn invalid_filters_are_reported_on_the_page() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let html = app
+        .get_admin_subscribers_html(&[("status", "gone"), ("subscribed_from", "yesterday")])
+        .await;
+
+    assert!(html.contains("gone is not a valid subscription status"));
+    assert!(html.contains("yesterday is not a valid date. Use YYYY-MM-DD."));
+}
+
+#[tokio::test]
+async fn subscribers_are_paginated() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    for i in 0..55 {
+        insert_subscriber(
+            &app,
+            &format!("reader{i}@example.com"),
+            "Reader",
+            "confirmed",
+        )
+        .await;
+    }
+
+    let html = app.get_admin_subscribers_html(&[]).await;
+    assert!(html.contains("55 subscriber(s), page 1 of 2"));
+    assert_eq!(html.matches("@example.com").count(), 50);
+    assert!(html.contains(r#"name="page" value="2""#));
+
+    let html = app.get_admin_subscribers_html(&[("page", "2")]).await;
+    assert!(html.contains("page 2 of 2"));
+    assert_eq!(html.matches("@example.com").count(), 5);
+    assert!(html.contains(r#"name="page" value="1""#));
+}
+
+#[tokio::test]
+async fn subscribers_can_be_unsubscribed_and_deleted() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let subscriber_id = insert_subscriber(&app, "ursula@example.com", "Ursula", "confirmed").await;
+
+    let response = app
+        .post_subscriber_action(subscriber_id, "unsubscribe")
+        .await;
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html = app.get_admin_subscribers_html(&[]).await;
+    assert!(html.contains("The subscriber has been unsubscribed."));
+    assert_eq!(
+        subscriber_status(&app, subscriber_id).await.as_deref(),
+        Some("unsubscribed")
+    );
+
+    let response = app.post_subscriber_action(subscriber_id, "delete").await;
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html = app.get_admin_subscribers_html(&[]).await;
+    assert!(html.contains("The subscriber has been deleted."));
+    assert_eq!(subscriber_status(&app, subscriber_id).await, None);
+}
+
+#[tokio::test]
+async fn a_new_confirmation_link_can_be_sent_to_pending_subscribers() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    create_unconfirmed_subscriber(&app).await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriber_action(subscriber_id, "resend-confirmation")
+        .await;
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html = app.get_admin_subscribers_html(&[]).await;
+    assert!(html.contains("A new confirmation email has been sent."));
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let confirmation_links = app.get_confirmation_links(email_requests.last().unwrap());
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    assert_eq!(
+        subscriber_status(&app, subscriber_id).await.as_deref(),
+        Some("confirmed")
+    );
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_are_not_sent_a_new_confirmation_link() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let subscriber_id = insert_subscriber(&app, "ursula@example.com", "Ursula", "confirmed").await;
+
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriber_action(subscriber_id, "resend-confirmation")
+        .await;
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html = app.get_admin_subscribers_html(&[]).await;
+    assert!(html.contains("The subscriber is not waiting for a confirmation."));
+}
+
+#[tokio::test]
+async fn only_owners_can_change_subscribers_from_the_browser() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let subscriber_id = insert_subscriber(&app, "ursula@example.com", "Ursula", "confirmed").await;
+
+    app.set_test_user_role("editor").await;
+    let html = app.get_admin_subscribers_html(&[]).await;
+    assert!(html.contains("ursula@example.com"));
+
+    for role in ["editor", "viewer"] {
+        app.set_test_user_role(role).await;
+
+        for action in ["resend-confirmation", "unsubscribe", "delete"] {
+            let response = app.post_subscriber_action(subscriber_id, action).await;
+            assert_eq!(response.status().as_u16(), 403);
+        }
+    }
+    assert_eq!(
+        subscriber_status(&app, subscriber_id).await.as_deref(),
+        Some("confirmed")
+    );
+}
+
+#[tokio::test]
+async fn viewers_cannot_see_subscribers() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let subscriber_id = insert_subscriber(&app, "ursula@example.com", "Ursula", "confirmed").await;
+    app.set_test_user_role("viewer").await;
+
+    let response = app.get_admin_subscribers(&[]).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.get_subscriber_details(subscriber_id).await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn the_consent_history_of_a_subscriber_is_shown() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    reqwest::Client::new()
+        .post(format!("{}/subscriptions", app.address))
+        .header("User-Agent", "Mozilla/5.0 <consent test>")
+        .form(&[("name", "ursula"), ("email", "ursula@example.com")])
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+
+    let html = app.get_admin_subscribers_html(&[]).await;
+    assert!(html.contains(&format!(r#"href="/admin/subscribers/{subscriber_id}""#)));
+
+    let response = app.get_subscriber_details(subscriber_id).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("<td>signup</td>"));
+    assert!(html.contains("<td>form</td>"));
+    assert!(html.contains("<td>confirmation</td>"));
+    assert!(html.contains("<td>127.0.0.1</td>"));
+    assert!(html.contains("Mozilla/5.0 &lt;consent test&gt;"));
+}
+
+#[tokio::test]
+async fn unknown_subscribers_have_no_details_page() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let response = app.get_subscriber_details(Uuid::new_v4()).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
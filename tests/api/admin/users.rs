@@ -0,0 +1,359 @@
+use newsletter_backend::authentication::{Role, UpdateUserError, create_user, set_user_active};
+use newsletter_backend::domain::AdminPassword;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn create_editor(app: &TestApp) -> (Uuid, String, String) {
+    create_user_with_role(app, Role::Editor).await
+}
+
+async fn create_user_with_role(app: &TestApp, role: Role) -> (Uuid, String, String) {
+    let username = Uuid::new_v4().to_string();
+    let password = Uuid::new_v4().to_string();
+    let user_id = create_user(
+        &username,
+        None,
+        AdminPassword::new(password.clone()).unwrap(),
+        role,
+        &app.db_pool,
+    )
+    .await
+    .unwrap();
+
+    (user_id, username, password)
+}
+
+/// Invites `email` as the logged-in user and returns the token sent to them.
+async fn invite(app: &TestApp, email: &str, role: &str) -> String {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_invite_user(&serde_json::json!({"email": email, "role": role}))
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    app.get_confirmation_links(email_request)
+        .html
+        .query_pairs()
+        .find(|(key, _)| key == "invitation_token")
+        .unwrap()
+        .1
+        .into_owned()
+}
+
+fn new_account(token: &str, username: &str, password: &str) -> serde_json::Value {
+    serde_json::json!({
+        "invitation_token": token,
+        "username": username,
+        "password": password,
+        "password_check": password,
+    })
+}
+
+#[tokio::test]
+async fn only_owners_can_manage_users() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    for role in ["editor", "viewer"] {
+        app.set_test_user_role(role).await;
+
+        assert_eq!(app.get_admin_users().await.status().as_u16(), 403);
+
+        let response = app
+            .post_invite_user(&serde_json::json!({
+                "email": "new.user@example.com",
+                "role": "owner"
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+}
+
+#[tokio::test]
+async fn owners_see_the_list_of_users() {
+    let app = spawn_app().await;
+    let (_, editor, _) = create_editor(&app).await;
+    app.login_user().await;
+
+    let html_page = app.get_admin_users_html().await;
+
+    assert!(html_page.contains(&app.test_user.username));
+    assert!(html_page.contains(&editor));
+}
+
+#[tokio::test]
+async fn an_invited_user_can_create_an_account_and_log_in() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let token = invite(&app, "new.user@example.com", "editor").await;
+
+    let html_page = app.get_invitation_html(&token).await;
+    assert!(html_page.contains("new.user@example.com has been invited with the editor role"));
+
+    let username = Uuid::new_v4().to_string();
+    let password = Uuid::new_v4().to_string();
+    let response = app
+        .post_accept_invitation(&new_account(&token, &username, &password))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_login(&serde_json::json!({"username": username, "password": password}))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // Editors cannot manage users.
+    assert_eq!(app.get_admin_users().await.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn an_invitation_can_only_be_used_once() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = invite(&app, "new.user@example.com", "viewer").await;
+    let password = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_accept_invitation(&new_account(&token, &Uuid::new_v4().to_string(), &password))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_accept_invitation(&new_account(&token, &Uuid::new_v4().to_string(), &password))
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn an_expired_invitation_is_rejected() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = invite(&app, "new.user@example.com", "viewer").await;
+
+    sqlx::query!("UPDATE user_invitations SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(app.get_invitation(&token).await.status().as_u16(), 404);
+    let password = Uuid::new_v4().to_string();
+    let response = app
+        .post_accept_invitation(&new_account(&token, &Uuid::new_v4().to_string(), &password))
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn invitees_must_choose_a_valid_password() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = invite(&app, "new.user@example.com", "viewer").await;
+
+    let response = app
+        .post_accept_invitation(&new_account(&token, "new-user", "too short"))
+        .await;
+
+    let form_url = format!("/invitations/accept?invitation_token={}", token);
+    assert_is_redirect_to(&response, &form_url);
+    let html_page = app.get_invitation_html(&token).await;
+    assert!(html_page.contains("The new password must be longer than 12 characters"));
+}
+
+#[tokio::test]
+async fn an_existing_user_email_cannot_be_invited_again() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = invite(&app, "new.user@example.com", "viewer").await;
+    let password = Uuid::new_v4().to_string();
+    app.post_accept_invitation(&new_account(&token, "new-user", &password))
+        .await;
+
+    let response = app
+        .post_invite_user(&serde_json::json!({
+            "email": "new.user@example.com",
+            "role": "viewer"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    let html_page = app.get_admin_users_html().await;
+    assert!(html_page.contains("A user with this email address already exists."));
+}
+
+#[tokio::test]
+async fn invalid_invitations_are_not_echoed_back() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    for (email, role, error_message) in [
+        (
+            "<script>alert(1)</script>",
+            "viewer",
+            "Please enter a valid email address.",
+        ),
+        (
+            "new.user@example.com",
+            "<script>alert(1)</script>",
+            "Please choose a valid role.",
+        ),
+    ] {
+        let response = app
+            .post_invite_user(&serde_json::json!({"email": email, "role": role}))
+            .await;
+        assert_is_redirect_to(&response, "/admin/users");
+
+        let html_page = app.get_admin_users_html().await;
+        assert!(html_page.contains(error_message));
+        assert!(!html_page.contains("<script>"));
+    }
+}
+
+#[tokio::test]
+async fn taken_usernames_are_not_echoed_back() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let username = "<script>alert(1)</script>";
+    create_user(
+        username,
+        None,
+        AdminPassword::new(Uuid::new_v4().to_string()).unwrap(),
+        Role::Viewer,
+        &app.db_pool,
+    )
+    .await
+    .unwrap();
+    let token = invite(&app, "new.user@example.com", "viewer").await;
+
+    let password = Uuid::new_v4().to_string();
+    app.post_accept_invitation(&new_account(&token, username, &password))
+        .await;
+
+    let html_page = app.get_invitation_html(&token).await;
+    assert!(html_page.contains("This username is already taken."));
+    assert!(!html_page.contains("<script>"));
+}
+
+#[tokio::test]
+async fn invitations_are_not_sent_when_they_cannot_be_stored() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    // Sabotage the audit log, the invitation must be rolled back with it.
+    sqlx::query!("ALTER TABLE audit_log DROP COLUMN user_agent")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_invite_user(&serde_json::json!({
+            "email": "new.user@example.com",
+            "role": "viewer"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 500);
+
+    let n_invitations = sqlx::query!(r#"SELECT count(*) AS "count!" FROM user_invitations"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_invitations, 0);
+}
+
+#[tokio::test]
+async fn a_deactivated_user_cannot_log_in() {
+    let app = spawn_app().await;
+    let (user_id, username, password) = create_editor(&app).await;
+    app.login_user().await;
+
+    let response = app.post_user_action(user_id, "deactivate").await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    let response = app
+        .post_login(&serde_json::json!({"username": username, "password": password}))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    app.login_user().await;
+    app.post_user_action(user_id, "reactivate").await;
+    let response = app
+        .post_login(&serde_json::json!({"username": username, "password": password}))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn deactivating_a_user_ends_their_session() {
+    let app = spawn_app().await;
+    create_user_with_role(&app, Role::Owner).await;
+    app.login_user().await;
+
+    set_user_active(app.test_user.user_id, false, &app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_last_active_owner_cannot_be_deactivated() {
+    let app = spawn_app().await;
+    create_editor(&app).await;
+
+    let result = set_user_active(app.test_user.user_id, false, &app.db_pool).await;
+
+    assert!(matches!(result, Err(UpdateUserError::LastActiveOwner)));
+}
+
+#[tokio::test]
+async fn a_deleted_user_cannot_log_in() {
+    let app = spawn_app().await;
+    let (user_id, username, password) = create_editor(&app).await;
+    app.login_user().await;
+
+    let response = app.post_user_action(user_id, "delete").await;
+    assert_is_redirect_to(&response, "/admin/users");
+    assert!(
+        app.get_admin_users_html()
+            .await
+            .contains("The user has been deleted.")
+    );
+
+    let response = app
+        .post_login(&serde_json::json!({"username": username, "password": password}))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn owners_cannot_remove_themselves() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    for (action, message) in [
+        ("deactivate", "You cannot deactivate your own account."),
+        ("delete", "You cannot delete your own account."),
+    ] {
+        let response = app.post_user_action(app.test_user.user_id, action).await;
+        assert_is_redirect_to(&response, "/admin/users");
+        assert!(app.get_admin_users_html().await.contains(message));
+    }
+
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+}
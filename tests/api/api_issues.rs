@@ -0,0 +1,328 @@
+use reqwest::Method;
+use uuid::Uuid;
+use wiremock::Mock;
+use wiremock::matchers::{method, path};
+
+use crate::helpers::{TestApp, batch_accepted_response, create_confirmed_subscriber, spawn_app};
+
+fn issue_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })
+}
+
+async fn create_draft(app: &TestApp, token: &str) -> serde_json::Value {
+    let response = app
+        .api_request(Method::POST, "/issues", Some(token))
+        .json(&issue_body())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    response.json().await.unwrap()
+}
+
+async fn n_queued_tasks(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+}
+
+#[tokio::test]
+async fn new_issues_are_drafts_and_are_not_delivered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:write"]).await;
+
+    let response = app
+        .api_request(Method::POST, "/issues", Some(&token))
+        .json(&issue_body())
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 201);
+    let location = response.headers()["Location"].to_str().unwrap().to_string();
+    let issue: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        location,
+        format!("/api/v1/issues/{}", issue["issue_id"].as_str().unwrap())
+    );
+    assert_eq!(issue["status"], "draft");
+    assert_eq!(issue["title"], "Newsletter title");
+    assert!(issue["published_at"].is_null());
+    assert_eq!(n_queued_tasks(&app).await, 0);
+}
+
+#[tokio::test]
+async fn drafts_can_be_updated_until_they_are_published() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:write"]).await;
+    let issue = create_draft(&app, &token).await;
+    let issue_path = format!("/issues/{}", issue["issue_id"].as_str().unwrap());
+
+    let response = app
+        .api_request(Method::PATCH, &issue_path, Some(&token))
+        .json(&serde_json::json!({"title": "A better title"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["title"], "A better title");
+    assert_eq!(updated["text_content"], issue["text_content"]);
+
+    let response = app
+        .api_request(
+            Method::POST,
+            &format!("{}/publish", issue_path),
+            Some(&token),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 202);
+
+    let response = app
+        .api_request(Method::PATCH, &issue_path, Some(&token))
+        .json(&serde_json::json!({"title": "Too late"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn published_issues_are_delivered_and_report_their_progress() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:read", "issues:write"]).await;
+    let issue = create_draft(&app, &token).await;
+    let issue_path = format!("/issues/{}", issue["issue_id"].as_str().unwrap());
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted_response(1))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .api_request(
+            Method::POST,
+            &format!("{}/publish", issue_path),
+            Some(&token),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 202);
+    let published: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(published["status"], "published");
+
+    let delivery_path = format!("{}/delivery", issue_path);
+    let status: serde_json::Value = app
+        .api_request(Method::GET, &delivery_path, Some(&token))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["recipients"], 1);
+    assert_eq!(status["pending"], 1);
+
+    app.dispatch_all_pending_emails().await;
+
+    let status: serde_json::Value = app
+        .api_request(Method::GET, &delivery_path, Some(&token))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["delivered"], 1);
+    assert_eq!(status["failed"], 0);
+    assert_eq!(status["pending"], 0);
+}
+
+#[tokio::test]
+async fn issues_cannot_be_published_twice() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:write"]).await;
+    let issue = create_draft(&app, &token).await;
+    let publish_path = format!("/issues/{}/publish", issue["issue_id"].as_str().unwrap());
+
+    let response = app
+        .api_request(Method::POST, &publish_path, Some(&token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 202);
+
+    let response = app
+        .api_request(Method::POST, &publish_path, Some(&token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(n_queued_tasks(&app).await, 1);
+}
+
+#[tokio::test]
+async fn requests_with_the_same_idempotency_key_create_a_single_issue() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:write"]).await;
+    let idempotency_key = Uuid::new_v4().to_string();
+
+    let mut issue_ids = Vec::new();
+    for _ in 0..2 {
+        let response = app
+            .api_request(Method::POST, "/issues", Some(&token))
+            .header("Idempotency-Key", &idempotency_key)
+            .json(&issue_body())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+        let issue: serde_json::Value = response.json().await.unwrap();
+        issue_ids.push(issue["issue_id"].clone());
+    }
+
+    assert_eq!(issue_ids[0], issue_ids[1]);
+    let n_issues = sqlx::query!(r#"SELECT count(*) AS "count!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_issues, 1);
+}
+
+#[tokio::test]
+async fn invalid_issues_are_rejected_with_a_json_error() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:write"]).await;
+
+    let test_cases = vec![
+        (
+            serde_json::json!({
+                "title": "",
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+            }),
+            "empty title",
+        ),
+        (
+            serde_json::json!({"title": "Newsletter!"}),
+            "missing content",
+        ),
+    ];
+
+    for (body, error_message) in test_cases {
+        let response = app
+            .api_request(Method::POST, "/issues", Some(&token))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload had an {}.",
+            error_message
+        );
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["error"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn issues_can_be_listed_and_retrieved() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:read", "issues:write"]).await;
+    let issue = create_draft(&app, &token).await;
+
+    let list: serde_json::Value = app
+        .api_request(Method::GET, "/issues", Some(&token))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(list["issues"][0]["issue_id"], issue["issue_id"]);
+    assert_eq!(list["issues"][0]["status"], "draft");
+
+    let issue_path = format!("/issues/{}", issue["issue_id"].as_str().unwrap());
+    let retrieved: serde_json::Value = app
+        .api_request(Method::GET, &issue_path, Some(&token))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(retrieved["html_content"], "<p>Newsletter body as HTML</p>");
+
+    let response = app
+        .api_request(
+            Method::GET,
+            &format!("/issues/{}", Uuid::new_v4()),
+            Some(&token),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn tokens_need_the_matching_scope() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:read"]).await;
+
+    let response = app
+        .api_request(Method::POST, "/issues", Some(&token))
+        .json(&issue_body())
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn editors_can_draft_but_not_publish_issues() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:write"]).await;
+    app.set_test_user_role("editor").await;
+    let issue = create_draft(&app, &token).await;
+
+    let response = app
+        .api_request(
+            Method::POST,
+            &format!("/issues/{}/publish", issue["issue_id"].as_str().unwrap()),
+            Some(&token),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 403);
+}
@@ -0,0 +1,345 @@
+use reqwest::Method;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    TestApp, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+};
+
+async fn post_subscriber(app: &TestApp, token: &str, body: serde_json::Value) -> reqwest::Response {
+    app.api_request(Method::POST, "/subscribers", Some(token))
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn get_json(app: &TestApp, token: &str, path: &str) -> serde_json::Value {
+    let response = app
+        .api_request(Method::GET, path, Some(token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn subscribers_can_be_added_without_double_opt_in() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app
+        .create_api_token(&["subscribers:read", "subscribers:write"])
+        .await;
+
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = post_subscriber(
+        &app,
+        &token,
+        serde_json::json!({
+            "email": "ursula_le_guin@gmail.com",
+            "name": "le guin",
+            "skip_confirmation": true
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+    let subscriber: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(subscriber["status"], "confirmed");
+    assert_eq!(subscriber["email"], "ursula_le_guin@gmail.com");
+
+    let consent = sqlx::query!("SELECT event, source, ip_address FROM consent_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(consent.event, "signup");
+    assert_eq!(consent.source.as_deref(), Some("api"));
+    assert_eq!(consent.ip_address.as_deref(), Some("127.0.0.1"));
+}
+
+#[tokio::test]
+async fn subscribers_added_with_double_opt_in_receive_a_confirmation_email() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["subscribers:write"]).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = post_subscriber(
+        &app,
+        &token,
+        serde_json::json!({"email": "ursula_le_guin@gmail.com", "name": "le guin"}),
+    )
+    .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+    let subscriber: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(subscriber["status"], "pending_confirmation");
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.plain_text)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn invalid_or_duplicate_subscribers_are_rejected() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_user().await;
+    let token = app.create_api_token(&["subscribers:write"]).await;
+
+    let test_cases = vec![
+        (
+            serde_json::json!({"email": "not-an-email", "name": "le guin", "skip_confirmation": true}),
+            400,
+            "an invalid email",
+        ),
+        (
+            serde_json::json!({"email": "ursula@gmail.com", "name": "", "skip_confirmation": true}),
+            400,
+            "an empty name",
+        ),
+        (
+            serde_json::json!({"email": "diego20@gmail.com", "name": "diego", "skip_confirmation": true}),
+            409,
+            "an email already subscribed",
+        ),
+    ];
+
+    for (body, status, description) in test_cases {
+        let response = post_subscriber(&app, &token, body).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            status,
+            "The API did not fail with {} when the payload had {}.",
+            status,
+            description
+        );
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["error"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn subscribers_can_be_filtered() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    app.login_user().await;
+    let token = app
+        .create_api_token(&["subscribers:read", "subscribers:write"])
+        .await;
+    for (email, name) in [
+        ("ursula@gmail.com", "ursula"),
+        ("octavia@gmail.com", "octavia"),
+    ] {
+        let response = post_subscriber(
+            &app,
+            &token,
+            serde_json::json!({"email": email, "name": name, "skip_confirmation": true}),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 201);
+    }
+
+    let all = get_json(&app, &token, "/subscribers").await;
+    assert_eq!(all["subscribers"].as_array().unwrap().len(), 3);
+
+    let confirmed = get_json(&app, &token, "/subscribers?status=confirmed").await;
+    assert_eq!(confirmed["subscribers"].as_array().unwrap().len(), 2);
+
+    let searched = get_json(&app, &token, "/subscribers?search=URSULA").await;
+    assert_eq!(searched["subscribers"].as_array().unwrap().len(), 1);
+    assert_eq!(searched["subscribers"][0]["email"], "ursula@gmail.com");
+
+    let future = get_json(
+        &app,
+        &token,
+        "/subscribers?subscribed_after=2999-01-01T00:00:00Z",
+    )
+    .await;
+    assert!(future["subscribers"].as_array().unwrap().is_empty());
+
+    let paged = get_json(&app, &token, "/subscribers?limit=1&offset=1").await;
+    assert_eq!(paged["subscribers"].as_array().unwrap().len(), 1);
+
+    let response = app
+        .api_request(Method::GET, "/subscribers?status=sleeping", Some(&token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_updated_and_deleted() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app
+        .create_api_token(&["subscribers:read", "subscribers:write"])
+        .await;
+    let subscriber: serde_json::Value = post_subscriber(
+        &app,
+        &token,
+        serde_json::json!({"email": "ursula@gmail.com", "name": "ursula", "skip_confirmation": true}),
+    )
+    .await
+    .json()
+    .await
+    .unwrap();
+    let subscriber_path = format!(
+        "/subscribers/{}",
+        subscriber["subscriber_id"].as_str().unwrap()
+    );
+
+    let response = app
+        .api_request(Method::PATCH, &subscriber_path, Some(&token))
+        .json(&serde_json::json!({"name": "Ursula K. Le Guin", "status": "unsubscribed"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let updated = get_json(&app, &token, &subscriber_path).await;
+    assert_eq!(updated["name"], "Ursula K. Le Guin");
+    assert_eq!(updated["status"], "unsubscribed");
+    assert_eq!(updated["do_not_track"], false);
+
+    let response = app
+        .api_request(Method::PATCH, &subscriber_path, Some(&token))
+        .json(&serde_json::json!({"do_not_track": true}))
+        .send()
+        .await
+        .unwrap();
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["do_not_track"], true);
+    assert_eq!(updated["name"], "Ursula K. Le Guin");
+
+    let response = app
+        .api_request(Method::PATCH, &subscriber_path, Some(&token))
+        .json(&serde_json::json!({"name": "<script>"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .api_request(Method::DELETE, &subscriber_path, Some(&token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app
+        .api_request(Method::GET, &subscriber_path, Some(&token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn unconfirmed_subscribers_can_be_deleted() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    app.login_user().await;
+    let token = app
+        .create_api_token(&["subscribers:read", "subscribers:write"])
+        .await;
+    let list = get_json(&app, &token, "/subscribers").await;
+    let subscriber_id = list["subscribers"][0]["subscriber_id"].as_str().unwrap();
+
+    let response = app
+        .api_request(
+            Method::DELETE,
+            &format!("/subscribers/{}", subscriber_id),
+            Some(&token),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 204);
+}
+
+#[tokio::test]
+async fn only_owners_can_change_subscribers() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app
+        .create_api_token(&["subscribers:read", "subscribers:write"])
+        .await;
+    app.set_test_user_role("editor").await;
+
+    let response = post_subscriber(
+        &app,
+        &token,
+        serde_json::json!({"email": "ursula@gmail.com", "name": "ursula", "skip_confirmation": true}),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .api_request(
+            Method::DELETE,
+            &format!("/subscribers/{}", Uuid::new_v4()),
+            Some(&token),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+
+    get_json(&app, &token, "/subscribers").await;
+}
+
+#[tokio::test]
+async fn tokens_without_subscriber_scopes_cannot_list_subscribers() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:read"]).await;
+
+    let response = app
+        .api_request(Method::GET, "/subscribers", Some(&token))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn viewers_cannot_read_subscribers() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["subscribers:read"]).await;
+    app.set_test_user_role("viewer").await;
+
+    for path in [
+        "/subscribers".to_string(),
+        format!("/subscribers/{}", Uuid::new_v4()),
+    ] {
+        let response = app
+            .api_request(Method::GET, &path, Some(&token))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 403);
+    }
+}
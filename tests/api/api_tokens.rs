@@ -0,0 +1,169 @@
+use reqwest::Method;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn api_requests_without_credentials_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_request(Method::GET, "/me", None)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(response.headers()["WWW-Authenticate"], "Bearer");
+}
+
+#[tokio::test]
+async fn an_unknown_token_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_request(Method::GET, "/me", Some("nlt_not-a-real-token"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn a_token_identifies_its_user_and_scopes() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:write"]).await;
+
+    let response = app
+        .api_request(Method::GET, "/me", Some(&token))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["username"], app.test_user.username);
+    assert_eq!(body["role"], "owner");
+    assert_eq!(body["scopes"], serde_json::json!(["issues:write"]));
+}
+
+#[tokio::test]
+async fn tokens_are_stored_hashed_and_record_their_last_use() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:read"]).await;
+
+    app.api_request(Method::GET, "/me", Some(&token))
+        .send()
+        .await
+        .unwrap();
+
+    let row = sqlx::query!("SELECT token_hash, last_used_at FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_ne!(row.token_hash, token);
+    assert!(row.last_used_at.is_some());
+}
+
+#[tokio::test]
+async fn an_expired_token_is_rejected() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:read"]).await;
+
+    sqlx::query!("UPDATE api_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .api_request(Method::GET, "/me", Some(&token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn a_revoked_token_is_rejected() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:read"]).await;
+    let api_token_id = sqlx::query!("SELECT api_token_id FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .api_token_id;
+
+    let response = app.post_revoke_api_token(api_token_id).await;
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+    assert!(
+        app.get_api_tokens_html()
+            .await
+            .contains("The token has been revoked.")
+    );
+
+    let response = app
+        .api_request(Method::GET, "/me", Some(&token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn tokens_of_deactivated_users_are_rejected() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:read"]).await;
+
+    sqlx::query!("UPDATE users SET is_active = false")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .api_request(Method::GET, "/me", Some(&token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn a_token_requires_at_least_one_scope() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let response = app
+        .post_create_api_token(&serde_json::json!({"name": "test", "expires_in_days": ""}))
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+    assert!(
+        app.get_api_tokens_html()
+            .await
+            .contains("Select at least one scope.")
+    );
+}
+
+#[tokio::test]
+async fn tokens_do_not_give_access_to_the_admin_pages() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    let token = app.create_api_token(&["issues:write"]).await;
+
+    let response = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get(format!("{}/admin/dashboard", app.address))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/login");
+}
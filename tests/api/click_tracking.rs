@@ -0,0 +1,269 @@
+use newsletter_backend::tracking::{TrackedLink, tracked_links};
+use reqwest::Method;
+use uuid::Uuid;
+use wiremock::matchers::path;
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+const ISSUE_HTML: &str = r#"<p>
+    Read <a href="https://example.com/a">the first article</a>,
+    <a href="https://example.com/b?x=1&amp;y=2">the second one</a>
+    or <a href="mailto:editor@example.com">write to us</a>.
+</p>"#;
+
+/// Publishes an issue to the confirmed subscriber and returns its id with the HTML
+/// content the subscriber received.
+async fn deliver_issue(app: &TestApp, no_click_tracking: bool) -> (Uuid, String) {
+    let mut body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": ISSUE_HTML,
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+    if no_click_tracking {
+        body["no_click_tracking"] = "true".into();
+    }
+    let html_body = app.deliver_issue(body).await;
+
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+
+    (issue_id, html_body)
+}
+
+async fn subscriber_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id
+}
+
+async fn spawn_app_with_subscriber() -> TestApp {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_user().await;
+    app
+}
+
+#[tokio::test]
+async fn links_of_delivered_issues_are_replaced_by_tracking_redirects() {
+    let app = spawn_app_with_subscriber().await;
+
+    let (_, html_body) = deliver_issue(&app, false).await;
+
+    let links = tracked_links(&html_body);
+    assert_eq!(links.len(), 2);
+    for link in links {
+        assert!(link.starts_with(&format!("{}/t/c/", app.address)));
+    }
+    assert!(!html_body.contains("https://example.com/a"));
+    assert!(html_body.contains(r#"<a href="mailto:editor@example.com">"#));
+}
+
+#[tokio::test]
+async fn following_a_tracked_link_records_the_click_and_redirects_to_the_link() {
+    let app = spawn_app_with_subscriber().await;
+    let (issue_id, html_body) = deliver_issue(&app, false).await;
+    let links = tracked_links(&html_body);
+
+    let response = app.get_tracking_url(&links[1]).await;
+
+    assert_eq!(response.status().as_u16(), 302);
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        "https://example.com/b?x=1&y=2"
+    );
+    let click = sqlx::query!("SELECT newsletter_issue_id, subscriber_id, url FROM link_clicks")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(click.newsletter_issue_id, issue_id);
+    assert_eq!(click.subscriber_id, subscriber_id(&app).await);
+    assert_eq!(click.url, "https://example.com/b?x=1&y=2");
+}
+
+#[tokio::test]
+async fn forged_or_unknown_tracking_links_are_rejected() {
+    let app = spawn_app_with_subscriber().await;
+    let (_, html_body) = deliver_issue(&app, false).await;
+    let link = &tracked_links(&html_body)[0];
+
+    let tampered = format!("{}x", link);
+    let unknown_issue = app.tracker.click_url(&TrackedLink {
+        newsletter_issue_id: Uuid::new_v4(),
+        subscriber_id: subscriber_id(&app).await,
+        url: "https://example.com/a".into(),
+    });
+
+    for url in [
+        tampered,
+        format!("{}/t/c/not-a-token", app.address),
+        unknown_issue,
+    ] {
+        let response = app.get_tracking_url(&url).await;
+        assert_eq!(response.status().as_u16(), 404, "{}", url);
+    }
+    let n_clicks = sqlx::query!(r#"SELECT count(*) AS "count!" FROM link_clicks"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_clicks, 0);
+}
+
+#[tokio::test]
+async fn editing_an_issue_does_not_change_where_sent_links_lead() {
+    let app = spawn_app_with_subscriber().await;
+    let token = app.create_api_token(&["issues:write"]).await;
+    let response = app
+        .api_request(Method::POST, "/issues", Some(&token))
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": ISSUE_HTML,
+        }))
+        .send()
+        .await
+        .unwrap();
+    let issue: serde_json::Value = response.json().await.unwrap();
+    let issue_id: Uuid = issue["issue_id"].as_str().unwrap().parse().unwrap();
+    // As sent before the draft was changed, for instance as a preview.
+    let click_url = app.tracker.click_url(&TrackedLink {
+        newsletter_issue_id: issue_id,
+        subscriber_id: subscriber_id(&app).await,
+        url: "https://example.com/a".into(),
+    });
+
+    let response = app
+        .api_request(
+            Method::PATCH,
+            &format!("/issues/{}", issue_id),
+            Some(&token),
+        )
+        .json(&serde_json::json!({
+            "html_content": r#"<a href="https://attacker.example.com">the first article</a>"#,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.get_tracking_url(&click_url).await;
+
+    assert_eq!(response.status().as_u16(), 302);
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        "https://example.com/a"
+    );
+}
+
+#[tokio::test]
+async fn links_still_redirect_once_the_subscriber_is_deleted() {
+    let app = spawn_app_with_subscriber().await;
+    let (_, html_body) = deliver_issue(&app, false).await;
+    let link = &tracked_links(&html_body)[0];
+    let subscriber_id = subscriber_id(&app).await;
+    app.post_subscriber_action(subscriber_id, "delete").await;
+
+    let response = app.get_tracking_url(link).await;
+
+    assert_eq!(response.status().as_u16(), 302);
+    let n_clicks = sqlx::query!(r#"SELECT count(*) AS "count!" FROM link_clicks"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_clicks, 0);
+}
+
+#[tokio::test]
+async fn issues_can_opt_out_of_click_tracking() {
+    let app = spawn_app_with_subscriber().await;
+
+    let (issue_id, html_body) = deliver_issue(&app, true).await;
+
+    assert_eq!(html_body, ISSUE_HTML);
+    let html_page = app.get_issue_stats_html(issue_id).await;
+    assert!(html_page.contains("Clicks are not tracked for this issue."));
+}
+
+#[tokio::test]
+async fn the_issue_stats_page_aggregates_clicks_per_link() {
+    let app = spawn_app_with_subscriber().await;
+    let (issue_id, html_body) = deliver_issue(&app, false).await;
+    let links = tracked_links(&html_body);
+
+    app.get_tracking_url(&links[0]).await;
+    app.get_tracking_url(&links[1]).await;
+    app.get_tracking_url(&links[1]).await;
+
+    let html_page: String = app
+        .get_issue_stats_html(issue_id)
+        .await
+        .split_whitespace()
+        .collect();
+    let b = html_page
+        .find("<td>https://example.com/b?x=1&amp;y=2</td><td>2</td><td>1</td>")
+        .unwrap();
+    let a = html_page
+        .find("<td>https://example.com/a</td><td>1</td><td>1</td>")
+        .unwrap();
+    assert!(b < a, "The most clicked link should come first");
+    assert!(html_page.contains("<p>Delivered:1</p>"));
+}
+
+#[tokio::test]
+async fn viewers_can_see_issue_stats() {
+    let app = spawn_app_with_subscriber().await;
+    let (issue_id, _) = deliver_issue(&app, false).await;
+    app.set_test_user_role("viewer").await;
+
+    let html_page = app.get_issue_stats_html(issue_id).await;
+    assert!(html_page.contains("Newsletter title"));
+
+    let response = app.get_issue_stats(Uuid::new_v4()).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_issue_stats() {
+    let app = spawn_app().await;
+
+    let response = app.get_issue_stats(Uuid::new_v4()).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn clicks_are_part_of_the_personal_data_of_the_subscriber() {
+    let app = spawn_app_with_subscriber().await;
+    let (issue_id, html_body) = deliver_issue(&app, false).await;
+    app.get_tracking_url(&tracked_links(&html_body)[0]).await;
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_personal_data_action("export", "diego20@gmail.com")
+        .await;
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        data["link_clicks"][0]["newsletter_issue_id"],
+        issue_id.to_string()
+    );
+    assert_eq!(data["link_clicks"][0]["url"], "https://example.com/a");
+
+    app.post_personal_data_action("erase", "diego20@gmail.com")
+        .await;
+    let n_clicks = sqlx::query!(r#"SELECT count(*) AS "count!" FROM link_clicks"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_clicks, 0);
+}
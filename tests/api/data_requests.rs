@@ -0,0 +1,157 @@
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, create_confirmed_subscriber, queue_delivery, spawn_app,
+};
+
+const EMAIL: &str = "diego20@gmail.com";
+const SENT_MESSAGE: &str =
+    "If this address is subscribed, a link to complete your request has been sent to it.";
+
+/// Makes a request for the subscriber and returns the token sent to them.
+async fn request_token(app: &TestApp, kind: &str) -> String {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let n_emails = app.email_server.received_requests().await.unwrap().len();
+    let response = app.post_data_request(EMAIL, kind).await;
+    assert_is_redirect_to(&response, "/subscriptions/personal-data");
+
+    let email_request = app.wait_for_emails(n_emails + 1).await.pop().unwrap();
+    app.get_confirmation_links(&email_request)
+        .html
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .into_owned()
+}
+
+#[tokio::test]
+async fn subscribers_can_download_their_data_through_an_emailed_link() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    queue_delivery(&app, EMAIL).await;
+    let token = request_token(&app, "access").await;
+
+    let html = app.get_data_request_html().await;
+    assert!(html.contains(SENT_MESSAGE));
+
+    // Opening the link does not use it up.
+    let response = app.get_data_request_confirmation(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Download my data"));
+
+    let response = app.post_data_request_confirmation(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["email"], EMAIL);
+    assert_eq!(data["subscription"]["status"], "confirmed");
+    assert_eq!(data["pending_deliveries"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn subscribers_can_erase_their_data_through_an_emailed_link() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    queue_delivery(&app, EMAIL).await;
+    let token = request_token(&app, "erasure").await;
+
+    let response = app.post_data_request_confirmation(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("Your data has been deleted.")
+    );
+    let remaining = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT count(*) FROM subscriptions)
+            + (SELECT count(*) FROM subscription_tokens)
+            + (SELECT count(*) FROM issue_delivery_queue)
+            + (SELECT count(*) FROM data_request_tokens)
+            AS "count!"
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count;
+    assert_eq!(remaining, 0);
+}
+
+#[tokio::test]
+async fn data_request_links_can_only_be_used_once() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = request_token(&app, "access").await;
+
+    let response = app.post_data_request_confirmation(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_data_request_confirmation(&token).await;
+    assert_eq!(response.status().as_u16(), 404);
+    let response = app.get_data_request_confirmation(&token).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn no_link_is_sent_to_unsubscribed_addresses() {
+    let app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_data_request("nobody@example.com", "access").await;
+
+    assert_is_redirect_to(&response, "/subscriptions/personal-data");
+    let html = app.get_data_request_html().await;
+    assert!(html.contains(SENT_MESSAGE));
+}
+
+#[tokio::test]
+async fn unknown_data_request_tokens_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app.get_data_request_confirmation("not-a-token").await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.post_data_request_confirmation("not-a-token").await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn invalid_data_requests_are_not_echoed_back() {
+    let app = spawn_app().await;
+
+    for (email, kind, error_message) in [
+        (
+            "<script>alert(1)</script>",
+            "access",
+            "Please enter a valid email address.",
+        ),
+        (
+            EMAIL,
+            "<script>alert(1)</script>",
+            "Please choose whether to download or delete your data.",
+        ),
+    ] {
+        let response = app.post_data_request(email, kind).await;
+
+        assert_is_redirect_to(&response, "/subscriptions/personal-data");
+        let html = app.get_data_request_html().await;
+        assert!(html.contains(error_message));
+        assert!(!html.contains("<script>"));
+    }
+}
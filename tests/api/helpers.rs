@@ -1,23 +1,37 @@
 use argon2::password_hash::SaltString;
 use argon2::{Argon2, Params, PasswordHasher};
-use newsletter_backend::configuration::{DatabaseSettings, get_configuration};
+use newsletter_backend::configuration::{
+    DatabaseSettings, Settings, WorkerSettings, get_configuration,
+};
 use newsletter_backend::email_client::EmailClient;
-use newsletter_backend::issue_delivery_worker::{ExecutionOutcome, try_execute_task};
+use newsletter_backend::issue_delivery_worker::{
+    ExecutionOutcome, run_worker_until_stopped, try_execute_task,
+};
 use newsletter_backend::startup::{Application, get_connection_pool};
 use newsletter_backend::telemetry::{get_opentelemetry_parts, get_subscriber, init_subscriber};
+use newsletter_backend::tracking::Tracker;
 use once_cell::sync::Lazy;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
-use wiremock::{MockServer, Request};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
 
 pub struct TestApp {
     pub address: String,
     pub port: u16,
     pub db_pool: PgPool,
     http_client: reqwest::Client,
+    /// Doesn't keep cookies, so requests are only authenticated by their token.
+    api_client: reqwest::Client,
     pub email_server: MockServer,
     pub test_user: TestUser,
     pub email_client: EmailClient,
+    pub worker_settings: WorkerSettings,
+    /// Signs tracking links pointing to the test app.
+    pub tracker: Tracker,
+    configuration: Settings,
 }
 
 impl TestApp {
@@ -48,8 +62,8 @@ impl TestApp {
             confirmation_link
         };
 
-        let html_link = get_link(&body["HtmlBody"].as_str().unwrap());
-        let plain_text_link = get_link(&body["TextBody"].as_str().unwrap());
+        let html_link = get_link(body["HtmlBody"].as_str().unwrap());
+        let plain_text_link = get_link(body["TextBody"].as_str().unwrap());
 
         ConfirmationLinks {
             html: html_link,
@@ -59,7 +73,7 @@ impl TestApp {
 
     pub async fn post_send_issue(&self, body: serde_json::Value) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/admin/newsletters", &self.address))
+            .post(format!("{}/admin/newsletters", &self.address))
             .form(&body)
             .send()
             .await
@@ -71,7 +85,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .form(body)
             .send()
             .await
@@ -80,7 +94,7 @@ impl TestApp {
 
     pub async fn get_login_html(&self) -> String {
         self.http_client
-            .get(&format!("{}/login", &self.address))
+            .get(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -89,9 +103,29 @@ impl TestApp {
             .unwrap()
     }
 
+    pub async fn get_setup(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/setup", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_setup<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/setup", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_admin_dashboard(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/admin/dashboard", &self.address))
+            .get(format!("{}/admin/dashboard", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -103,7 +137,7 @@ impl TestApp {
 
     pub async fn get_change_password(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/admin/password", self.address))
+            .get(format!("{}/admin/password", self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -118,7 +152,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/admin/password", &self.address))
+            .post(format!("{}/admin/password", &self.address))
             .form(body)
             .send()
             .await
@@ -127,7 +161,7 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/admin/logout", &self.address))
+            .post(format!("{}/admin/logout", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -135,7 +169,7 @@ impl TestApp {
 
     pub async fn get_send_issue(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/admin/newsletters", &self.address))
+            .get(format!("{}/admin/newsletters", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -145,6 +179,446 @@ impl TestApp {
         self.get_send_issue().await.text().await.unwrap()
     }
 
+    pub async fn get_admin_users(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_admin_users_html(&self) -> String {
+        self.get_admin_users().await.text().await.unwrap()
+    }
+
+    pub async fn post_invite_user<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/users/invitations", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    /// `action` is one of `deactivate`, `reactivate` or `delete`.
+    pub async fn post_user_action(&self, user_id: Uuid, action: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/admin/users/{}/{}",
+                &self.address, user_id, action
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_audit_log(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/audit", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_audit_log_html(&self, query: &[(&str, &str)]) -> String {
+        self.get_audit_log(query).await.text().await.unwrap()
+    }
+
+    /// `(action, target)` of every entry of the audit log, oldest first.
+    pub async fn audit_entries(&self) -> Vec<(String, Option<String>)> {
+        sqlx::query!("SELECT action, target FROM audit_log ORDER BY occurred_at, audit_log_id")
+            .fetch_all(&self.db_pool)
+            .await
+            .expect("Failed to fetch the audit log.")
+            .into_iter()
+            .map(|r| (r.action, r.target))
+            .collect()
+    }
+
+    pub async fn get_issue_stats(&self, issue_id: Uuid) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/issues/{}", &self.address, issue_id))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_issue_stats_html(&self, issue_id: Uuid) -> String {
+        self.get_issue_stats(issue_id).await.text().await.unwrap()
+    }
+
+    /// Loads a tracking URL of a delivered issue, without following redirects.
+    pub async fn get_tracking_url(&self, url: &str) -> reqwest::Response {
+        self.http_client
+            .get(url)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_admin_subscribers(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/subscribers", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_admin_subscribers_html(&self, query: &[(&str, &str)]) -> String {
+        self.get_admin_subscribers(query)
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_subscriber_details(&self, subscriber_id: Uuid) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/admin/subscribers/{}",
+                &self.address, subscriber_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    /// `action` is one of `resend-confirmation`, `unsubscribe` or `delete`.
+    pub async fn post_subscriber_action(
+        &self,
+        subscriber_id: Uuid,
+        action: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/admin/subscribers/{}/{}",
+                &self.address, subscriber_id, action
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_subscriber_export(&self, format: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/subscribers/export", &self.address))
+            .query(&[("format", format)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    /// `action` is either `export` or `erase`.
+    pub async fn post_personal_data_action(&self, action: &str, email: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/admin/subscribers/personal-data/{}",
+                &self.address, action
+            ))
+            .form(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_personal_data_html(&self) -> String {
+        self.http_client
+            .get(format!("{}/admin/subscribers/personal-data", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_data_request(&self, email: &str, kind: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/subscriptions/personal-data", &self.address))
+            .form(&[("email", email), ("kind", kind)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_data_request_html(&self) -> String {
+        self.http_client
+            .get(format!("{}/subscriptions/personal-data", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_data_request_confirmation(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/subscriptions/personal-data/confirm",
+                &self.address
+            ))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_data_request_confirmation(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/subscriptions/personal-data/confirm",
+                &self.address
+            ))
+            .form(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_subscriber_imports_html(&self) -> String {
+        self.http_client
+            .get(format!("{}/admin/subscribers/imports", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_import_rejections(&self, import_id: Uuid) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/admin/subscribers/imports/{}/rejections.csv",
+                &self.address, import_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    /// Uploads `csv` as a file, after the `mode` field if one is given.
+    pub async fn post_subscriber_import(&self, mode: Option<&str>, csv: &str) -> reqwest::Response {
+        const BOUNDARY: &str = "subscriber-import-boundary";
+
+        let mut body = String::new();
+        if let Some(mode) = mode {
+            body.push_str(&format!(
+                "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"mode\"\r\n\r\n{mode}\r\n"
+            ));
+        }
+        body.push_str(&format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"subscribers.csv\"\r\n\
+            Content-Type: text/csv\r\n\r\n{csv}\r\n--{BOUNDARY}--\r\n"
+        ));
+
+        self.http_client
+            .post(format!("{}/admin/subscribers/imports", &self.address))
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_invitation(&self, invitation_token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/invitations/accept", &self.address))
+            .query(&[("invitation_token", invitation_token)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_invitation_html(&self, invitation_token: &str) -> String {
+        self.get_invitation(invitation_token)
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_accept_invitation<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/invitations/accept", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_forgot_password(&self, username: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/forgot-password", &self.address))
+            .form(&[("username", username)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_reset_password(&self, reset_token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/reset-password", &self.address))
+            .query(&[("reset_token", reset_token)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/reset-password", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    /// Emails sent by background tasks may arrive after the response.
+    pub async fn wait_for_emails(&self, n_emails: usize) -> Vec<Request> {
+        for _ in 0..50 {
+            let requests = self.email_server.received_requests().await.unwrap();
+            if requests.len() >= n_emails {
+                return requests;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        panic!("Expected {} emails to be sent.", n_emails);
+    }
+
+    pub async fn get_two_factor_settings_html(&self) -> String {
+        self.http_client
+            .get(format!("{}/admin/two-factor", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_two_factor_enrollment(&self, code: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin/two-factor", &self.address))
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_disable_two_factor(&self, code: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin/two-factor/disable", &self.address))
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_second_factor(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/login/two-factor", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_second_factor(&self, code: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/login/two-factor", &self.address))
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_create_api_token<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/api-tokens", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_api_tokens_html(&self) -> String {
+        self.http_client
+            .get(format!("{}/admin/api-tokens", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_revoke_api_token(&self, api_token_id: Uuid) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/admin/api-tokens/{}/delete",
+                &self.address, api_token_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    /// Creates an API token for the logged-in user and returns it.
+    pub async fn create_api_token(&self, scopes: &[&str]) -> String {
+        let mut body = serde_json::json!({"name": "test", "expires_in_days": "30"});
+        for scope in scopes {
+            body[*scope] = "on".into();
+        }
+
+        let html_page = self
+            .post_create_api_token(&body)
+            .await
+            .text()
+            .await
+            .unwrap();
+        html_page
+            .split("<p><code>")
+            .nth(1)
+            .and_then(|s| s.split("</code>").next())
+            .expect("The page does not show the new token")
+            .to_string()
+    }
+
+    /// Request to the JSON API, authenticated with `token` when one is given.
+    pub fn api_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        token: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        let request = self
+            .api_client
+            .request(method, format!("{}/api/v1{}", &self.address, path));
+
+        match token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
     pub async fn login_user(&self) {
         let credentials = serde_json::json!({
             "username": self.test_user.username,
@@ -154,14 +628,69 @@ impl TestApp {
         self.post_login(&credentials).await;
     }
 
+    pub async fn set_test_user_role(&self, role: &str) {
+        sqlx::query!(
+            "UPDATE users SET role = $1 WHERE user_id = $2",
+            role,
+            self.test_user.user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .expect("Failed to update the test user role.");
+    }
+
+    /// Publishes an issue through the admin form and delivers it to a single subscriber.
+    /// Returns the HTML content they received.
+    pub async fn deliver_issue(&self, form: serde_json::Value) -> String {
+        let _mock_guard = Mock::given(path("/email/batch"))
+            .and(method("POST"))
+            .respond_with(batch_accepted_response(1))
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+
+        let response = self.post_send_issue(form).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        self.dispatch_all_pending_emails().await;
+
+        let batch = self
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .rfind(|request| request.url.path() == "/email/batch")
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&batch.body).unwrap();
+        body[0]["HtmlBody"].as_str().unwrap().to_string()
+    }
+
+    /// Runs the delivery worker, with settings changed by `configure`, until `shutdown`
+    /// is cancelled.
+    pub fn spawn_worker(
+        &self,
+        configure: impl FnOnce(&mut Settings),
+        shutdown: CancellationToken,
+    ) -> JoinHandle<Result<(), anyhow::Error>> {
+        let mut configuration = self.configuration.clone();
+        configure(&mut configuration);
+        tokio::spawn(run_worker_until_stopped(configuration, shutdown))
+    }
+
     pub async fn dispatch_all_pending_emails(&self) {
         loop {
-            if let ExecutionOutcome::EmptyQueue =
-                try_execute_task(&self.db_pool, &self.email_client)
-                    .await
-                    .unwrap()
+            match try_execute_task(
+                &self.db_pool,
+                &self.email_client,
+                &self.worker_settings,
+                &self.tracker,
+            )
+            .await
+            .unwrap()
             {
-                break;
+                ExecutionOutcome::EmptyQueue => break,
+                ExecutionOutcome::Throttled(retry_after) => tokio::time::sleep(retry_after).await,
+                ExecutionOutcome::TaskCompleted => {}
             }
         }
     }
@@ -195,7 +724,7 @@ impl TestUser {
         .to_string();
 
         sqlx::query!(
-            "INSERT INTO users(user_id, username, password_hash) VALUES ($1, $2, $3)",
+            "INSERT INTO users(user_id, username, password_hash, role) VALUES ($1, $2, $3, 'owner')",
             self.user_id,
             self.username,
             password_hash,
@@ -228,6 +757,11 @@ static TRACING: Lazy<()> = Lazy::new(|| {
 });
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawns the app with settings changed by `configure`.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     let email_server = MockServer::start().await;
@@ -239,6 +773,7 @@ pub async fn spawn_app() -> TestApp {
         c.application.port = 0;
 
         c.email_client.base_url = email_server.uri();
+        configure(&mut c);
 
         c
     };
@@ -251,7 +786,7 @@ pub async fn spawn_app() -> TestApp {
     let port = server.port();
     let address = format!("http://127.0.0.1:{}", port);
 
-    let _ = tokio::spawn(server.run_until_stopped());
+    tokio::spawn(server.run_until_stopped(CancellationToken::new()));
 
     let http_client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::none())
@@ -259,14 +794,23 @@ pub async fn spawn_app() -> TestApp {
         .build()
         .unwrap();
 
+    let tracker = Tracker::new(
+        address.clone(),
+        configuration.application.hmac_secret.clone(),
+        configuration.application.track_opens,
+    );
     let test_app = TestApp {
         address,
         db_pool: get_connection_pool(configuration.database.connection_string()),
         http_client,
+        api_client: reqwest::Client::new(),
         email_server,
         port,
         test_user: TestUser::generate(),
-        email_client: configuration.email_client.client(),
+        email_client: configuration.email_client.clone().client(),
+        worker_settings: configuration.worker.clone(),
+        tracker,
+        configuration,
     };
 
     test_app.test_user.store(&test_app.db_pool).await;
@@ -296,6 +840,69 @@ async fn configure_database(configuration: &mut DatabaseSettings) {
         .expect("Failed to migrate database");
 }
 
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+    let body = "name=diego&email=diego20@gmail.com";
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.post_subscription(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+
+    app.get_confirmation_links(email_request)
+}
+
+pub async fn create_confirmed_subscriber(app: &TestApp) {
+    let confirmation_link = create_unconfirmed_subscriber(app).await;
+
+    reqwest::get(confirmation_link.plain_text)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+/// Queues a delivery of a new issue to `email`, as if it had just been published.
+pub async fn queue_delivery(app: &TestApp, email: &str) -> Uuid {
+    let newsletter_issue_id = Uuid::new_v4();
+    sqlx::query!(
+        "INSERT INTO newsletter_issues
+            (newsletter_issue_id, title, text_content, html_content, published_at)
+        VALUES ($1, 'Issue', 'text', '<p>html</p>', now())",
+        newsletter_issue_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        "INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email) VALUES ($1, $2)",
+        newsletter_issue_id,
+        email
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    newsletter_issue_id
+}
+
+/// Postmark batch response accepting every one of `n_messages` messages.
+pub fn batch_accepted_response(n_messages: usize) -> ResponseTemplate {
+    let results: Vec<_> = (0..n_messages)
+        .map(|_| serde_json::json!({"ErrorCode": 0, "Message": "OK"}))
+        .collect();
+
+    ResponseTemplate::new(200).set_body_json(results)
+}
+
 pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
     assert_eq!(response.status().as_u16(), 303);
     assert_eq!(response.headers().get("Location").unwrap(), location);
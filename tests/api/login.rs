@@ -1,4 +1,6 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
 
 #[tokio::test]
 async fn an_error_flash_message_is_set_on_failure() {
@@ -36,3 +38,116 @@ async fn redirect_to_admin_dashboard_after_login_success() {
 
     assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
 }
+
+async fn fail_login(app: &TestApp, username: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": username,
+        "password": "random-password"
+    }))
+    .await
+}
+
+async fn expire_lockouts(app: &TestApp) {
+    sqlx::query!("UPDATE login_throttles SET locked_until = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn a_username_is_locked_after_too_many_failures() {
+    let app = spawn_app().await;
+
+    for _ in 0..4 {
+        fail_login(&app, &app.test_user.username).await;
+    }
+    let response = fail_login(&app, &app.test_user.username).await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts. Please try again in 1 minute."));
+
+    // Even the right password is refused while the lockout lasts.
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+    assert!(
+        app.get_login_html()
+            .await
+            .contains("Too many failed login attempts")
+    );
+}
+
+#[tokio::test]
+async fn the_lockout_doubles_with_every_further_failure() {
+    let app = spawn_app().await;
+    for _ in 0..5 {
+        fail_login(&app, &app.test_user.username).await;
+    }
+    expire_lockouts(&app).await;
+
+    fail_login(&app, &app.test_user.username).await;
+
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Please try again in 2 minutes."));
+}
+
+#[tokio::test]
+async fn a_user_can_log_in_once_the_lockout_expired() {
+    let app = spawn_app().await;
+    for _ in 0..5 {
+        fail_login(&app, &app.test_user.username).await;
+    }
+    expire_lockouts(&app).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn a_successful_login_resets_the_failures_of_the_username() {
+    let app = spawn_app().await;
+    for _ in 0..4 {
+        fail_login(&app, &app.test_user.username).await;
+    }
+    app.login_user().await;
+    app.post_logout().await;
+
+    for _ in 0..4 {
+        fail_login(&app, &app.test_user.username).await;
+    }
+
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Authentication failed"));
+    assert!(!html_page.contains("Too many failed login attempts"));
+}
+
+#[tokio::test]
+async fn a_client_is_locked_after_failing_with_too_many_usernames() {
+    let app = spawn_app().await;
+    for _ in 0..20 {
+        fail_login(&app, &Uuid::new_v4().to_string()).await;
+    }
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+    assert!(
+        app.get_login_html()
+            .await
+            .contains("Too many failed login attempts")
+    );
+}
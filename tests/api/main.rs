@@ -1,6 +1,17 @@
 mod admin;
+mod api_issues;
+mod api_subscribers;
+mod api_tokens;
+mod click_tracking;
+mod data_requests;
 mod health_check;
 mod helpers;
 mod login;
+mod open_tracking;
+mod openapi;
+mod password_reset;
+mod setup;
 mod subscriptions;
 mod subscriptions_confirm;
+mod two_factor;
+mod users;
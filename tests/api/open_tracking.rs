@@ -0,0 +1,244 @@
+use newsletter_backend::tracking::{TrackedLink, TrackedOpen, tracked_links};
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, create_confirmed_subscriber, spawn_app, spawn_app_with};
+
+const ISSUE_HTML: &str =
+    r#"<html><body><p>Read <a href="https://example.com/a">this</a>.</p></body></html>"#;
+
+fn issue_form() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": ISSUE_HTML,
+        "idempotency_key": Uuid::new_v4().to_string(),
+    })
+}
+
+fn open_pixel_url(html: &str) -> Option<&str> {
+    let start = html.find(r#"<img src=""#)? + r#"<img src=""#.len();
+    let end = start + html[start..].find('"')?;
+    Some(&html[start..end])
+}
+
+async fn spawn_app_tracking_opens() -> TestApp {
+    let app = spawn_app_with(|c| c.application.track_opens = true).await;
+    app.login_user().await;
+    app
+}
+
+async fn issue_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id
+}
+
+async fn subscriber_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id
+}
+
+async fn n_opens(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT count(*) AS "count!" FROM email_opens"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+}
+
+/// Signs up and confirms a subscriber who asked not to be tracked.
+async fn create_untracked_subscriber(app: &TestApp) {
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.post_subscription("name=diego&email=diego20@gmail.com&do_not_track=true".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.plain_text)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn the_first_and_later_opens_of_an_issue_are_recorded() {
+    let app = spawn_app_tracking_opens().await;
+    create_confirmed_subscriber(&app).await;
+
+    let html_body = app.deliver_issue(issue_form()).await;
+
+    let url = open_pixel_url(&html_body).unwrap();
+    assert!(url.starts_with(&format!("{}/t/o/", app.address)));
+    assert!(html_body.ends_with(r#"style="border:0"></body></html>"#));
+
+    let response = app.get_tracking_url(url).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("Content-Type").unwrap(), "image/gif");
+    assert_eq!(response.headers().get("Cache-Control").unwrap(), "no-store");
+    let first = sqlx::query!("SELECT first_opened_at, last_opened_at, n_opens FROM email_opens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(first.n_opens, 1);
+    assert_eq!(first.first_opened_at, first.last_opened_at);
+
+    app.get_tracking_url(url).await;
+    let open = sqlx::query!(
+        "SELECT newsletter_issue_id, subscriber_id, first_opened_at, last_opened_at, n_opens
+        FROM email_opens"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(open.newsletter_issue_id, issue_id(&app).await);
+    assert_eq!(open.subscriber_id, subscriber_id(&app).await);
+    assert_eq!(open.n_opens, 2);
+    assert_eq!(open.first_opened_at, first.first_opened_at);
+    assert!(open.last_opened_at > first.last_opened_at);
+
+    let html_page = app.get_issue_stats_html(issue_id(&app).await).await;
+    assert!(html_page.contains("Opened by 1 subscriber(s), 2 open(s)"));
+}
+
+#[tokio::test]
+async fn issues_can_opt_out_of_open_tracking() {
+    let app = spawn_app_tracking_opens().await;
+    create_confirmed_subscriber(&app).await;
+    let mut form = issue_form();
+    form["no_open_tracking"] = "true".into();
+    form["no_click_tracking"] = "true".into();
+
+    let html_body = app.deliver_issue(form).await;
+
+    assert_eq!(html_body, ISSUE_HTML);
+    let html_page = app.get_issue_stats_html(issue_id(&app).await).await;
+    assert!(html_page.contains("Opens are not tracked for this issue."));
+}
+
+#[tokio::test]
+async fn opens_are_not_tracked_unless_enabled_in_the_configuration() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    create_confirmed_subscriber(&app).await;
+
+    let html_body = app.deliver_issue(issue_form()).await;
+    assert_eq!(open_pixel_url(&html_body), None);
+
+    // Images of issues sent while it was enabled no longer record anything.
+    let url = app.tracker.open_url(&TrackedOpen {
+        newsletter_issue_id: issue_id(&app).await,
+        subscriber_id: subscriber_id(&app).await,
+    });
+    let response = app.get_tracking_url(&url).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(n_opens(&app).await, 0);
+}
+
+#[tokio::test]
+async fn forged_open_tokens_are_rejected() {
+    let app = spawn_app_tracking_opens().await;
+    create_confirmed_subscriber(&app).await;
+    let click_url = app.tracker.click_url(&TrackedLink {
+        newsletter_issue_id: Uuid::new_v4(),
+        subscriber_id: subscriber_id(&app).await,
+        url: "https://example.com/a".into(),
+    });
+
+    for url in [
+        format!("{}/t/o/not-a-token", app.address),
+        click_url.replace("/t/c/", "/t/o/"),
+    ] {
+        let response = app.get_tracking_url(&url).await;
+        assert_eq!(response.status().as_u16(), 404, "{}", url);
+    }
+}
+
+#[tokio::test]
+async fn subscribers_who_asked_not_to_be_tracked_get_untracked_issues() {
+    let app = spawn_app_tracking_opens().await;
+    create_untracked_subscriber(&app).await;
+
+    let html_body = app.deliver_issue(issue_form()).await;
+
+    assert_eq!(html_body, ISSUE_HTML);
+    let response = app.get_subscriber_details(subscriber_id(&app).await).await;
+    assert!(response.text().await.unwrap().contains("Tracking: refused"));
+}
+
+#[tokio::test]
+async fn opting_out_of_tracking_applies_to_issues_already_sent() {
+    let app = spawn_app_tracking_opens().await;
+    create_confirmed_subscriber(&app).await;
+    let html_body = app.deliver_issue(issue_form()).await;
+    let token = app.create_api_token(&["subscribers:write"]).await;
+
+    let response = app
+        .api_request(
+            reqwest::Method::PATCH,
+            &format!("/subscribers/{}", subscriber_id(&app).await),
+            Some(&token),
+        )
+        .json(&serde_json::json!({"do_not_track": true}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.get_tracking_url(open_pixel_url(&html_body).unwrap())
+        .await;
+    let link = tracked_links(&html_body).into_iter().next().unwrap();
+    let response = app.get_tracking_url(&link).await;
+    assert_eq!(response.status().as_u16(), 302);
+
+    assert_eq!(n_opens(&app).await, 0);
+    let n_clicks = sqlx::query!(r#"SELECT count(*) AS "count!" FROM link_clicks"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_clicks, 0);
+}
+
+#[tokio::test]
+async fn opens_are_part_of_the_personal_data_of_the_subscriber() {
+    let app = spawn_app_tracking_opens().await;
+    create_confirmed_subscriber(&app).await;
+    let html_body = app.deliver_issue(issue_form()).await;
+    app.get_tracking_url(open_pixel_url(&html_body).unwrap())
+        .await;
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_personal_data_action("export", "diego20@gmail.com")
+        .await;
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["subscription"]["do_not_track"], false);
+    assert_eq!(
+        data["email_opens"][0]["newsletter_issue_id"],
+        issue_id(&app).await.to_string()
+    );
+    assert_eq!(data["email_opens"][0]["n_opens"], 1);
+
+    app.post_personal_data_action("erase", "diego20@gmail.com")
+        .await;
+    assert_eq!(n_opens(&app).await, 0);
+}
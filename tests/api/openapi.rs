@@ -0,0 +1,51 @@
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn the_openapi_document_describes_the_json_api() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(format!("{}/api/openapi.json", &app.address))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let document: serde_json::Value = response.json().await.unwrap();
+    assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+
+    let paths = &document["paths"];
+    assert!(paths["/subscriptions"]["post"].is_object());
+    assert!(paths["/api/v1/issues"]["post"].is_object());
+    assert!(paths["/api/v1/issues/{issue_id}/publish"]["post"].is_object());
+    assert!(paths["/api/v1/subscribers/{subscriber_id}"]["delete"].is_object());
+
+    let create_issue = &paths["/api/v1/issues"]["post"];
+    assert!(create_issue["security"].is_array());
+    assert!(create_issue["responses"]["401"].is_object());
+    assert!(
+        create_issue["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|p| p["name"] == "Idempotency-Key" && p["in"] == "header")
+    );
+    assert!(document["components"]["schemas"]["NewIssue"].is_object());
+}
+
+#[cfg(feature = "openapi-viewer")]
+#[tokio::test]
+async fn the_openapi_viewer_is_served() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(format!("{}/api/docs", &app.address))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("Newsletter backend")
+    );
+}
@@ -0,0 +1,199 @@
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+const SENT_MESSAGE: &str =
+    "If this account has an email address, a link to reset its password has been sent to it.";
+
+async fn set_test_user_email(app: &TestApp) {
+    sqlx::query!(
+        "UPDATE users SET email = 'admin@example.com' WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+/// Asks for a reset link for the test user and returns the token sent to them.
+async fn request_reset_token(app: &TestApp) -> String {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_forgot_password(&app.test_user.username).await;
+    assert_is_redirect_to(&response, "/login");
+
+    let email_request = &app.wait_for_emails(1).await[0];
+    app.get_confirmation_links(email_request)
+        .html
+        .query_pairs()
+        .find(|(key, _)| key == "reset_token")
+        .unwrap()
+        .1
+        .into_owned()
+}
+
+fn new_password(token: &str, password: &str) -> serde_json::Value {
+    serde_json::json!({
+        "reset_token": token,
+        "new_password": password,
+        "new_password_check": password,
+    })
+}
+
+#[tokio::test]
+async fn the_login_form_links_to_the_password_reset() {
+    let app = spawn_app().await;
+
+    let html_page = app.get_login_html().await;
+
+    assert!(html_page.contains(r#"<a href="/forgot-password">"#));
+}
+
+#[tokio::test]
+async fn the_response_does_not_reveal_whether_a_username_exists() {
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    for username in [app.test_user.username.clone(), Uuid::new_v4().to_string()] {
+        let response = app.post_forgot_password(&username).await;
+        assert_is_redirect_to(&response, "/login");
+        assert!(app.get_login_html().await.contains(SENT_MESSAGE));
+    }
+
+    app.wait_for_emails(1).await;
+}
+
+#[tokio::test]
+async fn no_email_is_sent_to_users_without_an_email_address() {
+    let app = spawn_app().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_forgot_password(&app.test_user.username).await;
+
+    assert_is_redirect_to(&response, "/login");
+    assert!(app.get_login_html().await.contains(SENT_MESSAGE));
+}
+
+#[tokio::test]
+async fn password_reset_requests_are_throttled() {
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(5)
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..5 {
+        let response = app.post_forgot_password(&app.test_user.username).await;
+        assert_is_redirect_to(&response, "/login");
+        assert!(app.get_login_html().await.contains(SENT_MESSAGE));
+    }
+    let response = app.post_forgot_password(&app.test_user.username).await;
+
+    assert_is_redirect_to(&response, "/login");
+    assert!(
+        app.get_login_html()
+            .await
+            .contains("Too many password reset requests. Please try again later.")
+    );
+    app.wait_for_emails(5).await;
+}
+
+#[tokio::test]
+async fn a_reset_link_lets_the_user_choose_a_new_password() {
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    let token = request_reset_token(&app).await;
+    let password = Uuid::new_v4().to_string();
+
+    assert_eq!(app.get_reset_password(&token).await.status().as_u16(), 200);
+    let response = app
+        .post_reset_password(&new_password(&token, &password))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn a_reset_link_can_only_be_used_once() {
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    let token = request_reset_token(&app).await;
+
+    let response = app
+        .post_reset_password(&new_password(&token, &Uuid::new_v4().to_string()))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app
+        .post_reset_password(&new_password(&token, &Uuid::new_v4().to_string()))
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn an_expired_reset_link_is_rejected() {
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    let token = request_reset_token(&app).await;
+
+    sqlx::query!("UPDATE password_reset_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(app.get_reset_password(&token).await.status().as_u16(), 404);
+    let response = app
+        .post_reset_password(&new_password(&token, &Uuid::new_v4().to_string()))
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn the_new_password_must_be_valid() {
+    let app = spawn_app().await;
+    set_test_user_email(&app).await;
+    let token = request_reset_token(&app).await;
+
+    let response = app
+        .post_reset_password(&new_password(&token, "too short"))
+        .await;
+
+    assert_is_redirect_to(&response, &format!("/reset-password?reset_token={}", token));
+    let html_page = app.get_reset_password(&token).await.text().await.unwrap();
+    assert!(html_page.contains("The new password must be longer than 12 characters"));
+}
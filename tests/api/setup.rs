@@ -0,0 +1,115 @@
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn remove_all_users(app: &TestApp) {
+    sqlx::query!("DELETE FROM users")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn setup_is_not_available_once_a_user_exists() {
+    let app = spawn_app().await;
+
+    let response = app.get_setup().await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let password = Uuid::new_v4().to_string();
+    let response = app
+        .post_setup(&serde_json::json!({
+            "username": "intruder",
+            "password": &password,
+            "password_check": &password,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn the_first_admin_can_be_created_on_a_fresh_install() {
+    let app = spawn_app().await;
+    remove_all_users(&app).await;
+
+    let response = app.get_setup().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let password = Uuid::new_v4().to_string();
+    let response = app
+        .post_setup(&serde_json::json!({
+            "username": "owner",
+            "password": &password,
+            "password_check": &password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Your admin account has been created"));
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": "owner",
+            "password": &password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let response = app.get_setup().await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn setup_rejects_invalid_passwords() {
+    let app = spawn_app().await;
+    remove_all_users(&app).await;
+
+    let test_cases = [
+        (
+            "too-short",
+            "too-short",
+            "must be longer than 12 characters",
+        ),
+        (
+            "a-long-enough-password",
+            "another-long-enough-password",
+            "two different passwords",
+        ),
+    ];
+
+    for (password, password_check, error_message) in test_cases {
+        let response = app
+            .post_setup(&serde_json::json!({
+                "username": "owner",
+                "password": password,
+                "password_check": password_check,
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/setup");
+
+        let html_page = app.get_setup().await.text().await.unwrap();
+        assert!(html_page.contains(error_message));
+    }
+}
+
+#[tokio::test]
+async fn setup_rejects_invalid_emails_without_echoing_them() {
+    let app = spawn_app().await;
+    remove_all_users(&app).await;
+
+    let password = Uuid::new_v4().to_string();
+    let response = app
+        .post_setup(&serde_json::json!({
+            "username": "owner",
+            "email": "<script>alert(1)</script>",
+            "password": &password,
+            "password_check": &password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/setup");
+
+    let html_page = app.get_setup().await.text().await.unwrap();
+    assert!(html_page.contains("Please enter a valid email address."));
+    assert!(!html_page.contains("<script>"));
+}
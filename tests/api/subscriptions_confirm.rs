@@ -69,3 +69,33 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     assert_eq!(query.name, "diego");
     assert_eq!(query.status, "confirmed");
 }
+
+#[tokio::test]
+async fn following_the_confirmation_link_again_records_a_single_confirmation() {
+    let app = spawn_app().await;
+    let body = "name=diego&email=diego20@gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscription(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+
+    for _ in 0..2 {
+        let response = reqwest::get(confirmation_links.html.clone()).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let n_confirmations = sqlx::query!(
+        r#"SELECT count(*) AS "count!" FROM consent_events WHERE event = 'confirmation'"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count;
+    assert_eq!(n_confirmations, 1);
+}
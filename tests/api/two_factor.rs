@@ -0,0 +1,232 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+/// Authenticator app paired with the test user.
+struct Authenticator(TOTP);
+
+impl Authenticator {
+    /// Code shown `steps` periods of 30 seconds from now.
+    fn code(&self, steps: i64) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        self.0.generate((now + steps * 30) as u64)
+    }
+}
+
+/// Logs in, enables two-factor authentication and logs out again.
+async fn enroll_test_user(app: &TestApp) -> (Authenticator, Vec<String>) {
+    app.login_user().await;
+
+    let html_page = app.get_two_factor_settings_html().await;
+    let secret = html_page
+        .split("<code>")
+        .nth(1)
+        .and_then(|s| s.split("</code>").next())
+        .unwrap()
+        .to_string();
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        0,
+        30,
+        Secret::Encoded(secret).to_bytes().unwrap(),
+        None,
+        "".into(),
+    )
+    .unwrap();
+    let authenticator = Authenticator(totp);
+
+    let response = app.post_two_factor_enrollment(&authenticator.code(0)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let recovery_codes = response
+        .text()
+        .await
+        .unwrap()
+        .split("<li><code>")
+        .skip(1)
+        .map(|s| s.split("</code>").next().unwrap().to_string())
+        .collect();
+
+    app.post_logout().await;
+
+    (authenticator, recovery_codes)
+}
+
+#[tokio::test]
+async fn enrollment_shows_a_qr_code_and_recovery_codes() {
+    let app = spawn_app().await;
+    app.login_user().await;
+
+    let html_page = app.get_two_factor_settings_html().await;
+    assert!(html_page.contains("<svg"));
+
+    let (_, recovery_codes) = enroll_test_user(&app).await;
+    assert_eq!(recovery_codes.len(), 10);
+}
+
+#[tokio::test]
+async fn enrollment_requires_a_valid_code() {
+    let app = spawn_app().await;
+    app.login_user().await;
+    app.get_two_factor_settings_html().await;
+
+    let response = app.post_two_factor_enrollment("000000").await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+
+    let html_page = app.get_two_factor_settings_html().await;
+    assert!(html_page.contains("The authentication code is invalid."));
+    assert!(!html_page.contains("Two-factor authentication is enabled."));
+}
+
+#[tokio::test]
+async fn a_password_alone_does_not_log_in_enrolled_users() {
+    let app = spawn_app().await;
+    enroll_test_user(&app).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_valid_code_completes_the_login() {
+    let app = spawn_app().await;
+    let (authenticator, _) = enroll_test_user(&app).await;
+    app.login_user().await;
+
+    let response = app.post_second_factor(&authenticator.code(1)).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn a_code_cannot_be_used_twice() {
+    let app = spawn_app().await;
+    let (authenticator, _) = enroll_test_user(&app).await;
+    let code = authenticator.code(1);
+
+    app.login_user().await;
+    app.post_second_factor(&code).await;
+    app.post_logout().await;
+
+    app.login_user().await;
+    let response = app.post_second_factor(&code).await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn a_recovery_code_can_be_used_once() {
+    let app = spawn_app().await;
+    let (_, recovery_codes) = enroll_test_user(&app).await;
+
+    app.login_user().await;
+    let response = app.post_second_factor(&recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    app.post_logout().await;
+
+    app.login_user().await;
+    let response = app.post_second_factor(&recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn too_many_invalid_codes_require_logging_in_again() {
+    let app = spawn_app().await;
+    let (authenticator, _) = enroll_test_user(&app).await;
+    app.login_user().await;
+
+    for _ in 0..4 {
+        let response = app.post_second_factor("000000").await;
+        assert_is_redirect_to(&response, "/login/two-factor");
+    }
+    let response = app.post_second_factor("000000").await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app.post_second_factor(&authenticator.code(1)).await;
+    assert_is_redirect_to(&response, "/login");
+    assert_is_redirect_to(&app.get_second_factor().await, "/login");
+}
+
+#[tokio::test]
+async fn two_factor_authentication_can_be_disabled() {
+    let app = spawn_app().await;
+    let (_, recovery_codes) = enroll_test_user(&app).await;
+    app.login_user().await;
+    app.post_second_factor(&recovery_codes[0]).await;
+
+    let response = app.post_disable_two_factor(&recovery_codes[1]).await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    assert!(
+        app.get_two_factor_settings_html()
+            .await
+            .contains("Two-factor authentication has been disabled.")
+    );
+    app.post_logout().await;
+
+    app.login_user().await;
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn a_password_alone_does_not_forget_failed_logins() {
+    let app = spawn_app().await;
+    enroll_test_user(&app).await;
+    let wrong_password = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": "random-password"
+    });
+
+    for _ in 0..4 {
+        app.post_login(&wrong_password).await;
+    }
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+
+    // Without a second factor, the right password did not reset the counter.
+    app.post_login(&wrong_password).await;
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts"));
+}
+
+#[tokio::test]
+async fn invalid_codes_across_logins_lock_the_account() {
+    let app = spawn_app().await;
+    let (authenticator, _) = enroll_test_user(&app).await;
+
+    for _ in 0..2 {
+        app.login_user().await;
+        for _ in 0..2 {
+            let response = app.post_second_factor("000000").await;
+            assert_is_redirect_to(&response, "/login/two-factor");
+        }
+    }
+    app.login_user().await;
+    let response = app.post_second_factor("000000").await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts"));
+
+    // The lockout applies to the password as well as to the second factor.
+    app.login_user().await;
+    let response = app.post_second_factor(&authenticator.code(1)).await;
+    assert_is_redirect_to(&response, "/login");
+}
@@ -0,0 +1,56 @@
+use claims::assert_ok;
+use newsletter_backend::authentication::{CreateUserError, Role, create_user, get_user_id};
+use newsletter_backend::domain::AdminPassword;
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+fn password(password: &str) -> AdminPassword {
+    AdminPassword::new(password.into()).unwrap()
+}
+
+#[tokio::test]
+async fn a_created_user_can_log_in() {
+    let app = spawn_app().await;
+    let username = Uuid::new_v4().to_string();
+    let new_password = Uuid::new_v4().to_string();
+
+    let user_id = assert_ok!(
+        create_user(
+            &username,
+            None,
+            password(&new_password),
+            Role::Editor,
+            &app.db_pool
+        )
+        .await
+    );
+    assert_eq!(
+        get_user_id(&username, &app.db_pool).await.unwrap(),
+        Some(user_id)
+    );
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": username,
+            "password": new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn usernames_must_be_unique() {
+    let app = spawn_app().await;
+
+    let result = create_user(
+        &app.test_user.username,
+        None,
+        password(&Uuid::new_v4().to_string()),
+        Role::Owner,
+        &app.db_pool,
+    )
+    .await;
+
+    assert!(matches!(result, Err(CreateUserError::UsernameTaken(_))));
+}